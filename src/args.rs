@@ -0,0 +1,401 @@
+// Command-line option parsing shared by the hexdump, xxd and od personalities
+
+use std::fmt; // Display formatting
+
+// Errors found while parsing the command line, naming the offending argument
+#[derive(Debug, PartialEq)]
+pub enum ArgError {
+    UnknownOption(String),   // An option that doesn't exist, as given
+    AmbiguousOption(String), // A long option prefix shared by several options
+    MissingValue(String),    // An option given without its value
+    UnexpectedValue(String), // A flag given a value, as in '--reverse=yes'
+    InvalidValue {
+        option: String,         // The option, as given
+        value: String,          // The value that was rejected
+        expected: &'static str, // What the option accepts
+    },
+    InvalidFormat(String), // A format or type string that can't be parsed or read
+    InvalidPattern(String), // A search pattern that can't be parsed
+    Conflict(String, String), // Two options asking for different things, as given
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgError::UnknownOption(arg) => write!(f, "unrecognized option '{}'", arg),
+            ArgError::AmbiguousOption(arg) => write!(f, "option '{}' is ambiguous", arg),
+            ArgError::MissingValue(arg) => write!(f, "option '{}' requires a value", arg),
+            ArgError::UnexpectedValue(arg) => write!(f, "option '{}' doesn't allow a value", arg),
+            ArgError::InvalidValue {
+                option,
+                value,
+                expected,
+            } => write!(
+                f,
+                "invalid value '{}' for '{}': expected {}",
+                value, option, expected
+            ),
+            ArgError::InvalidFormat(message) => write!(f, "invalid format: {}", message),
+            ArgError::InvalidPattern(message) => write!(f, "invalid pattern: {}", message),
+            ArgError::Conflict(option, other) => {
+                write!(f, "option '{}' can't be used with '{}'", option, other)
+            }
+        }
+    }
+}
+
+// Whether an option takes a value, and the value's name in the help text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    None,
+    Required(&'static str),
+    Optional(&'static str), // Only taken when attached, as in '-w32' or '--width=32'
+}
+
+// One option a personality accepts
+#[derive(Debug, PartialEq)]
+pub struct Opt {
+    pub short: Option<char>,
+    pub long: Option<&'static str>,
+    pub value: Value,
+    pub help: &'static str,
+}
+
+// An option found on the command line
+#[derive(Debug, PartialEq)]
+pub struct Parsed<'a> {
+    pub opt: &'a Opt,
+    pub name: String,          // The option as given, like '-n' or '--length'
+    pub value: Option<String>, // Its value, if it takes one
+}
+
+impl Parsed<'_> {
+    // The option's value, empty for flags
+    pub fn value(&self) -> &str {
+        self.value.as_deref().unwrap_or_default()
+    }
+
+    // Error for a value the option can't use
+    pub fn invalid(&self, expected: &'static str) -> ArgError {
+        ArgError::InvalidValue {
+            option: self.name.clone(),
+            value: self.value().to_string(),
+            expected,
+        }
+    }
+}
+
+// Function to split the command line into options, in the order given, and operands.
+// Short flags can be clustered ('-vC') and take values attached ('-n10') or
+// separately ('-n 10'); long options take them after '=' or separately.
+// Options and operands can be mixed, and '--' ends the options. With
+// 'long_only', long options can also be written with a single dash ('-cols 8'),
+// as xxd does.
+pub fn parse<'a>(
+    opts: &'a [Opt],
+    long_only: bool,
+    args: &[String],
+) -> Result<(Vec<Parsed<'a>>, Vec<String>), ArgError> {
+    let mut parsed = Vec::new();
+    let mut operands = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            operands.extend(args.by_ref().cloned()); // Everything after '--' is an operand
+            break;
+        }
+
+        let long = match arg.strip_prefix("--") {
+            Some(body) => Some(body),
+            // A single-dash long option needs more than one letter, so '-c' stays short
+            None if long_only && arg.len() > 2 && arg.starts_with('-') => {
+                let body = &arg[1..];
+                let name = body.split('=').next().unwrap_or_default();
+                find_long(opts, name).ok().flatten().map(|_| body)
+            }
+            None => None,
+        };
+
+        if let Some(body) = long {
+            let (name, attached) = match body.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (body, None),
+            };
+            let dashes = &arg[..arg.len() - body.len()];
+            let given = format!("{}{}", dashes, name);
+            let opt = find_long(opts, name)
+                .map_err(|_| ArgError::AmbiguousOption(given.clone()))?
+                .ok_or_else(|| ArgError::UnknownOption(given.clone()))?;
+            let value = match (opt.value, attached) {
+                (Value::None, Some(_)) => return Err(ArgError::UnexpectedValue(given)),
+                (Value::Required(_), None) => Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| ArgError::MissingValue(given.clone()))?,
+                ),
+                (_, attached) => attached,
+            };
+            parsed.push(Parsed {
+                opt,
+                name: given,
+                value,
+            });
+        } else if arg.len() > 1 && arg.starts_with('-') {
+            // Cluster of short options, the last of which may take a value
+            let mut rest = &arg[1..];
+            while let Some(c) = rest.chars().next() {
+                rest = &rest[c.len_utf8()..];
+                let given = format!("-{}", c);
+                let opt = opts
+                    .iter()
+                    .find(|opt| opt.short == Some(c))
+                    .ok_or_else(|| ArgError::UnknownOption(given.clone()))?;
+                let value = match opt.value {
+                    Value::None => None,
+                    Value::Required(_) if rest.is_empty() => Some(
+                        args.next()
+                            .cloned()
+                            .ok_or_else(|| ArgError::MissingValue(given.clone()))?,
+                    ),
+                    Value::Optional(_) if rest.is_empty() => None,
+                    _ => Some(std::mem::take(&mut rest).to_string()),
+                };
+                parsed.push(Parsed {
+                    opt,
+                    name: given,
+                    value,
+                });
+            }
+        } else {
+            operands.push(arg.clone()); // A FILE, or '-' for stdin
+        }
+    }
+
+    Ok((parsed, operands))
+}
+
+// Finds a long option by exact name or unique prefix; Err if the prefix is ambiguous
+fn find_long<'a>(opts: &'a [Opt], name: &str) -> Result<Option<&'a Opt>, ()> {
+    if let Some(opt) = opts.iter().find(|opt| opt.long == Some(name)) {
+        return Ok(Some(opt));
+    }
+    let mut matches = opts
+        .iter()
+        .filter(|opt| !name.is_empty() && opt.long.is_some_and(|long| long.starts_with(name)));
+    match (matches.next(), matches.next()) {
+        (Some(_), Some(_)) => Err(()),
+        (found, _) => Ok(found),
+    }
+}
+
+// Column the descriptions of '--help' start at, and the width they wrap at
+const HELP_COLUMN: usize = 30;
+const HELP_WIDTH: usize = 80;
+
+// Function to list the options for '--help', one per line with aligned descriptions
+// wrapped to 80 columns; names too long for their column get the description on the next line
+pub fn help(opts: &[Opt]) -> String {
+    let mut text = String::new();
+    for opt in opts {
+        let short = opt.short.map(|c| format!("-{}", c));
+        let long = opt.long.map(|long| match opt.value {
+            Value::None => format!("--{}", long),
+            Value::Required(name) => format!("--{}={}", long, name),
+            Value::Optional(name) => format!("--{}[={}]", long, name),
+        });
+        let names = match (short, long) {
+            (Some(short), Some(long)) => format!("{}, {}", short, long),
+            (Some(short), None) => match opt.value {
+                Value::None => short,
+                Value::Required(name) => format!("{} {}", short, name),
+                Value::Optional(name) => format!("{}[{}]", short, name),
+            },
+            (None, Some(long)) => format!("    {}", long),
+            (None, None) => continue,
+        };
+        let mut line = format!("  {}", names);
+        if line.len() + 2 > HELP_COLUMN {
+            text.push_str(&line);
+            text.push('\n');
+            line.clear();
+        }
+        for part in wrap(opt.help, HELP_WIDTH - HELP_COLUMN) {
+            text.push_str(&format!("{:<width$}{}\n", line, part, width = HELP_COLUMN));
+            line.clear();
+        }
+    }
+    text
+}
+
+// Function to split text into lines of at most 'width' characters at blanks,
+// leaving longer words on lines of their own
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTS: [Opt; 4] = [
+        Opt {
+            short: Some('v'),
+            long: Some("verbose"),
+            value: Value::None,
+            help: "show everything",
+        },
+        Opt {
+            short: Some('n'),
+            long: Some("length"),
+            value: Value::Required("LEN"),
+            help: "stop after LEN bytes",
+        },
+        Opt {
+            short: Some('w'),
+            long: Some("width"),
+            value: Value::Optional("BYTES"),
+            help: "bytes per line",
+        },
+        Opt {
+            short: None,
+            long: Some("level"),
+            value: Value::None,
+            help: "level",
+        },
+    ];
+
+    // Options as (name, value) pairs, and operands
+    type Outcome = Result<(Vec<(String, String)>, Vec<String>), ArgError>;
+
+    // Helper to parse a command line into (name, value) pairs and operands
+    fn run(args: &[&str], long_only: bool) -> Outcome {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (parsed, operands) = parse(&OPTS, long_only, &args)?;
+        let parsed = parsed
+            .iter()
+            .map(|p| (p.name.clone(), p.value().to_string()))
+            .collect();
+        Ok((parsed, operands))
+    }
+
+    // Helper to build the expected (name, value) pairs
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_short_forms() {
+        // Clustered flags, attached and separate values, operands anywhere
+        let (parsed, operands) =
+            run(&["file", "-vn10", "-w", "-n", "5", "-w8", "-"], false).unwrap();
+        assert_eq!(
+            parsed,
+            pairs(&[
+                ("-v", ""),
+                ("-n", "10"),
+                ("-w", ""),
+                ("-n", "5"),
+                ("-w", "8")
+            ])
+        );
+        assert_eq!(operands, vec!["file", "-"]);
+    }
+
+    #[test]
+    fn test_long_forms() {
+        // '=' and separate values, unique prefixes, and '--' ending the options
+        let (parsed, operands) =
+            run(&["--length=10", "--len", "3", "--verb", "--", "-v"], false).unwrap();
+        assert_eq!(
+            parsed,
+            pairs(&[("--length", "10"), ("--len", "3"), ("--verb", "")])
+        );
+        assert_eq!(operands, vec!["-v"]);
+    }
+
+    #[test]
+    fn test_long_only() {
+        // xxd-style single-dash long options, with single letters kept short
+        let (parsed, _) = run(&["-length", "4", "-n2", "-v"], true).unwrap();
+        assert_eq!(parsed, pairs(&[("-length", "4"), ("-n", "2"), ("-v", "")]));
+    }
+
+    #[test]
+    fn test_errors() {
+        // Each error names the offending option
+        assert_eq!(
+            run(&["-q"], false),
+            Err(ArgError::UnknownOption("-q".into()))
+        );
+        assert_eq!(
+            run(&["--quiet"], false),
+            Err(ArgError::UnknownOption("--quiet".into()))
+        );
+        assert_eq!(
+            run(&["--le"], false),
+            Err(ArgError::AmbiguousOption("--le".into()))
+        );
+        assert_eq!(
+            run(&["-vn"], false),
+            Err(ArgError::MissingValue("-n".into()))
+        );
+        assert_eq!(
+            run(&["--length"], false),
+            Err(ArgError::MissingValue("--length".into()))
+        );
+        assert_eq!(
+            run(&["--verbose=1"], false),
+            Err(ArgError::UnexpectedValue("--verbose".into()))
+        );
+    }
+
+    #[test]
+    fn test_help() {
+        // Options are listed with their value names
+        let text = help(&OPTS);
+        assert!(text.contains("  -n, --length=LEN            stop after LEN bytes\n"));
+        assert!(text.contains("  -w, --width[=BYTES]         bytes per line\n"));
+        assert!(text.contains("      --level                 level\n"));
+    }
+
+    #[test]
+    fn test_help_wrapping() {
+        // Long descriptions wrap under their column, and long names push them to the next line
+        let opts = [
+            Opt {
+                short: None,
+                long: Some("entropy-column"),
+                value: Value::Optional("BLOCK"),
+                help: "show the entropy",
+            },
+            Opt {
+                short: Some('x'),
+                long: None,
+                value: Value::None,
+                help: "a description long enough that it can't fit in the fifty columns left",
+            },
+        ];
+        let expected = "      --entropy-column[=BLOCK]
+                              show the entropy
+  -x                          a description long enough that it can't fit in the
+                              fifty columns left
+";
+        assert_eq!(help(&opts), expected);
+    }
+}
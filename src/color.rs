@@ -0,0 +1,405 @@
+//! ANSI colors for dumps, highlighting bytes by class.
+//!
+//! Every value shown is colored by the class of its bytes: NUL, printable
+//! ASCII, ASCII whitespace, other control bytes, `0xff`, or other non-ASCII
+//! bytes. A value made of bytes of different classes is left uncolored.
+//! Offsets get a color of their own.
+
+use std::borrow::Cow; // Colors combined with underlining
+use std::ops::Range; // Marked byte ranges
+
+use crate::html::Annotation; // Notes on byte ranges in HTML reports
+
+/// Environment variable holding a theme, e.g. `nul=90:print=36:ff=1;31`.
+pub const THEME_VAR: &str = "HEXDUMP_COLORS";
+
+/// SGR parameters (the part between `ESC [` and `m`) for each class of
+/// byte. An empty string leaves that class uncolored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Offsets (`offset`).
+    pub offset: String,
+    /// `0x00` (`nul`).
+    pub nul: String,
+    /// Printable ASCII other than space (`print`).
+    pub printable: String,
+    /// Space, tab, newline, vertical tab, form feed and carriage return (`space`).
+    pub whitespace: String,
+    /// Other ASCII control bytes and DEL (`ctrl`).
+    pub control: String,
+    /// `0xff` (`ff`).
+    pub ff: String,
+    /// Other bytes above `0x7f` (`high`).
+    pub high: String,
+    /// Bytes only in or changed in the first file of a diff (`del`).
+    pub deleted: String,
+    /// Bytes only in or changed in the second file of a diff (`ins`).
+    pub inserted: String,
+    /// Matches of `--find` (`match`).
+    pub matched: String,
+    /// Bytes of strings marked by `--mark-strings`, added to their other
+    /// colors (`string`).
+    pub string: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            offset: "2".to_string(),
+            nul: "90".to_string(),
+            printable: "36".to_string(),
+            whitespace: "32".to_string(),
+            control: "35".to_string(),
+            ff: "31".to_string(),
+            high: "33".to_string(),
+            deleted: "1;31".to_string(),
+            inserted: "1;32".to_string(),
+            matched: "30;43".to_string(),
+            string: "4".to_string(),
+        }
+    }
+}
+
+impl Theme {
+    /// The default theme with the colors given in `spec` replaced.
+    ///
+    /// `spec` is a `:`-separated list of `class=SGR` entries, in the style of
+    /// `GREP_COLORS`. Like `grep`, entries that can't be used are ignored.
+    ///
+    /// ```
+    /// use hexdump::color::Theme;
+    /// let theme = Theme::parse("nul=1;30:ff=:bogus=1:print=x");
+    /// assert_eq!(theme.nul, "1;30");
+    /// assert_eq!(theme.ff, "");
+    /// assert_eq!(theme.printable, Theme::default().printable);
+    /// ```
+    pub fn parse(spec: &str) -> Theme {
+        let mut theme = Theme::default();
+        for entry in spec.split(':') {
+            let Some((class, sgr)) = entry.split_once('=') else {
+                continue;
+            };
+            if !sgr.bytes().all(|b| b.is_ascii_digit() || b == b';') {
+                continue; // Not SGR parameters
+            }
+            let slot = match class {
+                "offset" => &mut theme.offset,
+                "nul" => &mut theme.nul,
+                "print" => &mut theme.printable,
+                "space" => &mut theme.whitespace,
+                "ctrl" => &mut theme.control,
+                "ff" => &mut theme.ff,
+                "high" => &mut theme.high,
+                "del" => &mut theme.deleted,
+                "ins" => &mut theme.inserted,
+                "match" => &mut theme.matched,
+                "string" => &mut theme.string,
+                _ => continue,
+            };
+            *slot = sgr.to_string();
+        }
+        theme
+    }
+
+    // Colors for a class of bytes
+    fn color(&self, class: Class) -> &str {
+        match class {
+            Class::Nul => &self.nul,
+            Class::Whitespace => &self.whitespace,
+            Class::Control => &self.control,
+            Class::Printable => &self.printable,
+            Class::Ff => &self.ff,
+            Class::High => &self.high,
+        }
+    }
+
+    // Colors for a value, when all its bytes share a class
+    pub(crate) fn for_bytes(&self, bytes: &[u8]) -> &str {
+        Class::shared(bytes).map_or("", |class| self.color(class))
+    }
+}
+
+// How a rendering shows its colors
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) enum Markup<'a> {
+    #[default]
+    Ansi, // Escapes from the theme
+    Html(&'a [Annotation]), // Elements classed by byte class, with these notes
+}
+
+// Colors for one rendering: the theme, overridden on marked byte ranges
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Palette<'a> {
+    pub(crate) theme: Option<&'a Theme>,
+    pub(crate) marks: &'a [(Range<u64>, &'a str)], // Later marks win over earlier ones
+    pub(crate) strings: &'a [Range<u64>],          // Bytes of strings, with 'theme.string' added
+    pub(crate) markup: Markup<'a>,
+}
+
+impl<'a> Palette<'a> {
+    // Whether the rendering is HTML rather than text
+    pub(crate) fn is_html(&self) -> bool {
+        matches!(self.markup, Markup::Html(_))
+    }
+
+    // Function to write the literal text of a format
+    pub(crate) fn text(&self, out: &mut Vec<u8>, text: &[u8]) {
+        match self.markup {
+            Markup::Ansi => out.extend_from_slice(text),
+            Markup::Html(_) => escape(out, text),
+        }
+    }
+
+    // Function to write the offset 'address', as shown by 'write'; in HTML it is an anchor
+    pub(crate) fn offset(&self, out: &mut Vec<u8>, address: u64, write: impl FnOnce(&mut Vec<u8>)) {
+        match self.markup {
+            Markup::Ansi => paint(out, self.theme.map_or("", |theme| &theme.offset), write),
+            Markup::Html(_) => {
+                let id = format!("0x{:x}", address);
+                let tag = format!("<a class=\"offset\" id=\"{}\" href=\"#{}\">", id, id);
+                out.extend_from_slice(tag.as_bytes());
+                escape_with(out, write);
+                out.extend_from_slice(b"</a>");
+            }
+        }
+    }
+
+    // Function to write the value made of 'bytes' read at 'address', as shown by 'write'
+    pub(crate) fn value(
+        &self,
+        out: &mut Vec<u8>,
+        address: u64,
+        bytes: &[u8],
+        write: impl FnOnce(&mut Vec<u8>),
+    ) {
+        let Markup::Html(annotations) = self.markup else {
+            return paint(out, &self.for_value(address, bytes), write);
+        };
+        let end = address + bytes.len().max(1) as u64;
+        let overlaps = |range: &Range<u64>| range.start < end && address < range.end;
+        let mut classes: Vec<&str> = Class::shared(bytes).map(Class::name).into_iter().collect();
+        if self.strings.iter().any(overlaps) {
+            classes.push("string");
+        }
+        let notes: Vec<&str> = annotations
+            .iter()
+            .filter(|annotation| overlaps(&annotation.range))
+            .map(|annotation| annotation.text.as_str())
+            .collect();
+        if !notes.is_empty() {
+            classes.push("note");
+        }
+
+        // Bytes are found again by their offsets to highlight every value showing them
+        let mut tag = format!(
+            "<span class=\"{}\" data-o=\"{}\"",
+            classes.join(" "),
+            address
+        );
+        if bytes.len() > 1 {
+            tag.push_str(&format!(" data-n=\"{}\"", bytes.len()));
+        }
+        out.extend_from_slice(tag.as_bytes());
+        if !notes.is_empty() {
+            out.extend_from_slice(b" title=\"");
+            escape(out, notes.join("\n").as_bytes());
+            out.push(b'"');
+        }
+        out.push(b'>');
+        escape_with(out, write);
+        out.extend_from_slice(b"</span>");
+    }
+
+    // Colors for a value made of 'bytes', read at 'address'
+    pub(crate) fn for_value(&self, address: u64, bytes: &[u8]) -> Cow<'a, str> {
+        let end = address + bytes.len().max(1) as u64;
+        let overlaps = |range: &Range<u64>| range.start < end && address < range.end;
+        let mark = self.marks.iter().rev().find(|(range, _)| overlaps(range));
+        let sgr = match (mark, self.theme) {
+            (Some((_, sgr)), _) => sgr,
+            (None, Some(theme)) => theme.for_bytes(bytes),
+            (None, None) => "",
+        };
+        match self.theme {
+            Some(theme) if !theme.string.is_empty() && self.strings.iter().any(overlaps) => {
+                if sgr.is_empty() {
+                    Cow::Borrowed(&theme.string)
+                } else {
+                    Cow::Owned(format!("{};{}", sgr, theme.string))
+                }
+            }
+            _ => Cow::Borrowed(sgr),
+        }
+    }
+}
+
+// Classes of bytes with a color of their own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Nul,
+    Whitespace,
+    Control,
+    Printable,
+    Ff,
+    High,
+}
+
+impl Class {
+    // Class of a single byte
+    fn of(b: u8) -> Class {
+        match b {
+            0x00 => Class::Nul,
+            b' ' | b'\t' | b'\n' | 0x0b | 0x0c | b'\r' => Class::Whitespace,
+            0x01..=0x1f | 0x7f => Class::Control,
+            0x21..=0x7e => Class::Printable,
+            0xff => Class::Ff,
+            _ => Class::High,
+        }
+    }
+
+    // Class of all of 'bytes', if they share one
+    fn shared(bytes: &[u8]) -> Option<Class> {
+        let mut classes = bytes.iter().map(|&b| Class::of(b));
+        let first = classes.next()?;
+        classes.all(|class| class == first).then_some(first)
+    }
+
+    // Name of the class, as in themes and HTML classes
+    fn name(self) -> &'static str {
+        match self {
+            Class::Nul => "nul",
+            Class::Whitespace => "space",
+            Class::Control => "ctrl",
+            Class::Printable => "print",
+            Class::Ff => "ff",
+            Class::High => "high",
+        }
+    }
+}
+
+// Function to wrap the output written by 'write' in the colors 'sgr'
+pub(crate) fn paint(out: &mut Vec<u8>, sgr: &str, write: impl FnOnce(&mut Vec<u8>)) {
+    if sgr.is_empty() {
+        return write(out);
+    }
+    out.extend_from_slice(b"\x1b[");
+    out.extend_from_slice(sgr.as_bytes());
+    out.push(b'm');
+    write(out);
+    out.extend_from_slice(b"\x1b[0m");
+}
+
+// Function to write 'text' as HTML: markup characters become entities, invalid UTF-8 a
+// replacement character and control characters other than tab and newline a '.'
+pub(crate) fn escape(out: &mut Vec<u8>, text: &[u8]) {
+    for c in String::from_utf8_lossy(text).chars() {
+        match c {
+            '&' => out.extend_from_slice(b"&amp;"),
+            '<' => out.extend_from_slice(b"&lt;"),
+            '>' => out.extend_from_slice(b"&gt;"),
+            '"' => out.extend_from_slice(b"&quot;"),
+            '\t' | '\n' => out.push(c as u8),
+            _ if c.is_control() => out.push(b'.'),
+            _ => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+}
+
+// Function to write the output written by 'write' as HTML
+fn escape_with(out: &mut Vec<u8>, write: impl FnOnce(&mut Vec<u8>)) {
+    let mut text = Vec::new();
+    write(&mut text);
+    escape(out, &text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_classes() {
+        // Each class gets its own color, and mixed values none
+        let theme = Theme::default();
+        assert_eq!(theme.for_bytes(&[0, 0]), "90");
+        assert_eq!(theme.for_bytes(b"Hi"), "36");
+        assert_eq!(theme.for_bytes(b" \n"), "32");
+        assert_eq!(theme.for_bytes(&[0x1b]), "35");
+        assert_eq!(theme.for_bytes(&[0xff, 0xff]), "31");
+        assert_eq!(theme.for_bytes(&[0x80]), "33");
+        assert_eq!(theme.for_bytes(&[b'A', 0]), "");
+    }
+
+    #[test]
+    fn test_palette_marks() {
+        // Marks override the theme on any value they overlap, the last one winning
+        let theme = Theme::default();
+        let marks = [(2..4, "7"), (3..4, "4")];
+        let palette = Palette {
+            theme: Some(&theme),
+            marks: &marks,
+            ..Default::default()
+        };
+        assert_eq!(palette.for_value(0, b"AB"), "36");
+        assert_eq!(palette.for_value(1, b"AB"), "7");
+        assert_eq!(palette.for_value(3, b"A"), "4");
+        assert_eq!(Palette::default().for_value(3, b"A"), "");
+    }
+
+    #[test]
+    fn test_palette_strings() {
+        // Bytes of strings are underlined on top of their other colors, only with a theme
+        let theme = Theme::default();
+        let marks = [(4..5, "7")];
+        let palette = Palette {
+            theme: Some(&theme),
+            marks: &marks,
+            strings: &[1..3, 3..5],
+            ..Default::default()
+        };
+        assert_eq!(palette.for_value(0, b"A"), "36");
+        assert_eq!(palette.for_value(0, b"AB"), "36;4");
+        assert_eq!(palette.for_value(2, &[b'A', 0]), "4");
+        assert_eq!(palette.for_value(4, b"A"), "7;4");
+        let plain = Palette {
+            strings: &[0..1, 4..5],
+            ..Default::default()
+        };
+        assert_eq!(plain.for_value(0, b"A"), "");
+    }
+
+    #[test]
+    fn test_palette_html() {
+        // HTML values are classed by their bytes, strings and notes, and text is escaped
+        let notes = [Annotation {
+            range: 1..2,
+            text: "a <b>".to_string(),
+        }];
+        let palette = Palette {
+            strings: &[0..1, 4..5],
+            markup: Markup::Html(&notes),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        palette.offset(&mut out, 0x1f40, |out| out.extend_from_slice(b"1f40"));
+        palette.text(&mut out, b" |");
+        palette.value(&mut out, 0, b"<", |out| out.push(b'<'));
+        palette.value(&mut out, 1, &[0, 0xff], |out| {
+            out.extend_from_slice(b"\xff")
+        });
+        let expected = concat!(
+            r##"<a class="offset" id="0x1f40" href="#0x1f40">1f40</a> |"##,
+            r#"<span class="print string" data-o="0">&lt;</span>"#,
+            "<span class=\"note\" data-o=\"1\" data-n=\"2\" title=\"a &lt;b&gt;\">\u{fffd}</span>",
+        );
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_paint() {
+        // Colors wrap the output, and empty colors add nothing
+        let mut out = Vec::new();
+        paint(&mut out, "1;31", |out| out.extend_from_slice(b"ff"));
+        paint(&mut out, "", |out| out.extend_from_slice(b"00"));
+        assert_eq!(out, b"\x1b[1;31mff\x1b[0m00");
+    }
+}
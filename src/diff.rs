@@ -0,0 +1,659 @@
+//! Byte-level comparison of two inputs, shown side by side.
+//!
+//! The inputs are aligned rather than compared offset by offset, so bytes
+//! inserted into or deleted from one of them don't make everything after
+//! them differ. Each run of differences is a [`Hunk`]. The side-by-side
+//! output lines both inputs up, with gaps where one of them has no bytes,
+//! and ends with a summary of the hunks.
+
+use std::collections::{HashMap, VecDeque}; // Anchor lookup and context lines
+use std::io::{self, Write}; // I/O operations
+use std::ops::Range; // Byte ranges
+
+use crate::color; // Highlighting
+use crate::Theme; // Colors
+
+// Bytes per side on each line
+const WIDTH: usize = 16;
+// Width of one side: offset, hex column and characters
+const SIDE_WIDTH: usize = 8 + 2 + WIDTH * 3 + 2 + WIDTH + 2;
+// Regions up to this size are aligned byte by byte; larger ones are split at anchors first
+const SMALL: usize = 1 << 16;
+// Edits tried when aligning a small region before treating it as one change
+const MAX_EDITS: usize = 1024;
+// Size of the blocks matched between large regions to find anchors
+const BLOCK: usize = 32;
+// Hunks separated by fewer equal bytes are merged, so stray matches don't split a change
+const MIN_MATCH: usize = 4;
+
+// Equal bytes in both inputs: offset in the first, offset in the second, length
+type Run = (usize, usize, usize);
+
+/// What a hunk does to the first input to get the second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// Bytes are replaced by other bytes, not necessarily as many.
+    Changed,
+    /// Bytes only in the second input.
+    Inserted,
+    /// Bytes only in the first input.
+    Deleted,
+}
+
+/// A run of differences: bytes `a` of the first input stand where bytes
+/// `b` of the second input do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub a: Range<usize>,
+    pub b: Range<usize>,
+}
+
+impl Hunk {
+    /// What kind of difference this is.
+    pub fn change(&self) -> Change {
+        match (self.a.is_empty(), self.b.is_empty()) {
+            (true, _) => Change::Inserted,
+            (_, true) => Change::Deleted,
+            _ => Change::Changed,
+        }
+    }
+}
+
+/// Finds the runs of differences between `a` and `b`.
+///
+/// Small regions are aligned with a shortest edit script (Myers'
+/// algorithm). Large regions are first split at blocks found once in each
+/// input, in the same order, so the work stays close to linear. Regions
+/// too different to align are compared offset by offset.
+///
+/// ```
+/// use hexdump::diff::{hunks, Change, Hunk};
+/// let found = hunks(b"header-v1-body", b"header-v12-body");
+/// assert_eq!(found, vec![Hunk { a: 9..9, b: 9..10 }]);
+/// assert_eq!(found[0].change(), Change::Inserted);
+/// ```
+pub fn hunks(a: &[u8], b: &[u8]) -> Vec<Hunk> {
+    let mut runs = Vec::new();
+    align(a, b, 0, 0, &mut runs);
+
+    // Differences are whatever lies between equal runs
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    for (ax, by, len) in runs.into_iter().chain([(a.len(), b.len(), 0)]) {
+        if ax > x || by > y {
+            match hunks.last_mut() {
+                // Too few equal bytes since the last hunk to keep them apart
+                Some(last) if x - last.a.end < MIN_MATCH => {
+                    last.a.end = ax;
+                    last.b.end = by;
+                }
+                _ => hunks.push(Hunk { a: x..ax, b: y..by }),
+            }
+        }
+        (x, y) = (ax + len, by + len);
+    }
+    hunks
+}
+
+// Function to find the equal runs '(a offset, b offset, length)' of two regions, in order
+fn align(a: &[u8], b: &[u8], a_base: usize, b_base: usize, runs: &mut Vec<Run>) {
+    // Common start and end
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    if prefix > 0 {
+        runs.push((a_base, b_base, prefix));
+    }
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+    let (a_start, b_start) = (a_base + prefix, b_base + prefix);
+
+    if !a_mid.is_empty() && !b_mid.is_empty() {
+        if a_mid.len() + b_mid.len() <= SMALL {
+            // Same-length regions differing in a few bytes read better as changes than
+            // as insertions and deletions, which cost two edits per changed byte
+            let found = myers(a_mid, b_mid);
+            let changed = (a_mid.len() == b_mid.len())
+                .then(|| a_mid.iter().zip(b_mid).filter(|(x, y)| x != y).count());
+            match (found, changed) {
+                (Some((edits, found)), changed) if changed.is_none_or(|c| edits < 2 * c) => runs
+                    .extend(
+                        found
+                            .into_iter()
+                            .map(|(x, y, len)| (a_start + x, b_start + y, len)),
+                    ),
+                (_, Some(_)) => positional(a_mid, b_mid, a_start, b_start, runs),
+                _ => {}
+            }
+        } else {
+            let anchors = anchors(a_mid, b_mid);
+            if anchors.is_empty() {
+                positional(a_mid, b_mid, a_start, b_start, runs);
+            }
+            // Align the regions between anchors, then each anchor itself
+            let (mut x, mut y) = (0, 0);
+            for (ax, by) in anchors {
+                align(&a_mid[x..ax], &b_mid[y..by], a_start + x, b_start + y, runs);
+                runs.push((a_start + ax, b_start + by, BLOCK));
+                (x, y) = (ax + BLOCK, by + BLOCK);
+            }
+            if x > 0 {
+                align(&a_mid[x..], &b_mid[y..], a_start + x, b_start + y, runs);
+            }
+        }
+    }
+
+    if suffix > 0 {
+        runs.push((a_base + a.len() - suffix, b_base + b.len() - suffix, suffix));
+    }
+}
+
+// Function to find the number of edits and the equal runs of a shortest
+// edit script, or None if more than MAX_EDITS edits are needed
+fn myers(a: &[u8], b: &[u8]) -> Option<(usize, Vec<Run>)> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (a.len() + b.len()).min(MAX_EDITS) as isize;
+    let offset = max + 1; // Index of diagonal 0 in 'v'
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    let mut trace: Vec<Vec<isize>> = Vec::new(); // 'v' before each step, for backtracking
+
+    for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let i = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1] // Down: a byte of 'b' inserted
+            } else {
+                v[i - 1] + 1 // Right: a byte of 'a' deleted
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[i] = x;
+            if x >= n && y >= m {
+                return Some((d as usize, backtrack(&trace, n, m)));
+            }
+        }
+    }
+    None
+}
+
+// Function to walk the Myers trace back from the end, collecting the diagonal runs
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Run> {
+    let mut runs = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let at = |k: isize| v[(k + d) as usize]; // 'v' is stored from diagonal -d
+        let (start_x, start_y, prev) = if d == 0 {
+            (0, 0, (0, 0))
+        } else if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            let prev_x = at(k + 1);
+            (prev_x, prev_x - k, (prev_x, prev_x - k - 1))
+        } else {
+            let prev_x = at(k - 1);
+            (prev_x + 1, prev_x + 1 - k, (prev_x, prev_x - k + 1))
+        };
+        if x > start_x {
+            runs.push((start_x as usize, start_y as usize, (x - start_x) as usize));
+        }
+        (x, y) = prev;
+    }
+    runs.reverse();
+    runs
+}
+
+// Function to find blocks appearing once in each region, keeping the
+// longest set that is in the same order in both
+fn anchors(a: &[u8], b: &[u8]) -> Vec<(usize, usize)> {
+    // Aligned blocks of 'a' that are unique there
+    let mut blocks: HashMap<&[u8], Option<usize>> = HashMap::new();
+    for (i, block) in a.chunks_exact(BLOCK).enumerate() {
+        blocks
+            .entry(block)
+            .and_modify(|at| *at = None)
+            .or_insert(Some(i * BLOCK));
+    }
+    // Where they appear in 'b', dropping those that appear more than once
+    let mut found: HashMap<usize, Option<usize>> = HashMap::new();
+    for j in 0..(b.len() + 1).saturating_sub(BLOCK) {
+        if let Some(Some(x)) = blocks.get(&b[j..j + BLOCK]) {
+            found
+                .entry(*x)
+                .and_modify(|at| *at = None)
+                .or_insert(Some(j));
+        }
+    }
+    let mut pairs: Vec<(usize, usize)> = found
+        .into_iter()
+        .filter_map(|(x, y)| y.map(|y| (x, y)))
+        .collect();
+    pairs.sort_unstable();
+
+    // Longest run of pairs increasing in 'b' too, without overlaps (patience sorting)
+    let mut tails: Vec<usize> = Vec::new(); // Index in 'pairs' of the best end of each length
+    let mut links = vec![usize::MAX; pairs.len()]; // Previous pair in the best sequence
+    for (i, &(_, y)) in pairs.iter().enumerate() {
+        let len = tails.partition_point(|&t| pairs[t].1 + BLOCK <= y);
+        if len > 0 {
+            links[i] = tails[len - 1];
+        }
+        if len == tails.len() {
+            tails.push(i);
+        } else if y < pairs[tails[len]].1 {
+            tails[len] = i;
+        }
+    }
+    let mut result = Vec::new();
+    let mut at = tails.last().copied().unwrap_or(usize::MAX);
+    while at != usize::MAX {
+        result.push(pairs[at]);
+        at = links[at];
+    }
+    result.reverse();
+    result
+}
+
+// Function to find the equal runs of two regions compared offset by offset
+fn positional(a: &[u8], b: &[u8], a_base: usize, b_base: usize, runs: &mut Vec<Run>) {
+    let mut start = None;
+    for i in 0..=a.len().min(b.len()) {
+        let equal = i < a.len().min(b.len()) && a[i] == b[i];
+        match (equal, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                runs.push((a_base + s, b_base + s, i - s));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Settings for the side-by-side output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SideBySide {
+    /// Show only lines with differences, and this many lines around them.
+    pub context: Option<usize>,
+    /// Colors for offsets and differing bytes, or `None` for plain output.
+    pub theme: Option<Theme>,
+    /// Offset of the first byte of both inputs in their files, such as the
+    /// bytes skipped, added to every offset shown.
+    pub start: u64,
+}
+
+// One position of the aligned output: a byte of either input or both
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    a: Option<usize>, // Offset in the first input, unless only in the second
+    b: Option<usize>, // Offset in the second input, unless only in the first
+    differs: bool,
+}
+
+impl SideBySide {
+    /// Writes `a` and `b` side by side, headed by their names, then a
+    /// summary of the differences. Returns whether the inputs differ.
+    ///
+    /// The middle column marks each line like `sdiff`: blank when equal,
+    /// `|` when bytes changed, `<` when bytes are only in `a` and `>` when
+    /// they are only in `b`. Missing bytes are shown as `--`.
+    pub fn write<W: Write>(
+        &self,
+        names: (&str, &str),
+        a: &[u8],
+        b: &[u8],
+        mut writer: W,
+    ) -> io::Result<bool> {
+        let hunks = hunks(a, b);
+        writeln!(
+            writer,
+            "{:<width$}   {}",
+            names.0,
+            names.1,
+            width = SIDE_WIDTH
+        )?;
+
+        // Equal runs and hunks in order, as cells
+        let mut segments = Vec::new();
+        let (mut x, mut y) = (0, 0);
+        for hunk in hunks.iter().chain([&Hunk {
+            a: a.len()..a.len(),
+            b: b.len()..b.len(),
+        }]) {
+            segments.push((x..hunk.a.start, y..hunk.b.start, false));
+            segments.push((hunk.a.clone(), hunk.b.clone(), true));
+            (x, y) = (hunk.a.end, hunk.b.end);
+        }
+        let mut cells = segments.into_iter().flat_map(|(ra, rb, hunk)| {
+            let len = ra.len().max(rb.len());
+            (0..len).map(move |i| {
+                let cell_a = (i < ra.len()).then_some(ra.start + i);
+                let cell_b = (i < rb.len()).then_some(rb.start + i);
+                Cell {
+                    a: cell_a,
+                    b: cell_b,
+                    differs: hunk
+                        && match (cell_a, cell_b) {
+                            (Some(i), Some(j)) => a[i] != b[j],
+                            _ => true,
+                        },
+                }
+            })
+        });
+
+        // Lines, keeping a few back when only differences are shown, like 'grep -C'
+        let mut before: VecDeque<Vec<u8>> = VecDeque::new();
+        let mut after = 0; // Context lines still to show after a difference
+        let mut last_shown: Option<usize> = None;
+        let mut row = Vec::with_capacity(WIDTH);
+        for index in 0.. {
+            row.clear();
+            row.extend(cells.by_ref().take(WIDTH));
+            if row.is_empty() {
+                break;
+            }
+            let line = self.line(&row, a, b);
+            let differs = row.iter().any(|cell| cell.differs);
+            let Some(context) = self.context else {
+                writer.write_all(&line)?;
+                continue;
+            };
+            if differs {
+                let first = index - before.len();
+                if last_shown.is_some_and(|last| first > last + 1) {
+                    writeln!(writer, "--")?;
+                }
+                for line in before.drain(..) {
+                    writer.write_all(&line)?;
+                }
+                writer.write_all(&line)?;
+                last_shown = Some(index);
+                after = context;
+            } else if after > 0 {
+                writer.write_all(&line)?;
+                last_shown = Some(index);
+                after -= 1;
+            } else if context > 0 {
+                if before.len() == context {
+                    before.pop_front();
+                }
+                before.push_back(line);
+            }
+        }
+
+        // Summary of the hunks
+        writeln!(writer)?;
+        let start = self.start;
+        for hunk in &hunks {
+            let line = match hunk.change() {
+                Change::Changed => format!(
+                    "changed   {} -> {}",
+                    range(&hunk.a, start),
+                    range(&hunk.b, start)
+                ),
+                Change::Inserted => format!(
+                    "inserted  at {:08x} -> {}",
+                    start + hunk.a.start as u64,
+                    range(&hunk.b, start)
+                ),
+                Change::Deleted => format!(
+                    "deleted   {} -> at {:08x}",
+                    range(&hunk.a, start),
+                    start + hunk.b.start as u64
+                ),
+            };
+            writeln!(writer, "{}", line)?;
+        }
+        match hunks.len() {
+            0 => writeln!(writer, "{} and {} are identical", names.0, names.1)?,
+            1 => writeln!(writer, "1 differing range")?,
+            n => writeln!(writer, "{} differing ranges", n)?,
+        }
+        Ok(!hunks.is_empty())
+    }
+
+    // Renders one line of cells: both sides and the marker between them
+    fn line(&self, row: &[Cell], a: &[u8], b: &[u8]) -> Vec<u8> {
+        let theme = self.theme.as_ref();
+        let marker = row
+            .iter()
+            .filter(|cell| cell.differs)
+            .fold(' ', |seen, cell| {
+                let kind = match (cell.a, cell.b) {
+                    (Some(_), None) => '<',
+                    (None, Some(_)) => '>',
+                    _ => '|',
+                };
+                if seen == ' ' || seen == kind {
+                    kind
+                } else {
+                    '|' // Mixed kinds on one line
+                }
+            });
+
+        let mut out = Vec::new();
+        side(
+            &mut out,
+            row,
+            |cell| cell.a.map(|i| a[i]),
+            |cell| cell.a,
+            self.start,
+            theme.map(|t| (t, &t.deleted)),
+        );
+        out.extend_from_slice(format!(" {} ", marker).as_bytes());
+        side(
+            &mut out,
+            row,
+            |cell| cell.b.map(|j| b[j]),
+            |cell| cell.b,
+            self.start,
+            theme.map(|t| (t, &t.inserted)),
+        );
+        while out.last() == Some(&b' ') {
+            out.pop();
+        }
+        out.push(b'\n');
+        out
+    }
+}
+
+// Function to render one side of a line: offset, hex column and characters
+fn side(
+    out: &mut Vec<u8>,
+    row: &[Cell],
+    byte: impl Fn(&Cell) -> Option<u8>,
+    offset: impl Fn(&Cell) -> Option<usize>,
+    start: u64,
+    colors: Option<(&Theme, &String)>,
+) {
+    let (offset_sgr, differs_sgr) =
+        colors.map_or(("", ""), |(theme, sgr)| (&theme.offset[..], &sgr[..]));
+    match row.iter().find_map(&offset) {
+        Some(at) => color::paint(out, offset_sgr, |out| {
+            out.extend_from_slice(format!("{:08x}", start + at as u64).as_bytes())
+        }),
+        None => out.extend_from_slice(b"        "),
+    }
+    out.extend_from_slice(b" ");
+    for i in 0..WIDTH {
+        out.extend_from_slice(if i == WIDTH / 2 { b"  " } else { b" " });
+        match row.get(i) {
+            Some(cell) => {
+                let sgr = if cell.differs { differs_sgr } else { "" };
+                match byte(cell) {
+                    Some(value) => color::paint(out, sgr, |out| {
+                        out.extend_from_slice(format!("{:02x}", value).as_bytes())
+                    }),
+                    None => color::paint(out, sgr, |out| out.extend_from_slice(b"--")),
+                }
+            }
+            None => out.extend_from_slice(b"  "),
+        }
+    }
+    out.extend_from_slice(b"  |");
+    for i in 0..WIDTH {
+        match row.get(i) {
+            Some(cell) => {
+                let sgr = if cell.differs { differs_sgr } else { "" };
+                let c = match byte(cell) {
+                    Some(value @ 0x20..=0x7e) => value,
+                    Some(_) => b'.',
+                    None => b' ',
+                };
+                color::paint(out, sgr, |out| out.push(c));
+            }
+            None => out.push(b' '),
+        }
+    }
+    out.push(b'|');
+}
+
+// Function to show a byte range of one input as 'first-last (n bytes)', from 'start' on
+fn range(range: &Range<usize>, start: u64) -> String {
+    let plural = if range.len() == 1 { "" } else { "s" };
+    format!(
+        "{:08x}-{:08x} ({} byte{})",
+        start + range.start as u64,
+        start + range.end as u64 - 1,
+        range.len(),
+        plural
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper to compare two inputs with the given settings, as text
+    fn compare(settings: &SideBySide, a: &[u8], b: &[u8]) -> (String, bool) {
+        let mut out = Vec::new();
+        let differs = settings.write(("a", "b"), a, b, &mut out).unwrap();
+        (String::from_utf8(out).unwrap(), differs)
+    }
+
+    #[test]
+    fn test_hunks() {
+        // Changes, insertions and deletions are told apart, and stray matches don't split a change
+        assert_eq!(hunks(b"same", b"same"), vec![]);
+        assert_eq!(
+            hunks(b"0123456789abcdef", b"0123XY456789abdef"),
+            vec![
+                Hunk { a: 4..4, b: 4..6 },
+                Hunk {
+                    a: 12..13,
+                    b: 14..14
+                }
+            ]
+        );
+        assert_eq!(
+            hunks(b"0123456789", b"01xyz5x789"),
+            vec![Hunk { a: 2..7, b: 2..7 }]
+        );
+        assert_eq!(hunks(b"", b"abc"), vec![Hunk { a: 0..0, b: 0..3 }]);
+    }
+
+    #[test]
+    fn test_large_insertion() {
+        // Large inputs are split at anchors, so an insertion far from the ends is still found
+        let mut state = 1u64;
+        let a: Vec<u8> = (0..200_000)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect();
+        let mut b = a.clone();
+        b.splice(100_000..100_000, *b"INSERTED");
+        b[150_000] ^= 0xff;
+        assert_eq!(
+            hunks(&a, &b),
+            vec![
+                Hunk {
+                    a: 100_000..100_000,
+                    b: 100_000..100_008
+                },
+                Hunk {
+                    a: 149_992..149_993,
+                    b: 150_000..150_001
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        // Inserted bytes leave a gap on the other side, and the summary lists the ranges
+        let (text, differs) = compare(&SideBySide::default(), b"Hello, world", b"Hello, big world");
+        let expected = format!(
+            "{:<78}   b\n\
+             00000000  48 65 6c 6c 6f 2c 20 --  -- -- -- 77 6f 72 6c 64  |Hello, ~   world| > \
+             00000000  48 65 6c 6c 6f 2c 20 62  69 67 20 77 6f 72 6c 64  |Hello, big world|\n\
+             \n\
+             inserted  at 00000007 -> 00000007-0000000a (4 bytes)\n\
+             1 differing range\n",
+            "a"
+        )
+        .replace('~', " ");
+        assert_eq!(text, expected);
+        assert!(differs);
+    }
+
+    #[test]
+    fn test_start_offset() {
+        // Offsets on both sides and in the summary count from where the inputs start
+        let settings = SideBySide {
+            start: 7,
+            ..SideBySide::default()
+        };
+        let (text, _) = compare(&settings, b"word", b"worm");
+        let line = text.lines().nth(1).unwrap();
+        assert!(line.starts_with("00000007  77 6f 72 64"), "{}", line);
+        assert!(line.contains("| 00000007  77 6f 72 6d"), "{}", line);
+        assert!(
+            text.contains("changed   0000000a-0000000a (1 byte) -> 0000000a-0000000a (1 byte)\n")
+        );
+        let (text, _) = compare(&settings, b"ab", b"axb");
+        assert!(text.contains("inserted  at 00000008 -> 00000008-00000008 (1 byte)\n"));
+    }
+
+    #[test]
+    fn test_context() {
+        // Only differing lines and their context are shown, with '--' between groups
+        let a = vec![0u8; 16 * 10];
+        let mut b = a.clone();
+        b[16] = 1;
+        b[16 * 8] = 2;
+        let settings = SideBySide {
+            context: Some(1),
+            ..SideBySide::default()
+        };
+        let (text, _) = compare(&settings, &a, &b);
+        let offsets: Vec<&str> = text
+            .lines()
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .map(|line| line.get(..8).unwrap_or(line))
+            .collect();
+        assert_eq!(
+            offsets,
+            vec!["00000000", "00000010", "00000020", "--", "00000070", "00000080", "00000090"]
+        );
+        assert!(text.ends_with(
+            "\nchanged   00000010-00000010 (1 byte) -> 00000010-00000010 (1 byte)\n\
+             changed   00000080-00000080 (1 byte) -> 00000080-00000080 (1 byte)\n\
+             2 differing ranges\n"
+        ));
+        let (text, differs) = compare(&settings, b"same", b"same");
+        assert_eq!(text, format!("{:<78}   b\n\na and b are identical\n", "a"));
+        assert!(!differs);
+    }
+}
@@ -0,0 +1,354 @@
+//! A builder for configured dumps.
+//!
+//! [`HexDumper`] covers the common knobs (bytes per line, grouping, byte
+//! order, offsets, the ASCII column, the end offset, squeezing and case)
+//! without writing a format string. [`HexDumper::build`] checks the settings
+//! once, giving a [`Dumper`] that dumps to any [`io::Write`] or straight into
+//! a [`fmt::Formatter`] through [`Dumper::display`].
+//!
+//! [`io::Write`]: std::io::Write
+
+use std::fmt; // Display formatting
+use std::io::{Read, Write}; // I/O operations
+
+use crate::format::{ParseError, GROUP_SIZES}; // Layout errors and supported group sizes
+use crate::{Config, Endian, Format}; // Dump engine
+
+/// How offsets are printed at the start of each line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OffsetBase {
+    /// Eight hexadecimal digits (the default).
+    #[default]
+    Hex,
+    /// Eight octal digits.
+    Octal,
+    /// Eight decimal digits.
+    Decimal,
+    /// No offsets at all.
+    None,
+}
+
+/// Builder for dumps with a custom layout.
+///
+/// Lines show the offset, then the bytes as groups of hex digits, then
+/// optionally the printable characters between `|` bars, and may end with
+/// the total length, like `hexdump -C`. The default settings give the same
+/// dump as [`hexdump`](crate::hexdump). With the ASCII column, a final
+/// partial group keeps its column, its digits placed where its bytes would
+/// be (as `xxd -e` does); without it, the last line stops at the last byte.
+///
+/// ```
+/// use hexdump::{Endian, HexDumper};
+///
+/// let hex = HexDumper::new()
+///     .width(8)
+///     .group(4)
+///     .endian(Endian::Big)
+///     .ascii(true)
+///     .end_offset(true)
+///     .build()
+///     .unwrap();
+/// assert_eq!(
+///     hex.display(b"hello").to_string(),
+///     "00000000 68656c6c 6f        |hello|\n00000005\n"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HexDumper {
+    width: usize,        // Bytes per line
+    group: usize,        // Bytes per group
+    endian: Endian,      // Byte order within groups
+    offsets: OffsetBase, // How offsets are printed
+    ascii: bool,         // Show the printable characters column
+    end_offset: bool,    // End with the total length
+    squeeze: bool,       // Replace repeated lines with '*'
+    upper: bool,         // Uppercase hex digits
+}
+
+impl Default for HexDumper {
+    fn default() -> Self {
+        HexDumper {
+            width: 16,
+            group: 2,
+            endian: Endian::Little,
+            offsets: OffsetBase::Hex,
+            ascii: false,
+            end_offset: false,
+            squeeze: true,
+            upper: false,
+        }
+    }
+}
+
+impl HexDumper {
+    /// The default layout: 16 bytes per line in little-endian two-byte
+    /// groups, hex offsets, no ASCII column or end offset, and squeezing on.
+    /// It dumps the same as [`hexdump`](crate::hexdump).
+    pub fn new() -> Self {
+        HexDumper::default()
+    }
+
+    /// Bytes per line (16 by default). Must be a multiple of the group size.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Bytes per group: 1, 2 (the default), 4, 8 or 16.
+    pub fn group(mut self, group: usize) -> Self {
+        self.group = group;
+        self
+    }
+
+    /// Byte order of each group (little-endian by default).
+    pub fn endian(mut self, endian: Endian) -> Self {
+        self.endian = endian;
+        self
+    }
+
+    /// How offsets are printed, or [`OffsetBase::None`] to leave them out.
+    pub fn offset_base(mut self, offsets: OffsetBase) -> Self {
+        self.offsets = offsets;
+        self
+    }
+
+    /// Whether to end each line with its printable characters (off by default).
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+
+    /// Whether to end the dump with the total length when offsets are shown
+    /// (off by default).
+    pub fn end_offset(mut self, end_offset: bool) -> Self {
+        self.end_offset = end_offset;
+        self
+    }
+
+    /// Whether to replace runs of identical lines with `*` (on by default).
+    pub fn squeeze(mut self, squeeze: bool) -> Self {
+        self.squeeze = squeeze;
+        self
+    }
+
+    /// Whether to print hex digits in uppercase (off by default).
+    pub fn uppercase(mut self, upper: bool) -> Self {
+        self.upper = upper;
+        self
+    }
+
+    /// The format implementing these settings, or an error if the width or
+    /// group size can't be used.
+    pub fn format(&self) -> Result<Format, ParseError> {
+        if !GROUP_SIZES.contains(&self.group) {
+            return Err(ParseError::new(format!(
+                "bad group size {} (expected 1, 2, 4, 8 or 16)",
+                self.group
+            )));
+        }
+        if self.width == 0 || !self.width.is_multiple_of(self.group) {
+            return Err(ParseError::new(format!(
+                "bad width {} (expected a multiple of the group size {})",
+                self.width, self.group
+            )));
+        }
+
+        let value = if self.upper { 'X' } else { 'x' };
+        let value = format!("%0{}{}", self.group * 2, value);
+        let groups = self.width / self.group;
+        let radix = match self.offsets {
+            OffsetBase::Hex => Some('x'),
+            OffsetBase::Octal => Some('o'),
+            OffsetBase::Decimal => Some('d'),
+            OffsetBase::None => None,
+        };
+
+        // Offset and hex column; without offsets the first group has no blank before it
+        let mut text = String::new();
+        if let Some(radix) = radix {
+            if self.end_offset {
+                text.push_str(&format!("\"%08.8_A{}\\n\"\n", radix));
+            }
+            text.push_str(&format!("\"%08.8_a{}\" ", radix));
+            text.push_str(&format!(r#"{}/{} " {}""#, groups, self.group, value));
+        } else {
+            text.push_str(&format!(r#"1/{} "{}""#, self.group, value));
+            if groups > 1 {
+                text.push_str(&format!(r#" {}/{} " {}""#, groups - 1, self.group, value));
+            }
+        }
+        if self.ascii {
+            text.push_str(&format!("\n\"  |\" {}/1 \"%_p\" \"|\\n\"", self.width));
+        } else {
+            text.push_str(r#" "\n""#);
+        }
+
+        // Partial lines keep the ASCII column in place, or stop at the last byte without it
+        let format = Format::parse_file(&text)?;
+        let mut format = if self.ascii {
+            format.aligned()
+        } else {
+            format.truncated()
+        };
+        format.set_endian(self.endian);
+        Ok(format)
+    }
+
+    /// The [`Config`] implementing these settings, to set a skip, length or
+    /// colors on top of them.
+    pub fn config(&self) -> Result<Config, ParseError> {
+        Ok(Config {
+            format: self.format()?,
+            squeeze: self.squeeze,
+            ..Config::default()
+        })
+    }
+
+    /// Checks these settings, giving a [`Dumper`] that can't fail on them,
+    /// or an error if the width or group size can't be used.
+    pub fn build(&self) -> Result<Dumper, ParseError> {
+        Ok(Dumper {
+            config: self.config()?,
+        })
+    }
+
+    /// Streams the whole of `reader` to `writer` with these settings, after
+    /// checking them like [`build`](HexDumper::build).
+    pub fn dump<R: Read, W: Write>(&self, reader: R, writer: W) -> crate::Result<()> {
+        self.build()?.dump(reader, writer)
+    }
+}
+
+/// A dump with checked settings, made by [`HexDumper::build`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dumper {
+    config: Config,
+}
+
+impl Dumper {
+    /// The [`Config`] implementing the settings.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Streams the whole of `reader` to `writer`; only I/O can fail.
+    pub fn dump<R: Read, W: Write>(&self, reader: R, writer: W) -> crate::Result<()> {
+        self.config.dump(reader, writer)
+    }
+
+    /// Adaptor showing `bytes` through [`fmt::Display`], as in
+    /// `format!("{}", hex.display(&bytes))`.
+    pub fn display<'a>(&'a self, bytes: &'a [u8]) -> Display<'a> {
+        Display {
+            dumper: self,
+            bytes,
+        }
+    }
+}
+
+/// Shows bytes as a dump through [`fmt::Display`]; see [`Dumper::display`].
+#[derive(Debug, Clone, Copy)]
+pub struct Display<'a> {
+    dumper: &'a Dumper,
+    bytes: &'a [u8],
+}
+
+impl fmt::Display for Display<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The settings were checked and the output is memory, so only the formatter can fail
+        let mut out = Vec::new();
+        if self.dumper.dump(self.bytes, &mut out).is_err() {
+            unreachable!("dumping checked settings into memory failed");
+        }
+        // Dumps are ASCII: non-printable bytes are shown as '.'
+        f.write_str(&String::from_utf8_lossy(&out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_layout() {
+        // The default settings dump exactly like the library and command line default
+        let hex = HexDumper::new().build().unwrap();
+        assert_eq!(
+            hex.display(&[0x00, 0x01, 0x02, 0x03]).to_string(),
+            "00000000 0100 0302\n"
+        );
+        assert_eq!(hex.display(&[]).to_string(), "");
+        let mut long = vec![7; 40];
+        long.push(1);
+        for input in [&[0, 1, 2][..], b"odd length!", &long] {
+            let mut expected = Vec::new();
+            crate::hexdump(input, &mut expected).unwrap();
+            assert_eq!(hex.display(input).to_string().as_bytes(), expected);
+        }
+        assert_eq!(hex.config(), &Config::default());
+    }
+
+    #[test]
+    fn test_partial_groups() {
+        // A partial group sits on the side of its missing bytes, keeping the ASCII column aligned
+        let little = HexDumper::new()
+            .width(8)
+            .group(4)
+            .ascii(true)
+            .end_offset(true);
+        assert_eq!(
+            little
+                .build()
+                .unwrap()
+                .display(&[0, 1, 2, 3, 4, 5])
+                .to_string(),
+            "00000000 03020100     0504  |......|\n00000006\n"
+        );
+        let big = little.endian(Endian::Big).build().unwrap();
+        assert_eq!(
+            big.display(&[0, 1, 2, 3, 4, 5]).to_string(),
+            "00000000 00010203 0405      |......|\n00000006\n"
+        );
+    }
+
+    #[test]
+    fn test_offsets_case_and_squeeze() {
+        // Octal and missing offsets, uppercase digits, and squeezing turned off
+        let hex = HexDumper::new()
+            .width(4)
+            .group(1)
+            .offset_base(OffsetBase::Octal)
+            .end_offset(true)
+            .uppercase(true);
+        assert_eq!(
+            hex.build().unwrap().display(&[0xab; 12]).to_string(),
+            "00000000 AB AB AB AB\n*\n00000014\n"
+        );
+        let hex = hex.offset_base(OffsetBase::None).squeeze(false);
+        assert_eq!(
+            hex.build().unwrap().display(&[0xab; 6]).to_string(),
+            "AB AB AB AB\nAB AB\n"
+        );
+        let hex = HexDumper::new()
+            .offset_base(OffsetBase::Decimal)
+            .width(2)
+            .end_offset(true);
+        assert_eq!(
+            hex.build().unwrap().display(&[0; 3]).to_string(),
+            "00000000 0000\n00000002 00\n00000003\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        // Unsupported group sizes and widths are reported when building or dumping
+        assert!(HexDumper::new().group(3).build().is_err());
+        assert!(HexDumper::new().width(6).group(4).build().is_err());
+        assert!(HexDumper::new().width(0).build().is_err());
+        let mut out = Vec::new();
+        assert!(HexDumper::new()
+            .group(3)
+            .dump(&b"abc"[..], &mut out)
+            .is_err());
+    }
+}
@@ -0,0 +1,608 @@
+//! Text encodings of binary data: hex, base64, base32 and base85.
+//!
+//! [`Decoder`] turns encoded text back into bytes as it is read, so that
+//! payloads found in logs can be dumped like any other input, and
+//! [`TextEncoder`] writes bytes as encoded text. Both stream, holding at
+//! most one chunk of input at a time.
+
+use std::fmt; // Display formatting
+use std::io::{self, Read, Write}; // I/O operations
+use std::str::FromStr; // Encodings given by name
+
+// Bytes read at a time, a multiple of the bytes in a group of every encoding
+const CHUNK: usize = 60 * 1024;
+
+// Alphabets, by symbol value
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const Z85: &[u8; 85] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+/// Ways of writing bytes as text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// The bytes as they are (`raw`).
+    #[default]
+    Raw,
+    /// Two hex digits per byte (`hex`).
+    Hex,
+    /// Base64 with `+` and `/`, padded with `=` (`base64`, RFC 4648).
+    Base64,
+    /// Base64 with `-` and `_`, unpadded (`base64url`, RFC 4648).
+    Base64Url,
+    /// Base32 with `A`–`Z` and `2`–`7`, padded with `=` (`base32`, RFC 4648).
+    Base32,
+    /// Adobe Ascii85, with `z` for four zero bytes (`ascii85`).
+    Ascii85,
+    /// ZeroMQ Z85 (`z85`).
+    Z85,
+}
+
+/// The names accepted by [`Encoding::from_str`], for messages.
+pub const ENCODINGS: &str = "raw, hex, base64, base64url, base32, ascii85 or z85";
+
+impl FromStr for Encoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "raw" => Ok(Encoding::Raw),
+            "hex" => Ok(Encoding::Hex),
+            "base64" => Ok(Encoding::Base64),
+            "base64url" => Ok(Encoding::Base64Url),
+            "base32" => Ok(Encoding::Base32),
+            "ascii85" => Ok(Encoding::Ascii85),
+            "z85" => Ok(Encoding::Z85),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Encoding::Raw => "raw",
+            Encoding::Hex => "hex",
+            Encoding::Base64 => "base64",
+            Encoding::Base64Url => "base64url",
+            Encoding::Base32 => "base32",
+            Encoding::Ascii85 => "ascii85",
+            Encoding::Z85 => "z85",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Encoding {
+    // Symbols and bytes in a full group
+    fn group(self) -> (usize, usize) {
+        match self {
+            Encoding::Raw => (1, 1),
+            Encoding::Hex => (2, 1),
+            Encoding::Base64 | Encoding::Base64Url => (4, 3),
+            Encoding::Base32 => (8, 5),
+            Encoding::Ascii85 | Encoding::Z85 => (5, 4),
+        }
+    }
+
+    // Value of a symbol, if it is one
+    fn value(self, c: u8) -> Option<u8> {
+        let find = |alphabet: &[u8]| alphabet.iter().position(|&a| a == c).map(|i| i as u8);
+        match self {
+            Encoding::Raw => Some(c),
+            Encoding::Hex => (c as char).to_digit(16).map(|d| d as u8),
+            Encoding::Base64 => find(BASE64),
+            Encoding::Base64Url => find(BASE64_URL),
+            Encoding::Base32 => {
+                let c = c.to_ascii_uppercase(); // Lowercase is accepted too
+                BASE32.iter().position(|&a| a == c).map(|i| i as u8)
+            }
+            Encoding::Ascii85 => matches!(c, b'!'..=b'u').then(|| c - b'!'),
+            Encoding::Z85 => find(Z85),
+        }
+    }
+
+    // Function to decode a group of 'symbols' values, full or not, into 'out'; false if
+    // a partial group this long can't be
+    fn decode(self, symbols: &[u8], out: &mut Vec<u8>) -> bool {
+        let (full, _) = self.group();
+        let n = symbols.len();
+        let bytes = match self {
+            Encoding::Raw => {
+                out.extend_from_slice(symbols);
+                return true;
+            }
+            Encoding::Hex if n == 2 => {
+                out.push(symbols[0] << 4 | symbols[1]);
+                return true;
+            }
+            Encoding::Hex => return false,
+            Encoding::Base64 | Encoding::Base64Url => match n {
+                2..=4 => n * 6 / 8,
+                _ => return false,
+            },
+            Encoding::Base32 => match n {
+                2 | 4 | 5 | 7 | 8 => n * 5 / 8,
+                _ => return false,
+            },
+            Encoding::Ascii85 | Encoding::Z85 => match n {
+                2..=5 => n - 1,
+                _ => return false,
+            },
+        };
+        match self {
+            Encoding::Ascii85 | Encoding::Z85 => {
+                // Missing symbols count as the highest, which rounds the missing bytes away
+                let value = (0..full).fold(0u64, |value, i| {
+                    value * 85 + symbols.get(i).map_or(84, |&s| s as u64)
+                });
+                if value > u32::MAX as u64 {
+                    return false;
+                }
+                out.extend_from_slice(&(value as u32).to_be_bytes()[..bytes]);
+            }
+            _ => {
+                let bits = if self == Encoding::Base32 { 5 } else { 6 };
+                let value = symbols
+                    .iter()
+                    .fold(0u64, |value, &s| value << bits | s as u64);
+                let value = value >> (n * bits - bytes * 8); // Drop the padding bits
+                out.extend((0..bytes).rev().map(|i| (value >> (i * 8)) as u8));
+            }
+        }
+        true
+    }
+
+    // Function to encode a group of bytes, full or not, into 'out'
+    fn encode(self, bytes: &[u8], out: &mut Vec<u8>) {
+        let (full, size) = self.group();
+        match self {
+            Encoding::Raw => out.extend_from_slice(bytes),
+            Encoding::Hex => out.extend_from_slice(format!("{:02x}", bytes[0]).as_bytes()),
+            Encoding::Ascii85 if bytes == [0; 4] => out.push(b'z'),
+            Encoding::Ascii85 | Encoding::Z85 => {
+                let mut word = [0u8; 4];
+                word[..bytes.len()].copy_from_slice(bytes);
+                let mut value = u32::from_be_bytes(word);
+                let mut symbols = [0u8; 5];
+                for symbol in symbols.iter_mut().rev() {
+                    *symbol = (value % 85) as u8;
+                    value /= 85;
+                }
+                for &symbol in &symbols[..bytes.len() + 1] {
+                    out.push(match self {
+                        Encoding::Z85 => Z85[symbol as usize],
+                        _ => b'!' + symbol,
+                    });
+                }
+            }
+            _ => {
+                let (bits, alphabet): (usize, &[u8]) = match self {
+                    Encoding::Base32 => (5, BASE32),
+                    Encoding::Base64Url => (6, BASE64_URL),
+                    _ => (6, BASE64),
+                };
+                let value = bytes.iter().fold(0u64, |value, &b| value << 8 | b as u64);
+                let value = value << ((size - bytes.len()) * 8); // Zero bits up to a full group
+                let symbols = (bytes.len() * 8).div_ceil(bits);
+                for i in 0..symbols {
+                    let shift = (full - 1 - i) * bits;
+                    out.push(alphabet[(value >> shift) as usize & ((1 << bits) - 1)]);
+                }
+                if self != Encoding::Base64Url {
+                    out.resize(out.len() + full - symbols, b'=');
+                }
+            }
+        }
+    }
+}
+
+/// Reader of the bytes encoded as text by another reader.
+///
+/// Whitespace is skipped everywhere. Padding is optional, and ends the group
+/// it is in. Ascii85 may be wrapped in `<~` and `~>`, after which the input
+/// ends. Anything else that isn't part of the encoding is an error of kind
+/// [`InvalidData`](io::ErrorKind::InvalidData) giving its offset in the
+/// encoded input.
+///
+/// ```
+/// use std::io::Read;
+/// use hexdump::encoding::{Decoder, Encoding};
+/// let mut bytes = Vec::new();
+/// Decoder::new(Encoding::Base64, &b"SGVs\nbG8="[..]).read_to_end(&mut bytes).unwrap();
+/// assert_eq!(bytes, b"Hello");
+/// ```
+#[derive(Debug)]
+pub struct Decoder<R> {
+    reader: R,
+    encoding: Encoding,
+    text: Vec<u8>,    // Encoded text read at a time
+    offset: u64,      // Offset in the encoded input of the text read next
+    symbols: Vec<u8>, // Values of the symbols of the group under way
+    out: Vec<u8>,     // Decoded bytes not returned yet
+    start: usize,     // Position of the next of them
+    state: State,
+}
+
+// Where a decoder is in the text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Start, // Before any symbol: Ascii85 may open with '<~'
+    Open,  // Ascii85: after a '<' at the start
+    Data,  // Within the data
+    Close, // Ascii85: after a '~'
+    Done,  // At the end of the data, or of the input
+}
+
+impl<R: Read> Decoder<R> {
+    /// Decodes the text of `encoding` read from `reader`.
+    pub fn new(encoding: Encoding, reader: R) -> Self {
+        Decoder {
+            reader,
+            encoding,
+            text: vec![0; CHUNK],
+            offset: 0,
+            symbols: Vec::new(),
+            out: Vec::new(),
+            start: 0,
+            state: State::Start,
+        }
+    }
+
+    /// The reader of the encoded text.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    // Function to decode the next chunk of text into 'out'
+    fn fill(&mut self) -> io::Result<()> {
+        self.out.clear();
+        self.start = 0;
+        let n = match self.reader.read(&mut self.text) {
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if n == 0 {
+            if self.state == State::Open {
+                self.symbol(b'<')?; // A lone '<'
+            }
+            self.end_group()?;
+            self.state = State::Done;
+            return Ok(());
+        }
+        for i in 0..n {
+            let c = self.text[i];
+            let result = self.byte(c);
+            self.offset += 1;
+            result?;
+            if self.state == State::Done {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Function to take one byte of text
+    fn byte(&mut self, c: u8) -> io::Result<()> {
+        let ascii85 = self.encoding == Encoding::Ascii85;
+        match (self.state, c) {
+            (_, _) if c.is_ascii_whitespace() && self.encoding != Encoding::Raw => Ok(()),
+            (State::Start, b'<') if ascii85 => {
+                self.state = State::Open;
+                Ok(())
+            }
+            (State::Open, b'~') => {
+                self.state = State::Data;
+                Ok(())
+            }
+            (State::Open, _) => {
+                self.state = State::Data;
+                self.symbol(b'<')?;
+                self.symbol(c)
+            }
+            (State::Close, b'>') => {
+                self.end_group()?;
+                self.state = State::Done;
+                Ok(())
+            }
+            (State::Close, _) => Err(self.invalid(c)),
+            (_, b'~') if ascii85 => {
+                self.state = State::Close;
+                Ok(())
+            }
+            (_, b'z') if ascii85 && self.symbols.is_empty() => {
+                self.state = State::Data;
+                self.out.extend_from_slice(&[0; 4]);
+                Ok(())
+            }
+            (_, b'=')
+                if matches!(
+                    self.encoding,
+                    Encoding::Base64 | Encoding::Base64Url | Encoding::Base32
+                ) =>
+            {
+                self.state = State::Data;
+                self.end_group()
+            }
+            _ => {
+                self.state = State::Data;
+                self.symbol(c)
+            }
+        }
+    }
+
+    // Function to add a symbol to the group under way, decoding the group once full
+    fn symbol(&mut self, c: u8) -> io::Result<()> {
+        let value = self.encoding.value(c).ok_or_else(|| self.invalid(c))?;
+        self.symbols.push(value);
+        if self.symbols.len() == self.encoding.group().0 {
+            self.end_group()?;
+        }
+        Ok(())
+    }
+
+    // Function to decode the group under way, full or not
+    fn end_group(&mut self) -> io::Result<()> {
+        if self.symbols.is_empty() {
+            return Ok(());
+        }
+        if !self.encoding.decode(&self.symbols, &mut self.out) {
+            let message = match self.symbols.len() == self.encoding.group().0 {
+                true => format!("{} group out of range", self.encoding),
+                false => format!("truncated {} group", self.encoding),
+            };
+            return Err(self.error(message));
+        }
+        self.symbols.clear();
+        Ok(())
+    }
+
+    // Error for a byte that isn't part of the encoding
+    fn invalid(&self, c: u8) -> io::Error {
+        let shown = match c {
+            0x21..=0x7e => format!("character '{}'", c as char),
+            _ => format!("byte 0x{:02x}", c),
+        };
+        self.error(format!("invalid {} {}", self.encoding, shown))
+    }
+
+    // Error at the current offset of the encoded input
+    fn error(&self, message: String) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} at offset {}", message, self.offset),
+        )
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.start == self.out.len() {
+            if self.state == State::Done {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let n = buf.len().min(self.out.len() - self.start);
+        buf[..n].copy_from_slice(&self.out[self.start..self.start + n]);
+        self.start += n;
+        Ok(n)
+    }
+}
+
+/// Settings for writing bytes as encoded text.
+///
+/// ```
+/// use hexdump::encoding::{Encoding, TextEncoder};
+/// let encoder = TextEncoder { wrap: 4, ..TextEncoder::new(Encoding::Base32) };
+/// let mut out = Vec::new();
+/// encoder.write(&hexdump::Config::default(), &b"hi"[..], &mut out).unwrap();
+/// assert_eq!(out, b"NBUQ\n====\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEncoder {
+    /// The encoding.
+    pub encoding: Encoding,
+    /// Characters per line, or 0 to write one line. Raw output is never
+    /// wrapped.
+    pub wrap: usize,
+}
+
+impl TextEncoder {
+    /// Text in `encoding`, wrapped at 76 characters.
+    pub fn new(encoding: Encoding) -> Self {
+        TextEncoder { encoding, wrap: 76 }
+    }
+
+    /// Writes the bytes of `reader`, positioned [`skip`](crate::Config::skip)
+    /// bytes into the input, as text. Text other than raw ends with a
+    /// newline. Returns the number of bytes encoded.
+    pub fn write<R: Read, W: Write>(
+        &self,
+        config: &crate::Config,
+        reader: R,
+        mut writer: W,
+    ) -> io::Result<u64> {
+        let mut reader = reader.take(config.length.unwrap_or(u64::MAX));
+        let mut chunk = vec![0u8; CHUNK];
+        let (_, size) = self.encoding.group();
+        let mut total = 0;
+        let mut column = 0; // Characters on the current line
+        loop {
+            let n = crate::read_line(&mut reader, &mut chunk)?;
+            let mut text = Vec::new();
+            for group in chunk[..n].chunks(size) {
+                self.encoding.encode(group, &mut text);
+            }
+            if self.encoding == Encoding::Raw || self.wrap == 0 {
+                column += text.len();
+                writer.write_all(&text)?;
+            } else {
+                let mut lines = Vec::with_capacity(text.len() + text.len() / self.wrap + 1);
+                for &c in &text {
+                    if column == self.wrap {
+                        lines.push(b'\n');
+                        column = 0;
+                    }
+                    lines.push(c);
+                    column += 1;
+                }
+                writer.write_all(&lines)?;
+            }
+            total += n as u64;
+            if n < chunk.len() {
+                break;
+            }
+        }
+        if column > 0 && self.encoding != Encoding::Raw {
+            writeln!(writer)?;
+        }
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper to decode text, one byte of it at a time to cross every boundary
+    fn decode(encoding: Encoding, text: &[u8]) -> io::Result<Vec<u8>> {
+        struct Trickle<'a>(&'a [u8]);
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let n = self.0.len().min(buf.len()).min(1);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+        let mut bytes = Vec::new();
+        Decoder::new(encoding, Trickle(text)).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    // Helper to encode bytes on one line
+    fn encode(encoding: Encoding, bytes: &[u8]) -> String {
+        let encoder = TextEncoder {
+            wrap: 0,
+            ..TextEncoder::new(encoding)
+        };
+        let mut out = Vec::new();
+        encoder
+            .write(&crate::Config::default(), bytes, &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_rfc_4648_vectors() {
+        // Base64 and base32 match the RFC test vectors both ways, for every length of "foobar"
+        let base64 = [
+            "", "Zg==", "Zm8=", "Zm9v", "Zm9vYg==", "Zm9vYmE=", "Zm9vYmFy",
+        ];
+        let base32 = [
+            "",
+            "MY======",
+            "MZXQ====",
+            "MZXW6===",
+            "MZXW6YQ=",
+            "MZXW6YTB",
+            "MZXW6YTBOI======",
+        ];
+        for len in 0..=6 {
+            let bytes = &b"foobar"[..len];
+            let newline = if len > 0 { "\n" } else { "" };
+            assert_eq!(
+                encode(Encoding::Base64, bytes),
+                format!("{}{}", base64[len], newline)
+            );
+            assert_eq!(
+                encode(Encoding::Base32, bytes),
+                format!("{}{}", base32[len], newline)
+            );
+            assert_eq!(
+                decode(Encoding::Base64, base64[len].as_bytes()).unwrap(),
+                bytes
+            );
+            assert_eq!(
+                decode(Encoding::Base32, base32[len].as_bytes()).unwrap(),
+                bytes
+            );
+            let unpadded = base64[len].trim_end_matches('=');
+            assert_eq!(
+                decode(Encoding::Base64, unpadded.as_bytes()).unwrap(),
+                bytes
+            );
+        }
+        assert_eq!(encode(Encoding::Base64Url, b"\xfb\xff"), "-_8\n");
+        assert_eq!(decode(Encoding::Base64Url, b"-_8").unwrap(), b"\xfb\xff");
+        assert_eq!(decode(Encoding::Base32, b"mzxw6===").unwrap(), b"foo");
+    }
+
+    #[test]
+    fn test_base85() {
+        // Ascii85 shortens zero groups and may be delimited, and Z85 matches its spec's example
+        let hello = b"\x86\x4f\xd2\x6f\xb5\x59\xf7\x5b";
+        assert_eq!(encode(Encoding::Z85, hello), "HelloWorld\n");
+        assert_eq!(decode(Encoding::Z85, b"Hello World").unwrap(), hello);
+        assert_eq!(encode(Encoding::Ascii85, b"\0\0\0\0hi"), "zBP@\n");
+        assert_eq!(decode(Encoding::Ascii85, b"zBP@").unwrap(), b"\0\0\0\0hi");
+        assert_eq!(decode(Encoding::Ascii85, b"<~BP@~> junk").unwrap(), b"hi");
+        assert_eq!(decode(Encoding::Ascii85, b"<BP@").unwrap().len(), 3); // '<' is a symbol
+    }
+
+    #[test]
+    fn test_hex_and_raw() {
+        // Hex ignores case and whitespace, and raw passes everything through
+        assert_eq!(encode(Encoding::Hex, b"\x01\xab"), "01ab\n");
+        assert_eq!(decode(Encoding::Hex, b"01 AB\n").unwrap(), b"\x01\xab");
+        assert_eq!(encode(Encoding::Raw, b" \n"), " \n");
+        assert_eq!(decode(Encoding::Raw, b" \n").unwrap(), b" \n");
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        // Errors name what was wrong and where in the encoded input
+        let message = |encoding, text| decode(encoding, text).unwrap_err().to_string();
+        assert_eq!(
+            message(Encoding::Base64, &b"SGVs!"[..]),
+            "invalid base64 character '!' at offset 4"
+        );
+        assert_eq!(
+            message(Encoding::Hex, &b"abc"[..]),
+            "truncated hex group at offset 3"
+        );
+        assert_eq!(
+            message(Encoding::Base32, &b"\xff"[..]),
+            "invalid base32 byte 0xff at offset 0"
+        );
+        assert_eq!(
+            message(Encoding::Ascii85, &b"s8W-\""[..]),
+            "ascii85 group out of range at offset 4"
+        );
+        assert_eq!(
+            message(Encoding::Base64, &b"S"[..]),
+            "truncated base64 group at offset 1"
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        // Lines are cut at the wrap width, with a final newline
+        let encoder = TextEncoder {
+            wrap: 4,
+            ..TextEncoder::new(Encoding::Hex)
+        };
+        let mut out = Vec::new();
+        encoder
+            .write(
+                &crate::Config::default(),
+                &b"\x00\x01\x02\x03"[..],
+                &mut out,
+            )
+            .unwrap();
+        assert_eq!(out, b"0001\n0203\n");
+    }
+}
@@ -0,0 +1,372 @@
+//! Shannon entropy and byte frequencies, to spot compressed, encrypted or
+//! padded regions.
+//!
+//! Entropy is measured in bits per byte, from 0 for a block of one repeated
+//! byte to 8 for a block where every byte value is equally frequent.
+//! Compressed and encrypted data sits close to 8, code and text around 4
+//! to 6.
+
+use std::collections::VecDeque; // Finished blocks
+use std::io::{Read, Write}; // I/O operations
+
+use crate::color; // Colored offsets
+use crate::format; // Offset colors
+use crate::Config; // Skip, length and theme
+
+// Bytes read at a time
+const CHUNK: usize = 64 * 1024;
+
+// Width of the entropy bars, in cells of a quarter bit
+const BAR: usize = 32;
+
+// Eighths of a cell, for bars and sparklines
+const EIGHTHS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Counts of each byte value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    counts: [u64; 256],
+    total: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram {
+            counts: [0; 256],
+            total: 0,
+        }
+    }
+}
+
+impl Histogram {
+    /// Counts the bytes of `bytes`.
+    pub fn add(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.counts[b as usize] += 1;
+        }
+        self.total += bytes.len() as u64;
+    }
+
+    /// How many times `byte` was seen.
+    pub fn count(&self, byte: u8) -> u64 {
+        self.counts[byte as usize]
+    }
+
+    /// Number of bytes counted.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Shannon entropy of the bytes counted, in bits per byte.
+    ///
+    /// ```
+    /// use hexdump::entropy::Histogram;
+    /// let mut histogram = Histogram::default();
+    /// histogram.add(b"aaaa");
+    /// assert_eq!(histogram.entropy(), 0.0);
+    /// histogram.add(b"bbbb");
+    /// assert_eq!(histogram.entropy(), 1.0);
+    /// ```
+    pub fn entropy(&self) -> f64 {
+        let total = self.total as f64;
+        self.counts
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / total;
+                -p * p.log2()
+            })
+            .sum::<f64>()
+            .abs() // Not -0 for a single byte value
+    }
+
+    /// The least frequent byte and its count; the lowest byte on ties.
+    pub fn min(&self) -> (u8, u64) {
+        (0..=255u8).fold((0, u64::MAX), |min, b| {
+            if self.count(b) < min.1 {
+                (b, self.count(b))
+            } else {
+                min
+            }
+        })
+    }
+
+    /// The most frequent byte and its count; the lowest byte on ties.
+    pub fn max(&self) -> (u8, u64) {
+        (0..=255u8).fold((0, 0), |max, b| {
+            if self.count(b) > max.1 {
+                (b, self.count(b))
+            } else {
+                max
+            }
+        })
+    }
+
+    /// Mean count over the 256 byte values.
+    pub fn mean(&self) -> f64 {
+        self.total as f64 / 256.0
+    }
+
+    /// Writes the counts as a 16 by 16 grid of levels, one row per high
+    /// nibble, followed by the size, entropy and count statistics. Levels
+    /// are on a log scale so that rare bytes stay visible next to common
+    /// ones; a blank means the byte never occurs.
+    pub fn write<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let max = self.max().1;
+        writeln!(writer, "      0 1 2 3 4 5 6 7 8 9 a b c d e f")?;
+        for high in 0..16u8 {
+            let cells: Vec<String> = (0..16u8)
+                .map(|low| level(self.count(high << 4 | low), max).to_string())
+                .collect();
+            let row = format!("  {:x}_  {}", high, cells.join(" "));
+            writeln!(writer, "{}", row.trim_end())?;
+        }
+        let (min_byte, min) = self.min();
+        let (max_byte, max) = self.max();
+        writeln!(
+            writer,
+            "{} bytes, entropy {:.2} bits/byte, min {} (0x{:02x}), max {} (0x{:02x}), mean {:.2}",
+            self.total,
+            self.entropy(),
+            min,
+            min_byte,
+            max,
+            max_byte,
+            self.mean()
+        )
+    }
+}
+
+// Function to pick the sparkline level of a count, on a log scale up to 'max'
+fn level(count: u64, max: u64) -> char {
+    match count {
+        0 => ' ',
+        _ if max <= 1 => LEVELS[7],
+        _ => LEVELS[((count as f64).ln() / (max as f64).ln() * 7.0).round() as usize],
+    }
+}
+
+// Function to draw a bar as long as 'value' out of 'max', 'width' cells at most
+fn bar(value: f64, max: f64, width: usize) -> String {
+    let eighths = (value / max * (width * 8) as f64).round() as usize;
+    let mut bar = EIGHTHS[7].to_string().repeat(eighths / 8);
+    if let Some(part) = (eighths % 8).checked_sub(1) {
+        bar.push(EIGHTHS[part]);
+    }
+    bar
+}
+
+/// Entropy of one block of input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Block {
+    /// Offset of its first byte.
+    pub offset: u64,
+    /// Its length, the block size but for the last one.
+    pub len: u64,
+    /// Its entropy, in bits per byte.
+    pub entropy: f64,
+}
+
+impl Block {
+    /// The block's entropy as a number and a level from `▁` to `█`, the
+    /// way the entropy column of a dump shows it.
+    pub fn column(&self) -> String {
+        let level = ((self.entropy / 8.0 * 7.0).round() as usize).min(7);
+        format!("{:.2} {}", self.entropy, LEVELS[level])
+    }
+}
+
+/// Streaming entropy of consecutive blocks of a fixed size.
+///
+/// ```
+/// use hexdump::entropy::Blocks;
+/// let mut blocks = Blocks::new(4, 0);
+/// blocks.feed(b"aaaaabcd");
+/// blocks.feed(b"ab");
+/// blocks.finish();
+/// let entropies: Vec<_> = std::iter::from_fn(|| blocks.pop()).map(|b| b.entropy).collect();
+/// assert_eq!(entropies, vec![0.0, 2.0, 1.0]);
+/// ```
+#[derive(Debug)]
+pub struct Blocks {
+    size: u64,
+    current: Histogram, // Bytes of the block under way
+    start: u64,         // Its offset
+    done: VecDeque<Block>,
+}
+
+impl Blocks {
+    /// Starts measuring blocks of `size` bytes, the first at `start`.
+    pub fn new(size: u64, start: u64) -> Self {
+        Blocks {
+            size: size.max(1),
+            current: Histogram::default(),
+            start,
+            done: VecDeque::new(),
+        }
+    }
+
+    /// Adds the next bytes of input.
+    pub fn feed(&mut self, mut chunk: &[u8]) {
+        while !chunk.is_empty() {
+            let room = (self.size - self.current.total()).min(chunk.len() as u64) as usize;
+            self.current.add(&chunk[..room]);
+            chunk = &chunk[room..];
+            if self.current.total() == self.size {
+                self.end_block();
+            }
+        }
+    }
+
+    /// Ends the last block at the end of input.
+    pub fn finish(&mut self) {
+        if self.current.total() > 0 {
+            self.end_block();
+        }
+    }
+
+    // Function to move the block under way to the finished ones
+    fn end_block(&mut self) {
+        let block = Block {
+            offset: self.start,
+            len: self.current.total(),
+            entropy: self.current.entropy(),
+        };
+        self.start += block.len;
+        self.current = Histogram::default();
+        self.done.push_back(block);
+    }
+
+    /// Takes the first finished block.
+    pub fn pop(&mut self) -> Option<Block> {
+        self.done.pop_front()
+    }
+
+    /// The finished block holding `offset`, forgetting the ones before it.
+    pub fn at(&mut self, offset: u64) -> Option<Block> {
+        while self.done.front()?.offset + self.done.front()?.len <= offset {
+            self.done.pop_front();
+        }
+        self.done
+            .front()
+            .copied()
+            .filter(|block| block.offset <= offset)
+    }
+
+    /// Offset of the block under way: the bytes before it are in finished blocks.
+    pub fn finished(&self) -> u64 {
+        self.start
+    }
+}
+
+/// Writes the entropy of each `block`-byte block of `reader`, positioned
+/// [`skip`](Config::skip) bytes into the input, as its offset, value and a
+/// bar, followed by the [`Histogram`] of the whole input.
+pub fn write<R: Read, W: Write>(
+    config: &Config,
+    block: u64,
+    reader: R,
+    mut writer: W,
+) -> std::io::Result<()> {
+    let mut reader = reader.take(config.length.unwrap_or(u64::MAX));
+    let mut blocks = Blocks::new(block, config.skip);
+    let mut histogram = Histogram::default();
+    let mut chunk = vec![0; CHUNK];
+    loop {
+        let n = crate::read_line(&mut reader, &mut chunk)?;
+        blocks.feed(&chunk[..n]);
+        histogram.add(&chunk[..n]);
+        if n == 0 {
+            blocks.finish();
+        }
+        while let Some(block) = blocks.pop() {
+            let mut line = Vec::new();
+            color::paint(
+                &mut line,
+                format::offset_color(config.theme.as_ref()),
+                |out| out.extend_from_slice(format!("{:08x}", block.offset).as_bytes()),
+            );
+            let bar = bar(block.entropy, 8.0, BAR);
+            let text = format!("  {:.2}  {}", block.entropy, bar);
+            line.extend_from_slice(text.trim_end().as_bytes());
+            line.push(b'\n');
+            writer.write_all(&line)?;
+        }
+        if n == 0 {
+            break;
+        }
+    }
+    writeln!(writer)?;
+    histogram.write(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        // Entropy of every byte value once is 8 bits, and statistics pick the lowest byte on ties
+        let mut histogram = Histogram::default();
+        histogram.add(&(0..=255).collect::<Vec<u8>>());
+        assert_eq!(histogram.entropy(), 8.0);
+        histogram.add(b"zz");
+        assert_eq!(histogram.min(), (0, 1));
+        assert_eq!(histogram.max(), (b'z', 3));
+        assert_eq!(histogram.mean(), 258.0 / 256.0);
+        assert_eq!(Histogram::default().entropy(), 0.0);
+    }
+
+    #[test]
+    fn test_bars_and_levels() {
+        // Bars grow by eighths of a cell, and levels follow a log scale
+        assert_eq!(bar(8.0, 8.0, 4), "████");
+        assert_eq!(bar(2.5, 8.0, 4), "█▎");
+        assert_eq!(bar(0.0, 8.0, 4), "");
+        assert_eq!(level(0, 1000), ' ');
+        assert_eq!(level(1, 1000), '▁');
+        assert_eq!(level(1000, 1000), '█');
+        assert_eq!(level(1, 1), '█');
+    }
+
+    #[test]
+    fn test_blocks_at() {
+        // Blocks are looked up by offset, from the skipped offset on
+        let mut blocks = Blocks::new(4, 2);
+        blocks.feed(b"aaaaab");
+        assert_eq!(blocks.finished(), 6);
+        assert_eq!(blocks.at(3).map(|b| b.entropy), Some(0.0));
+        assert_eq!(blocks.at(6), None);
+        blocks.finish();
+        assert_eq!(
+            blocks.at(7).map(|b| (b.offset, b.len, b.entropy)),
+            Some((6, 2, 1.0))
+        );
+        assert_eq!(blocks.at(2), None); // Forgotten
+    }
+
+    #[test]
+    fn test_write() {
+        // Each block gets a line with its bar, followed by the histogram
+        let config = Config {
+            skip: 0x100,
+            ..Config::default()
+        };
+        let mut out = Vec::new();
+        write(&config, 4, &b"aaaaabcdef"[..], &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "00000100  0.00");
+        assert_eq!(lines[1], "00000104  2.00  ████████");
+        assert_eq!(lines[2], "00000108  1.00  ████");
+        assert_eq!(lines[3], "");
+        assert_eq!(lines[4], "      0 1 2 3 4 5 6 7 8 9 a b c d e f");
+        assert_eq!(lines[10], "  5_");
+        assert_eq!(lines[11], "  6_    █ ▁ ▁ ▁ ▁ ▁");
+        assert_eq!(
+            lines[21],
+            "10 bytes, entropy 2.16 bits/byte, min 0 (0x00), max 5 (0x61), mean 0.04"
+        );
+    }
+}
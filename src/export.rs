@@ -0,0 +1,418 @@
+//! Array literals of the input bytes, to embed in source code.
+//!
+//! Outputs that cover the whole input at once, rather than one line at a
+//! time in a [`Format`](crate::Format), are written by an [`Emitter`]: it
+//! is handed the input in chunks between a beginning and an end. The
+//! [`Export`] emitter writes an array in one of several languages, and the
+//! `xxd -i` [`Include`](crate::xxd::Include) output is another.
+
+use std::fmt;
+use std::io::{self, Read, Write}; // I/O operations
+use std::str::FromStr; // Language names
+
+/// Output written around the whole input, instead of line by line.
+///
+/// Emitters keep no state between calls: each is told how many bytes came
+/// before, so that one emitter can write any number of outputs.
+pub trait Emitter {
+    /// Writes what comes before the first byte.
+    fn begin(&self, writer: &mut dyn Write) -> io::Result<()>;
+    /// Writes the next `bytes`, `offset` bytes into the output.
+    fn bytes(&self, writer: &mut dyn Write, offset: u64, bytes: &[u8]) -> io::Result<()>;
+    /// Writes what comes after the last byte, `total` bytes in all.
+    fn end(&self, writer: &mut dyn Write, total: u64) -> io::Result<()>;
+}
+
+/// Streams the whole of `reader` to `writer` through `emitter`, returning
+/// the number of bytes read.
+pub fn emit<R: Read, W: Write>(
+    emitter: &dyn Emitter,
+    mut reader: R,
+    mut writer: W,
+) -> io::Result<u64> {
+    emitter.begin(&mut writer)?;
+    let mut buf = [0u8; 8192];
+    let mut total: u64 = 0; // Bytes emitted so far
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break, // End of input
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        emitter.bytes(&mut writer, total, &buf[..n])?;
+        total += n as u64;
+    }
+    emitter.end(&mut writer, total)?;
+    Ok(total)
+}
+
+/// Languages [`Export`] writes arrays in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// `unsigned char name[] = { ... };`
+    C,
+    /// `pub static NAME: &[u8] = &[ ... ];`
+    Rust,
+    /// `name = bytes([ ... ])`
+    Python,
+    /// `var name = []byte{ ... }`
+    Go,
+    /// `const name = new Uint8Array([ ... ]);`
+    Js,
+    /// `byte[] name = { ... };`, with casts for bytes above `0x7f`.
+    Java,
+    /// `byte[] name = { ... };`
+    CSharp,
+    /// `var name = [_]u8{ ... };`
+    Zig,
+}
+
+/// The names accepted by [`Language::from_str`], in the order shown in help.
+pub const LANGUAGES: &str = "c, rust, python, go, js, java, csharp or zig";
+
+impl FromStr for Language {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s.to_ascii_lowercase().as_str() {
+            "c" => Ok(Language::C),
+            "rust" | "rs" => Ok(Language::Rust),
+            "python" | "py" => Ok(Language::Python),
+            "go" => Ok(Language::Go),
+            "js" | "javascript" => Ok(Language::Js),
+            "java" => Ok(Language::Java),
+            "csharp" | "cs" | "c#" => Ok(Language::CSharp),
+            "zig" => Ok(Language::Zig),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Language::C => "c",
+            Language::Rust => "rust",
+            Language::Python => "python",
+            Language::Go => "go",
+            Language::Js => "js",
+            Language::Java => "java",
+            Language::CSharp => "csharp",
+            Language::Zig => "zig",
+        })
+    }
+}
+
+impl Language {
+    /// Whether arrays can be made read-only: all but Go and Python.
+    pub fn has_const(self) -> bool {
+        !matches!(self, Language::Go | Language::Python)
+    }
+
+    /// Whether arrays can be given static storage: C, Java and C#, and Rust,
+    /// whose arrays are `static` unless `const`.
+    pub fn has_static(self) -> bool {
+        matches!(
+            self,
+            Language::C | Language::Rust | Language::Java | Language::CSharp
+        )
+    }
+}
+
+/// Settings for an array literal of the input.
+///
+/// The qualifiers map to what each language has: `is_const` makes the
+/// array read-only (`const` in C, Rust, JavaScript and Zig, `final` in Java,
+/// `readonly` in C#) and `is_static` gives it static storage (`static` in C,
+/// Java and C#). Rust arrays are `static` unless `const`, and JavaScript
+/// ones are always `const`. [`Language::has_const`] and
+/// [`Language::has_static`] tell which qualifiers a language can express;
+/// the others are ignored.
+///
+/// ```
+/// use hexdump::export::{emit, Export, Language};
+/// let export = Export::new(Language::Rust, "magic");
+/// let mut out = Vec::new();
+/// emit(&export, &b"\x7fELF"[..], &mut out).unwrap();
+/// let expected = "\
+/// pub static MAGIC: &[u8] = &[
+///     0x7f, 0x45, 0x4c, 0x46,
+/// ];
+/// pub const MAGIC_LEN: usize = 4;
+/// ";
+/// assert_eq!(String::from_utf8(out).unwrap(), expected);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    /// Language to write.
+    pub language: Language,
+    /// Variable name, also the base of the length constant's name. Rust
+    /// names are written in uppercase.
+    pub name: String,
+    /// Number of bytes per line.
+    pub cols: usize,
+    /// Follow the array with a constant holding its length.
+    pub length: bool,
+    /// Make the array read-only.
+    pub is_const: bool,
+    /// Give the array static storage.
+    pub is_static: bool,
+}
+
+impl Export {
+    /// An export of the array `name` with the default settings: 12 bytes
+    /// per line and a length constant.
+    pub fn new(language: Language, name: &str) -> Self {
+        Export {
+            language,
+            name: name.to_string(),
+            cols: 12,
+            length: true,
+            is_const: false,
+            is_static: false,
+        }
+    }
+
+    /// A valid variable name made from a file path, like `xxd -i` does it:
+    /// every character that isn't a letter or digit becomes `_`, and a
+    /// leading digit gets a `_` prefix.
+    pub fn name_for_path(path: &str) -> String {
+        let mut name: String = path
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            name.insert(0, '_');
+        }
+        name
+    }
+
+    /// Whether `name` can be used as is for the array: a letter or `_`,
+    /// then letters, digits and `_`, as every language accepts.
+    pub fn is_name(name: &str) -> bool {
+        name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    // Names of the array and of its length constant
+    fn names(&self) -> (String, String) {
+        match self.language {
+            Language::Rust => {
+                let name = self.name.to_ascii_uppercase();
+                (name.clone(), name + "_LEN")
+            }
+            Language::C | Language::Python | Language::Zig => {
+                (self.name.clone(), self.name.clone() + "_len")
+            }
+            Language::Go | Language::Js | Language::Java | Language::CSharp => {
+                (self.name.clone(), self.name.clone() + "Len")
+            }
+        }
+    }
+
+    // Qualifiers before a declaration, for languages where they are written the same way
+    fn qualifiers(&self, constant: &str) -> String {
+        let mut qualifiers = String::new();
+        if self.is_static {
+            qualifiers.push_str("static ");
+        }
+        if self.is_const {
+            qualifiers.push_str(constant);
+            qualifiers.push(' ');
+        }
+        qualifiers
+    }
+}
+
+impl Emitter for Export {
+    fn begin(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let (name, _) = self.names();
+        match self.language {
+            Language::C => writeln!(
+                writer,
+                "{}unsigned char {}[] = {{",
+                self.qualifiers("const"),
+                name
+            ),
+            Language::Rust if self.is_const => writeln!(writer, "pub const {}: &[u8] = &[", name),
+            Language::Rust => writeln!(writer, "pub static {}: &[u8] = &[", name),
+            Language::Python => writeln!(writer, "{} = bytes([", name),
+            Language::Go => writeln!(writer, "var {} = []byte{{", name),
+            Language::Js => writeln!(writer, "const {} = new Uint8Array([", name),
+            Language::Java => writeln!(writer, "{}byte[] {} = {{", self.qualifiers("final"), name),
+            Language::CSharp => writeln!(
+                writer,
+                "{}byte[] {} = {{",
+                self.qualifiers("readonly"),
+                name
+            ),
+            Language::Zig if self.is_const => writeln!(writer, "const {} = [_]u8{{", name),
+            Language::Zig => writeln!(writer, "var {} = [_]u8{{", name),
+        }
+    }
+
+    fn bytes(&self, writer: &mut dyn Write, offset: u64, bytes: &[u8]) -> io::Result<()> {
+        // Every element is followed by a comma, which all these languages allow
+        let cols = self.cols.max(1) as u64;
+        let mut line = String::new();
+        for (position, &b) in (offset..).zip(bytes) {
+            line.push_str(if position % cols == 0 { "    " } else { " " });
+            match self.language {
+                Language::Java if b > 0x7f => line.push_str(&format!("(byte) 0x{:02x},", b)),
+                _ => line.push_str(&format!("0x{:02x},", b)),
+            }
+            if (position + 1) % cols == 0 {
+                line.push('\n');
+            }
+        }
+        writer.write_all(line.as_bytes())
+    }
+
+    fn end(&self, writer: &mut dyn Write, total: u64) -> io::Result<()> {
+        if !total.is_multiple_of(self.cols.max(1) as u64) {
+            writeln!(writer)?; // End the partial last line
+        }
+        let close = match self.language {
+            Language::C | Language::Java | Language::CSharp | Language::Zig => "};",
+            Language::Rust => "];",
+            Language::Python => "])",
+            Language::Go => "}",
+            Language::Js => "]);",
+        };
+        writeln!(writer, "{}", close)?;
+        if !self.length {
+            return Ok(());
+        }
+        let (_, len) = self.names();
+        match self.language {
+            Language::C => writeln!(
+                writer,
+                "{}unsigned int {} = {};",
+                self.qualifiers("const"),
+                len,
+                total
+            ),
+            Language::Rust => writeln!(writer, "pub const {}: usize = {};", len, total),
+            Language::Python => writeln!(writer, "{} = {}", len, total),
+            Language::Go => writeln!(writer, "const {} = {}", len, total),
+            Language::Js => writeln!(writer, "const {} = {};", len, total),
+            Language::Java => writeln!(
+                writer,
+                "{}int {} = {};",
+                self.qualifiers("final"),
+                len,
+                total
+            ),
+            Language::CSharp if self.is_const => writeln!(writer, "const int {} = {};", len, total),
+            Language::CSharp => writeln!(writer, "{}int {} = {};", self.qualifiers(""), len, total),
+            Language::Zig => writeln!(writer, "const {}: usize = {};", len, total),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper to export an input into a string, fed a byte at a time
+    fn export(settings: &Export, input: &[u8]) -> String {
+        let mut out = Vec::new();
+        settings.begin(&mut out).unwrap();
+        for (offset, b) in (0..).zip(input) {
+            settings.bytes(&mut out, offset, &[*b]).unwrap();
+        }
+        settings.end(&mut out, input.len() as u64).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_languages() {
+        // Each language gets its own declaration, closing and length constant
+        let cases = [
+            (
+                Language::C,
+                "unsigned char v[] = {",
+                "};\nunsigned int v_len = 3;\n",
+            ),
+            (
+                Language::Rust,
+                "pub static V: &[u8] = &[",
+                "];\npub const V_LEN: usize = 3;\n",
+            ),
+            (Language::Python, "v = bytes([", "])\nv_len = 3\n"),
+            (Language::Go, "var v = []byte{", "}\nconst vLen = 3\n"),
+            (
+                Language::Js,
+                "const v = new Uint8Array([",
+                "]);\nconst vLen = 3;\n",
+            ),
+            (Language::Java, "byte[] v = {", "};\nint vLen = 3;\n"),
+            (Language::CSharp, "byte[] v = {", "};\nint vLen = 3;\n"),
+            (
+                Language::Zig,
+                "var v = [_]u8{",
+                "};\nconst v_len: usize = 3;\n",
+            ),
+        ];
+        for (language, begin, end) in cases {
+            let body = match language {
+                Language::Java => "    0x00, 0x41, (byte) 0xff,\n",
+                _ => "    0x00, 0x41, 0xff,\n",
+            };
+            let expected = format!("{}\n{}{}", begin, body, end);
+            assert_eq!(
+                export(&Export::new(language, "v"), b"\x00A\xff"),
+                expected,
+                "{}",
+                language
+            );
+            assert_eq!(language.to_string().parse(), Ok(language));
+        }
+    }
+
+    #[test]
+    fn test_qualifiers_and_columns() {
+        // Qualifiers follow each language, and lines break every 'cols' bytes
+        let settings = Export {
+            cols: 2,
+            is_const: true,
+            is_static: true,
+            ..Export::new(Language::C, "v")
+        };
+        let expected = "\
+static const unsigned char v[] = {
+    0x61, 0x62,
+    0x63,
+};
+static const unsigned int v_len = 3;
+";
+        assert_eq!(export(&settings, b"abc"), expected);
+        let settings = Export {
+            language: Language::CSharp,
+            length: true,
+            ..settings
+        };
+        assert!(export(&settings, b"ab").starts_with("static readonly byte[] v = {\n"));
+        assert!(export(&settings, b"ab").ends_with("};\nconst int vLen = 2;\n"));
+        let settings = Export {
+            language: Language::Java,
+            length: false,
+            ..settings
+        };
+        assert_eq!(export(&settings, b""), "static final byte[] v = {\n};\n");
+    }
+
+    #[test]
+    fn test_names() {
+        // Paths become valid identifiers
+        assert_eq!(Export::name_for_path("fw/boot-1.bin"), "fw_boot_1_bin");
+        assert_eq!(Export::name_for_path("1.bin"), "_1_bin");
+        assert!(Export::is_name(&Export::name_for_path("1.bin")));
+        assert!(Export::is_name("_fw2"));
+        for bad in ["", "1bad", "a-b", "é"] {
+            assert!(!Export::is_name(bad), "{}", bad);
+        }
+        assert_eq!("Perl".parse::<Language>(), Err(()));
+    }
+}
@@ -0,0 +1,482 @@
+//! Searching the input for byte patterns.
+//!
+//! A [`Pattern`] is hex bytes with `??` wildcards, a string (as ASCII or
+//! UTF-8, or UTF-16) or a regular expression over bytes. A [`Finder`]
+//! searches a stream fed in chunks of any size, so matches across chunk
+//! boundaries are found, and [`Search`] prints the lines of a dump holding
+//! matches, with context around them like `grep`.
+
+use std::collections::VecDeque; // Lines kept for context
+use std::error;
+use std::fmt;
+use std::io::{Read, Write}; // I/O operations
+use std::ops::Range; // Match offsets
+
+use crate::color::Palette; // Highlighting matches
+use crate::regex::{Matcher, Regex}; // Regular expressions
+use crate::Config; // Dump settings
+
+// Bytes read at a time
+const CHUNK: usize = 64 * 1024;
+
+/// Error for a pattern that can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Something to search for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Kind {
+    Bytes(Vec<Option<u8>>), // Fixed bytes, 'None' matching any byte
+    Regex(Regex),           // A regular expression over bytes
+}
+
+impl Pattern {
+    /// Parses a pattern, its kind picked by a prefix:
+    ///
+    /// - hex bytes, optionally after `hex:`, with `??` matching any byte
+    ///   and blanks ignored: `7f 45 4c 46`, `hex:cafe??be`;
+    /// - `text:` then a string, matched as its ASCII (or UTF-8) bytes;
+    /// - `utf16:` or `utf16be:` then a string, matched as UTF-16 in little-
+    ///   or big-endian byte order;
+    /// - `re:` then a regular expression over bytes, such as
+    ///   `re:PK\x03\x04|\x7fELF` or `re:[\x20-\x7e]{8,}`.
+    ///
+    /// ```
+    /// use hexdump::find::Pattern;
+    /// let elf = Pattern::parse("7f 45 4c ??").unwrap();
+    /// assert_eq!(elf.find_all(&b"..\x7fELF\x7fEL"[..]).unwrap(), vec![2..6]);
+    /// let wide = Pattern::parse("utf16:Hi").unwrap();
+    /// assert_eq!(wide.find_all(&b"xH\0i\0"[..]).unwrap(), vec![1..5]);
+    /// assert!(Pattern::parse("re:(unclosed").is_err());
+    /// ```
+    pub fn parse(spec: &str) -> Result<Pattern, ParseError> {
+        let error = |message: String| ParseError { message };
+        let kind = if let Some(text) = spec.strip_prefix("text:") {
+            Kind::Bytes(text.bytes().map(Some).collect())
+        } else if let Some(text) = spec.strip_prefix("utf16:") {
+            Kind::Bytes(
+                text.encode_utf16()
+                    .flat_map(u16::to_le_bytes)
+                    .map(Some)
+                    .collect(),
+            )
+        } else if let Some(text) = spec.strip_prefix("utf16be:") {
+            Kind::Bytes(
+                text.encode_utf16()
+                    .flat_map(u16::to_be_bytes)
+                    .map(Some)
+                    .collect(),
+            )
+        } else if let Some(regex) = spec.strip_prefix("re:") {
+            Kind::Regex(Regex::parse(regex).map_err(error)?)
+        } else {
+            Kind::Bytes(hex(spec.strip_prefix("hex:").unwrap_or(spec)).map_err(error)?)
+        };
+        match &kind {
+            Kind::Bytes(bytes) if bytes.is_empty() => Err(error("empty pattern".to_string())),
+            Kind::Bytes(bytes) if bytes.iter().all(Option::is_none) => {
+                Err(error("the pattern matches anything".to_string()))
+            }
+            _ => Ok(Pattern { kind }),
+        }
+    }
+
+    /// Finds every match in `reader`, as byte ranges from its start.
+    pub fn find_all<R: Read>(&self, mut reader: R) -> std::io::Result<Vec<Range<u64>>> {
+        let mut finder = Finder::new(self, 0);
+        let mut found = Vec::new();
+        let mut chunk = vec![0; CHUNK];
+        loop {
+            let n = crate::read_line(&mut reader, &mut chunk)?;
+            if n == 0 {
+                break;
+            }
+            finder.feed(&chunk[..n], &mut found);
+        }
+        finder.finish(&mut found);
+        Ok(found)
+    }
+}
+
+// Function to parse hex bytes and '??' wildcards
+fn hex(text: &str) -> Result<Vec<Option<u8>>, String> {
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!("odd number of hex digits in '{}'", text));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| match pair {
+            ['?', '?'] => Ok(None),
+            [high, low] => match (high.to_digit(16), low.to_digit(16)) {
+                (Some(high), Some(low)) => Ok(Some((high * 16 + low) as u8)),
+                _ => Err(format!(
+                    "'{}{}' is not a hex byte (use text: or re: for other patterns)",
+                    high, low
+                )),
+            },
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Streaming search for a [`Pattern`].
+///
+/// Matches don't overlap: after one is found, the search goes on from its
+/// end. Regular expressions find the longest of the matches starting
+/// leftmost.
+#[derive(Debug)]
+pub struct Finder<'a> {
+    state: State<'a>,
+}
+
+#[derive(Debug)]
+enum State<'a> {
+    Bytes {
+        pattern: &'a [Option<u8>],
+        tail: Vec<u8>, // Bytes where a match may still start
+        base: u64,     // Offset of the first byte in 'tail'
+    },
+    Regex(Matcher<'a>),
+}
+
+impl<'a> Finder<'a> {
+    /// Starts searching, counting offsets from `start`.
+    pub fn new(pattern: &'a Pattern, start: u64) -> Self {
+        let state = match &pattern.kind {
+            Kind::Bytes(bytes) => State::Bytes {
+                pattern: bytes,
+                tail: Vec::new(),
+                base: start,
+            },
+            Kind::Regex(regex) => State::Regex(Matcher::new(regex, start)),
+        };
+        Finder { state }
+    }
+
+    /// Searches the next bytes of input, adding the matches found to `found`.
+    pub fn feed(&mut self, chunk: &[u8], found: &mut Vec<Range<u64>>) {
+        match &mut self.state {
+            State::Bytes {
+                pattern,
+                tail,
+                base,
+            } => {
+                tail.extend_from_slice(chunk);
+                // Jump between occurrences of the first fixed byte
+                let (skip, first) = pattern
+                    .iter()
+                    .enumerate()
+                    .find_map(|(i, b)| b.map(|b| (i, b)))
+                    .unwrap();
+                let mut at = 0;
+                while at + pattern.len() <= tail.len() {
+                    let Some(next) = tail[at + skip..].iter().position(|&b| b == first) else {
+                        at = tail.len() - skip;
+                        break;
+                    };
+                    at += next;
+                    if at + pattern.len() > tail.len() {
+                        break;
+                    }
+                    let window = &tail[at..at + pattern.len()];
+                    if window
+                        .iter()
+                        .zip(pattern.iter())
+                        .all(|(b, p)| p.is_none_or(|p| p == *b))
+                    {
+                        found.push(*base + at as u64..*base + (at + pattern.len()) as u64);
+                        at += pattern.len();
+                    } else {
+                        at += 1;
+                    }
+                }
+                // Only the bytes where a match may start are kept
+                let at = at.min(tail.len());
+                tail.drain(..at);
+                *base += at as u64;
+            }
+            State::Regex(matcher) => matcher.feed(chunk, found),
+        }
+    }
+
+    /// Adds the last matches at the end of input to `found`.
+    pub fn finish(&mut self, found: &mut Vec<Range<u64>>) {
+        if let State::Regex(matcher) = &mut self.state {
+            matcher.finish(found);
+        }
+    }
+
+    /// Offset before which no more matches can start or end.
+    pub fn settled(&self) -> u64 {
+        match &self.state {
+            State::Bytes { base, .. } => *base,
+            State::Regex(matcher) => matcher.settled(),
+        }
+    }
+}
+
+/// Settings for printing the lines of a dump that hold matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Search {
+    /// What to look for.
+    pub pattern: Pattern,
+    /// Lines shown before each line with a match.
+    pub before: usize,
+    /// Lines shown after each line with a match.
+    pub after: usize,
+}
+
+impl Search {
+    /// Searches `reader`, positioned [`skip`](Config::skip) bytes into the
+    /// input, and writes the lines of the dump holding matches in the layout
+    /// of `config`. Each match is announced by a line with its offsets just
+    /// above the dump line where it starts, and highlighted when `config`
+    /// has a theme; without one, that line is all that marks it. Groups of lines that aren't adjacent are separated by
+    /// `--`. Returns the number of matches.
+    pub fn write<R: Read, W: Write>(
+        &self,
+        config: &Config,
+        reader: R,
+        mut writer: W,
+    ) -> crate::Result<usize> {
+        let program = config.format.compile()?;
+        let size = program.block_size();
+        let sgr = config.theme.as_ref().map_or("", |theme| &theme.matched[..]);
+        let mut reader = reader.take(config.length.unwrap_or(u64::MAX));
+        let mut finder = Finder::new(&self.pattern, config.skip);
+        let mut chunk = vec![0; CHUNK];
+        let mut block = vec![0; size];
+
+        let mut found = Vec::new(); // Matches just found
+        let mut matches: VecDeque<Range<u64>> = VecDeque::new(); // Matches not behind the current line
+        let mut count = 0;
+        let mut pending: Vec<u8> = Vec::new(); // Bytes read but not shown or dropped yet
+        let mut offset = config.skip; // Offset of the first pending byte, the start of a line
+        let mut before: VecDeque<(u64, Vec<u8>)> = VecDeque::new(); // Lines kept for context
+        let mut after = 0; // Context lines still to show after a match
+        let mut shown_end: Option<u64> = None; // End of the last line shown
+
+        // Function to render one line, highlighting the matches on it
+        let mut render = |writer: &mut W, line: &[u8], at: u64, marks: &[(Range<u64>, &str)]| {
+            block[..line.len()].copy_from_slice(line);
+            block[line.len()..].fill(0); // Conversions overlapping the end of input see zeros
+            let palette = Palette {
+                theme: config.theme.as_ref(),
+                marks,
+                ..Default::default()
+            };
+            program.render_block(writer, &block, line.len(), at, &palette)
+        };
+
+        loop {
+            let n = crate::read_line(&mut reader, &mut chunk)?;
+            if n == 0 {
+                finder.finish(&mut found);
+            } else {
+                finder.feed(&chunk[..n], &mut found);
+            }
+            count += found.len();
+            matches.extend(found.drain(..));
+            pending.extend_from_slice(&chunk[..n]);
+
+            // Lines no match still to be found can reach
+            let settled = if n == 0 { u64::MAX } else { finder.settled() };
+            let mut done = 0;
+            while pending.len() - done >= size || (n == 0 && done < pending.len()) {
+                let line = &pending[done..(done + size).min(pending.len())];
+                let end = offset + line.len() as u64;
+                if end > settled {
+                    break;
+                }
+                while matches.front().is_some_and(|m| m.end <= offset) {
+                    matches.pop_front();
+                }
+                let hits: Vec<(Range<u64>, &str)> = matches
+                    .iter()
+                    .take_while(|m| m.start < end)
+                    .map(|m| (m.clone(), sgr))
+                    .collect();
+
+                if !hits.is_empty() {
+                    let first = before.front().map_or(offset, |(at, _)| *at);
+                    if shown_end.is_some_and(|shown| first > shown) {
+                        writeln!(writer, "--")?;
+                    }
+                    for (at, line) in before.drain(..) {
+                        render(&mut writer, &line, at, &[])?;
+                    }
+                    for (m, _) in hits.iter().filter(|(m, _)| m.start >= offset) {
+                        let plural = if m.end - m.start == 1 { "" } else { "s" };
+                        writeln!(
+                            writer,
+                            "match {:08x}-{:08x} ({} byte{})",
+                            m.start,
+                            m.end - 1,
+                            m.end - m.start,
+                            plural
+                        )?;
+                    }
+                    render(&mut writer, line, offset, &hits)?;
+                    shown_end = Some(end);
+                    after = self.after;
+                } else if after > 0 {
+                    render(&mut writer, line, offset, &[])?;
+                    shown_end = Some(end);
+                    after -= 1;
+                } else if self.before > 0 {
+                    if before.len() == self.before {
+                        before.pop_front();
+                    }
+                    before.push_back((offset, line.to_vec()));
+                }
+                done += line.len();
+                offset = end;
+            }
+            pending.drain(..done);
+            if n == 0 {
+                break;
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Format, Mode, Theme};
+
+    // Reader that hands out a few bytes per call, so matches straddle chunks
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.1.min(buf.len()).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    // Helper to find the matches of a pattern, feeding the input in chunks of 'size' bytes
+    fn find(spec: &str, input: &[u8], size: usize) -> Vec<Range<u64>> {
+        let pattern = Pattern::parse(spec).unwrap();
+        let mut finder = Finder::new(&pattern, 0);
+        let mut found = Vec::new();
+        for chunk in input.chunks(size) {
+            finder.feed(chunk, &mut found);
+        }
+        finder.finish(&mut found);
+        found
+    }
+
+    #[test]
+    fn test_patterns() {
+        // Hex with wildcards, text, UTF-16 and regular expressions, whatever the chunk size
+        let input = b"\x7fELF\x02\x01 MZ\x90\x00 h\0e\0y\0 \0h\0e\0y hey";
+        for size in [1, 2, 3, 5, input.len()] {
+            assert_eq!(find("7f454c46", input, size), vec![0..4]);
+            assert_eq!(find("hex:4d 5a ?? 00", input, size), vec![7..11]);
+            assert_eq!(find("text:hey", input, size), vec![26..29]);
+            assert_eq!(find("utf16:hey", input, size), vec![12..18]);
+            assert_eq!(find("utf16be:hey", input, size), vec![19..25]);
+            assert_eq!(
+                find(r"re:h\x00?e\x00?y", input, size),
+                vec![12..17, 20..25, 26..29]
+            );
+        }
+        assert_eq!(find("aaaa", &[0xaa; 9], 4), vec![0..2, 2..4, 4..6, 6..8]);
+    }
+
+    #[test]
+    fn test_bad_patterns() {
+        // Patterns that can't be searched for are rejected
+        for spec in [
+            "", "hex:", "abc", "zz", "?? ??", "text:", "utf16:", "utf16be:", "re:", "re:()",
+            "re:[x",
+        ] {
+            assert!(Pattern::parse(spec).is_err(), "{}", spec);
+        }
+    }
+
+    // Helper to search a small input with the canonical layout
+    fn search(spec: &str, before: usize, after: usize, input: &[u8]) -> (String, usize) {
+        let search = Search {
+            pattern: Pattern::parse(spec).unwrap(),
+            before,
+            after,
+        };
+        let config = Config {
+            format: Mode::Canonical.format(),
+            ..Config::default()
+        };
+        let mut out = Vec::new();
+        let count = search.write(&config, Trickle(input, 7), &mut out).unwrap();
+        (String::from_utf8(out).unwrap(), count)
+    }
+
+    #[test]
+    fn test_search_context() {
+        // Lines with matches are announced and shown with their context, groups split by '--'
+        let mut input = vec![b'.'; 16 * 8];
+        input[16 * 2 + 14..16 * 3 + 2].copy_from_slice(b"KEY!");
+        input[16 * 7..16 * 7 + 3].copy_from_slice(b"KEY");
+        let (text, count) = search("text:KEY", 1, 1, &input);
+        let expected = "\
+00000010  2e 2e 2e 2e 2e 2e 2e 2e  2e 2e 2e 2e 2e 2e 2e 2e  |................|
+match 0000002e-00000030 (3 bytes)
+00000020  2e 2e 2e 2e 2e 2e 2e 2e  2e 2e 2e 2e 2e 2e 4b 45  |..............KE|
+00000030  59 21 2e 2e 2e 2e 2e 2e  2e 2e 2e 2e 2e 2e 2e 2e  |Y!..............|
+00000040  2e 2e 2e 2e 2e 2e 2e 2e  2e 2e 2e 2e 2e 2e 2e 2e  |................|
+--
+00000060  2e 2e 2e 2e 2e 2e 2e 2e  2e 2e 2e 2e 2e 2e 2e 2e  |................|
+match 00000070-00000072 (3 bytes)
+00000070  4b 45 59 2e 2e 2e 2e 2e  2e 2e 2e 2e 2e 2e 2e 2e  |KEY.............|
+";
+        assert_eq!(text, expected);
+        assert_eq!(count, 2);
+        assert_eq!(search("text:nope", 2, 2, &input), (String::new(), 0));
+    }
+
+    #[test]
+    fn test_search_highlight() {
+        // Matched bytes are painted in the match color, in any layout, counting from the skipped offset
+        let search = Search {
+            pattern: Pattern::parse("re:b+").unwrap(),
+            before: 0,
+            after: 0,
+        };
+        let config = Config {
+            skip: 2,
+            format: Format::parse(r#""%_ad: " 4/1 "%02x" "\n""#).unwrap(),
+            theme: Some(Theme::parse("offset=:nul=:print=:space=:ctrl=:ff=:high=")),
+            ..Config::default()
+        };
+        let mut out = Vec::new();
+        let count = search.write(&config, &b"bbbaab"[..], &mut out).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "match 00000002-00000004 (3 bytes)\n\
+             2: \x1b[30;43m62\x1b[0m\x1b[30;43m62\x1b[0m\x1b[30;43m62\x1b[0m61\n\
+             match 00000007-00000007 (1 byte)\n\
+             6: 61\x1b[30;43m62\x1b[0m    \n"
+        );
+    }
+}
@@ -0,0 +1,1819 @@
+//! Parser and interpreter for the hexdump format language (`-e` and `-f`).
+//!
+//! A format is a list of format strings. Each format string is a sequence of
+//! format units of the form `[iterations][/byte_count] "text"`, where the
+//! quoted text mixes literal characters with printf-style conversions:
+//!
+//! ```text
+//! "%08.8_ax  " 8/1 "%02x " "  " 8/1 "%02x " "  |" 16/1 "%_p" "|\n"
+//! ```
+//!
+//! Every format string is applied in turn to the same block of input. The
+//! block size is the largest number of bytes consumed by any format string.
+//! Besides the usual `%d %i %o %u %x %X %c %e %E %f %g %G %s` conversions the
+//! language supports `%_a[dox]` (offset of the next byte), `%_A[dox]` (offset
+//! after the last byte, printed once at the end), `%_c` (C-escaped
+//! character), `%_p` (printable character or `.`) and `%_u` (US-ASCII control
+//! names).
+
+use std::error; // Error trait
+use std::fmt; // Display formatting
+use std::io::{self, Write}; // I/O operations
+
+use crate::color::{Palette, Theme}; // Byte class colors
+use crate::od; // od output types
+
+// Group sizes supported by the grouped hex layout
+pub(crate) const GROUP_SIZES: [usize; 5] = [1, 2, 4, 8, 16];
+
+// Most bytes a format may read per block, as a whole block is held in memory
+const MAX_BLOCK: usize = 4 << 20;
+
+// util-linux `hexdump -C` layout, one format string per line
+const CANONICAL: &str = r#"
+"%08.8_Ax\n"
+"%08.8_ax  " 8/1 "%02x " "  " 8/1 "%02x "
+"  |" 16/1 "%_p" "|\n"
+"#;
+
+// util-linux `-b`, `-c`, `-d`, `-o` and `-x` layouts, each with 7-digit offsets
+const OCTAL_BYTES: &str = r#"
+"%07.7_Ax\n"
+"%07.7_ax " 16/1 "%03o " "\n"
+"#;
+const CHARS: &str = r#"
+"%07.7_Ax\n"
+"%07.7_ax " 16/1 "%3_c " "\n"
+"#;
+const DECIMAL_WORDS: &str = r#"
+"%07.7_Ax\n"
+"%07.7_ax " 8/2 "  %05u " "\n"
+"#;
+const OCTAL_WORDS: &str = r#"
+"%07.7_Ax\n"
+"%07.7_ax " 8/2 " %06o " "\n"
+"#;
+const HEX_WORDS: &str = r#"
+"%07.7_Ax\n"
+"%07.7_ax " 8/2 "   %04x " "\n"
+"#;
+
+/// Error produced when a format string can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    message: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        ParseError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Byte order used to read multi-byte values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Endian {
+    /// Least significant byte first (the default, as on x86).
+    #[default]
+    Little,
+    /// Most significant byte first, as in network captures.
+    Big,
+    /// The byte order of the machine running the dump.
+    Native,
+}
+
+impl Endian {
+    // Resolves 'Native' to the byte order of this machine
+    pub(crate) fn resolve(self) -> Endian {
+        match self {
+            Endian::Native if cfg!(target_endian = "big") => Endian::Big,
+            Endian::Native => Endian::Little,
+            endian => endian,
+        }
+    }
+}
+
+/// Built-in layouts, each defined by a predefined format string.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Offset followed by two-byte little-endian hex words (`0100 0302`).
+    /// A word running past the end of the input shows only the bytes that
+    /// exist, so `00 01 02` is shown as `0100 02`.
+    #[default]
+    Words,
+    /// util-linux `hexdump -C`: offset, 16 hex bytes in two groups of 8 and
+    /// a `|...|` column of printable ASCII, ending with the total length.
+    Canonical,
+    /// util-linux `hexdump -b`: one-byte octal.
+    OctalBytes,
+    /// util-linux `hexdump -c`: one-byte characters, with C escapes.
+    Chars,
+    /// util-linux `hexdump -d`: two-byte unsigned decimal.
+    DecimalWords,
+    /// util-linux `hexdump -o`: two-byte octal.
+    OctalWords,
+    /// util-linux `hexdump -x`: two-byte hexadecimal.
+    HexWords,
+}
+
+impl Mode {
+    /// The format implementing this layout.
+    pub fn format(self) -> Format {
+        match self {
+            Mode::Words => Format::grouped(2).expect("2 is a valid group size"),
+            Mode::Canonical => builtin(CANONICAL),
+            Mode::OctalBytes => builtin(OCTAL_BYTES),
+            Mode::Chars => builtin(CHARS),
+            Mode::DecimalWords => builtin(DECIMAL_WORDS),
+            Mode::OctalWords => builtin(OCTAL_WORDS),
+            Mode::HexWords => builtin(HEX_WORDS),
+        }
+    }
+}
+
+impl From<Mode> for Format {
+    fn from(mode: Mode) -> Self {
+        mode.format()
+    }
+}
+
+// Checks an xxd column count, which xxd limits to 256
+fn check_cols(cols: usize) -> Result<(), ParseError> {
+    if (1..=256).contains(&cols) {
+        Ok(())
+    } else {
+        Err(ParseError::new(format!(
+            "bad column count {} (expected 1 to 256)",
+            cols
+        )))
+    }
+}
+
+// Parses one of the predefined formats, which are known to be valid
+fn builtin(text: &str) -> Format {
+    Format::parse_file(text).expect("predefined format is valid")
+}
+
+/// A parsed format: one or more format strings applied to each input block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Format {
+    strings: Vec<FormatString>,
+    end: End,
+}
+
+// When the end offset ('_A') is printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum End {
+    Dumped, // Only after some input was shown (util-linux), with a plain one after a squeezed tail
+    Always, // Always, even for empty input, and only through '_A' (od)
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Mode::default().format()
+    }
+}
+
+impl Format {
+    /// Parses a single format string, as given to `-e`.
+    ///
+    /// ```
+    /// use hexdump::format::Format;
+    /// assert!(Format::parse(r#""%08.8_ax  " 8/1 "%02x " "\n""#).is_ok());
+    /// assert!(Format::parse(r#""%y""#).is_err());
+    /// ```
+    pub fn parse(text: &str) -> Result<Format, ParseError> {
+        Ok(Format {
+            strings: vec![parse_string(text)?],
+            end: End::Dumped,
+        })
+    }
+
+    /// Parses the contents of a format file, as given to `-f`: one format
+    /// string per line, ignoring blank lines and lines starting with `#`.
+    pub fn parse_file(text: &str) -> Result<Format, ParseError> {
+        let mut strings = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let string = parse_string(line)
+                .map_err(|e| ParseError::new(format!("line {}: {}", number + 1, e)))?;
+            strings.push(string);
+        }
+        Ok(Format {
+            strings,
+            end: End::Dumped,
+        })
+    }
+
+    /// Checks that the format reads some input, as a dump needs, or
+    /// describes what is wrong. A string reading nothing, such as
+    /// `"%_ad\n"`, is only of use next to others that do.
+    ///
+    /// ```
+    /// use hexdump::format::Format;
+    /// let mut format = Format::parse(r#""%_ad\n""#).unwrap();
+    /// assert!(format.check().is_err());
+    /// format.append(Format::parse(r#"16/1 "%02x" "\n""#).unwrap());
+    /// assert!(format.check().is_ok());
+    /// ```
+    pub fn check(&self) -> Result<(), ParseError> {
+        if self.strings.iter().all(|string| string.size() == 0) {
+            return Err(ParseError::new("format does not consume any input"));
+        }
+        Ok(())
+    }
+
+    /// The grouped hex layout: the offset, then 16 bytes shown as numbers of
+    /// `size` bytes each (1, 2, 4, 8 or 16), as in `00000000 0100 0302`.
+    ///
+    /// A final group that runs past the end of the input shows only the bytes
+    /// that exist, so `00 01 02` is shown as `0100 02`.
+    pub fn grouped(size: usize) -> Result<Format, ParseError> {
+        if !GROUP_SIZES.contains(&size) {
+            return Err(ParseError::new(format!(
+                "bad group size {} (expected 1, 2, 4, 8 or 16)",
+                size
+            )));
+        }
+        let text = format!(
+            r#""%08.8_ax" {}/{} " %0{}x" "\n""#,
+            16 / size,
+            size,
+            size * 2
+        );
+        // Lines stop at the last byte instead of being padded with blanks
+        Ok(builtin(&text).truncated())
+    }
+
+    /// The `xxd` layout: `00000000: 4865 6c6c  Hell`, with `cols` bytes per
+    /// line split into groups of `group` bytes (0 for a single group).
+    pub fn xxd(cols: usize, group: usize, upper: bool) -> Result<Format, ParseError> {
+        check_cols(cols)?;
+        let group = if group == 0 { cols } else { group.min(cols) };
+        let hex = if upper { "%02X" } else { "%02x" };
+
+        // Hex column: a blank before each group, then the ASCII column
+        let mut text = String::from("\"%08.8_ax:\"");
+        let mut done = 0;
+        while done < cols {
+            let n = group.min(cols - done);
+            text.push_str(&format!(r#" " " {}/1 "{}""#, n, hex));
+            done += n;
+        }
+        text.push_str(&format!("\n\"  \" {}/1 \"%_p\" \"\\n\"", cols));
+        Ok(builtin(&text))
+    }
+
+    /// The `xxd -p` layout: `cols` bytes per line as continuous hex digits.
+    pub fn xxd_plain(cols: usize, upper: bool) -> Result<Format, ParseError> {
+        check_cols(cols)?;
+        let hex = if upper { "%02X" } else { "%02x" };
+        Ok(builtin(&format!(r#"{}/1 "{}" "\n""#, cols, hex)).truncated())
+    }
+
+    /// The `od` layout: one line per output type for each block of `width`
+    /// bytes (16 by default), the first starting with the offset, as in
+    /// `0000000 000401 001402`. Without types, two-byte octal words are shown.
+    ///
+    /// Like GNU od, values are aligned across the lines of a block, a final
+    /// partial value is shown as if the input were padded with zeros, and the
+    /// end offset is printed even for empty input.
+    pub fn od(
+        address: od::Address,
+        types: &[od::Type],
+        width: Option<usize>,
+    ) -> Result<Format, ParseError> {
+        let types = if types.is_empty() {
+            &[od::Type::DEFAULT][..]
+        } else {
+            types
+        };
+
+        // Blocks hold a whole number of values of every type
+        let lcm = types
+            .iter()
+            .fold(1, |lcm, ty| lcm / gcd(lcm, ty.size) * ty.size);
+        let width = match width {
+            None if lcm < 16 => 16 / lcm * lcm,
+            None => lcm,
+            Some(width) if width > MAX_BLOCK => {
+                return Err(ParseError::new(format!(
+                    "invalid width {} (at most {})",
+                    width, MAX_BLOCK
+                )))
+            }
+            Some(width) if width > 0 && width.is_multiple_of(lcm) => width,
+            Some(width) => {
+                return Err(ParseError::new(format!(
+                    "invalid width {} (expected a multiple of {})",
+                    width, lcm
+                )))
+            }
+        };
+        // Room given to each line, so that values line up across the lines
+        let line_width = types
+            .iter()
+            .map(|ty| (ty.field_width() + 1) * (width / ty.size))
+            .max()
+            .unwrap_or(0);
+
+        let radix = match address {
+            od::Address::Decimal => Some(Radix::Decimal),
+            od::Address::Octal => Some(Radix::Octal),
+            od::Address::Hex => Some(Radix::Hex { upper: false }),
+            od::Address::None => None,
+        };
+        let address_spec = Spec {
+            zero: true,
+            width: Some(address.width()),
+            ..Spec::default()
+        };
+        let endian = Endian::Native.resolve();
+
+        let mut strings = Vec::new();
+        for (i, ty) in types.iter().enumerate() {
+            // The offset starts the first line, the others are indented to match
+            let mut units = vec![match radix {
+                Some(radix) if i == 0 => Unit::once(
+                    0,
+                    Item::conv(Conv {
+                        spec: address_spec.clone(),
+                        kind: Kind::Address(radix),
+                        size: 0,
+                    }),
+                ),
+                _ => Unit::once(0, Item::text(&" ".repeat(address.width()))),
+            }];
+
+            let (kind, precision) = match ty.kind {
+                od::Kind::Named => (Kind::Ascii, None),
+                od::Kind::Char => (Kind::Escaped, None),
+                od::Kind::Signed => (Kind::Signed, None),
+                od::Kind::Float => (Kind::Float(FloatStyle::Shortest), None),
+                od::Kind::Octal => (Kind::Unsigned(Radix::Octal), Some(ty.field_width())),
+                od::Kind::Unsigned => (Kind::Unsigned(Radix::Decimal), None),
+                od::Kind::Hex => (
+                    Kind::Unsigned(Radix::Hex { upper: false }),
+                    Some(ty.field_width()),
+                ),
+            };
+
+            // Spare room is spread over the values the way GNU od does it
+            let fields = width / ty.size;
+            let spare = line_width - ty.field_width() * fields;
+            for field in 0..fields {
+                let extra =
+                    spare * (fields - field) / fields - spare * (fields - field - 1) / fields;
+                let spec = Spec {
+                    width: Some(ty.field_width() + extra),
+                    precision,
+                    ..Spec::default()
+                };
+                units.push(Unit::once(
+                    ty.size,
+                    Item::conv(Conv {
+                        spec,
+                        kind,
+                        size: ty.size,
+                    }),
+                ));
+            }
+
+            // With a trailer, missing values are blanked to keep it in its column
+            let tail = if ty.trailer {
+                Tail::Pad
+            } else {
+                units.push(Unit::once(0, Item::text("\n")));
+                Tail::Skip
+            };
+            if let (Some(radix), 0) = (radix, i) {
+                let end = Conv {
+                    spec: address_spec.clone(),
+                    kind: Kind::EndAddress(radix),
+                    size: 0,
+                };
+                units.push(Unit {
+                    reps: 1,
+                    explicit_reps: true,
+                    size: 0,
+                    items: vec![Item::conv(end), Item::text("\n")],
+                });
+            }
+            strings.push(FormatString {
+                units,
+                tail,
+                endian,
+            });
+
+            if ty.trailer {
+                // The printable characters of the block, as in '>abc<'
+                let printable = Conv {
+                    spec: Spec::default(),
+                    kind: Kind::Printable,
+                    size: 1,
+                };
+                let mut chars = Unit::once(1, Item::conv(printable));
+                chars.reps = width;
+                strings.push(FormatString {
+                    units: vec![
+                        Unit::once(0, Item::text("  >")),
+                        chars,
+                        Unit::once(0, Item::text("<\n")),
+                    ],
+                    tail: Tail::Skip,
+                    endian,
+                });
+            }
+        }
+
+        Ok(Format {
+            strings,
+            end: End::Always,
+        })
+    }
+
+    /// Sets the byte order used by every multi-byte conversion.
+    pub fn set_endian(&mut self, endian: Endian) {
+        for string in &mut self.strings {
+            string.endian = endian.resolve();
+        }
+    }
+
+    /// Adds the format strings of `other` after those of `self`.
+    pub fn append(&mut self, other: Format) {
+        self.strings.extend(other.strings);
+    }
+
+    // Marks every format string to stop at the end of input instead of blank-padding
+    pub(crate) fn truncated(mut self) -> Self {
+        for string in &mut self.strings {
+            string.tail = Tail::Truncate;
+        }
+        self
+    }
+
+    // Marks every format string to keep columns aligned at the end of input,
+    // showing a partial integer where its bytes would be, like 'xxd -e'
+    pub(crate) fn aligned(mut self) -> Self {
+        for string in &mut self.strings {
+            string.tail = Tail::Align;
+        }
+        self
+    }
+
+    // Resolves iteration counts against the block size, ready for rendering
+    pub(crate) fn compile(&self) -> Result<Program, ParseError> {
+        self.check()?;
+        let block_size = self
+            .strings
+            .iter()
+            .map(FormatString::size)
+            .max()
+            .unwrap_or(0);
+
+        let mut strings = self.strings.clone();
+        for string in &mut strings {
+            // Like util-linux, the last unit is repeated to fill the block unless its count was given
+            let size = string.size();
+            if let Some(unit) = string.units.last_mut() {
+                if size < block_size && !unit.explicit_reps && unit.size > 0 {
+                    unit.reps += (block_size - size) / unit.size;
+                }
+            }
+        }
+
+        Ok(Program {
+            strings,
+            block_size,
+            end: self.end,
+        })
+    }
+}
+
+// One format string: a sequence of units applied to the same block
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FormatString {
+    units: Vec<Unit>,
+    tail: Tail,     // How conversions past the end of input are shown
+    endian: Endian, // Byte order of multi-byte conversions
+}
+
+impl FormatString {
+    // Number of bytes consumed per block, not counting the '_A' end units
+    fn size(&self) -> usize {
+        self.units
+            .iter()
+            .take_while(|unit| !unit.is_end())
+            .map(|unit| unit.reps * unit.size)
+            .sum()
+    }
+}
+
+// How a format string shows conversions that run past the end of input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tail {
+    Pad,      // Blank-pad them to their width (util-linux)
+    Truncate, // Skip them, showing a partial integer as a narrower one
+    Skip,     // Skip them entirely; a partial value sees zeros (od)
+    Align,    // Blank-pad them, and show a partial integer narrower but in its full width
+}
+
+// One format unit: 'reps' iterations over 'items', each iteration consuming 'size' bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Unit {
+    reps: usize,
+    explicit_reps: bool,
+    size: usize,
+    items: Vec<Item>,
+}
+
+impl Unit {
+    // A unit shown once per block, consuming 'size' bytes
+    fn once(size: usize, item: Item) -> Unit {
+        Unit {
+            reps: 1,
+            explicit_reps: true,
+            size,
+            items: vec![item],
+        }
+    }
+
+    // Units containing '_A' are only printed once, after the last block
+    fn is_end(&self) -> bool {
+        self.items.iter().any(|item| {
+            matches!(
+                item.conv,
+                Some(Conv {
+                    kind: Kind::EndAddress(_),
+                    ..
+                })
+            )
+        })
+    }
+}
+
+// Literal text followed by an optional conversion
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Item {
+    text: Vec<u8>,
+    conv: Option<Conv>,
+}
+
+impl Item {
+    // Literal text on its own
+    fn text(text: &str) -> Item {
+        Item {
+            text: text.as_bytes().to_vec(),
+            conv: None,
+        }
+    }
+
+    // A conversion without leading text
+    fn conv(conv: Conv) -> Item {
+        Item {
+            text: Vec::new(),
+            conv: Some(conv),
+        }
+    }
+}
+
+// A single '%' conversion and the number of bytes it consumes
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Conv {
+    spec: Spec,
+    kind: Kind,
+    size: usize,
+}
+
+// printf flags, field width and precision
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Spec {
+    minus: bool,
+    plus: bool,
+    space: bool,
+    hash: bool,
+    zero: bool,
+    width: Option<usize>,
+    precision: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Radix {
+    Octal,
+    Decimal,
+    Hex { upper: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FloatStyle {
+    Exp { upper: bool },
+    Fixed,
+    General { upper: bool },
+    Shortest, // Fewest '%g' digits that read back as the same value (od)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Signed,
+    Unsigned(Radix),
+    Float(FloatStyle),
+    Char,
+    Str,
+    Address(Radix),
+    EndAddress(Radix),
+    Escaped,
+    Printable,
+    Named,
+    Ascii, // od's named characters, ignoring the high bit
+}
+
+impl Kind {
+    // Bytes consumed when the unit gives no byte count
+    fn default_size(self) -> Option<usize> {
+        match self {
+            Kind::Signed | Kind::Unsigned(_) => Some(4),
+            Kind::Float(_) => Some(8),
+            Kind::Char | Kind::Escaped | Kind::Printable | Kind::Named | Kind::Ascii => Some(1),
+            Kind::Address(_) | Kind::EndAddress(_) => Some(0),
+            Kind::Str => None, // Taken from the precision
+        }
+    }
+
+    // Whether the conversion reads an integer value
+    fn is_integer(self) -> bool {
+        matches!(self, Kind::Signed | Kind::Unsigned(_))
+    }
+
+    // Whether the conversion can read 'size' bytes
+    fn accepts_size(self, size: usize) -> bool {
+        match self {
+            Kind::Signed | Kind::Unsigned(_) => matches!(size, 1 | 2 | 4 | 8 | 16),
+            Kind::Float(_) => matches!(size, 4 | 8),
+            Kind::Char | Kind::Escaped | Kind::Printable | Kind::Named | Kind::Ascii => size == 1,
+            Kind::Str => size > 0,
+            Kind::Address(_) | Kind::EndAddress(_) => true,
+        }
+    }
+}
+
+// Function to parse one format string into its units
+fn parse_string(text: &str) -> Result<FormatString, ParseError> {
+    let mut units = Vec::new();
+    let mut rest = text.trim_start();
+
+    while !rest.is_empty() {
+        // Optional iteration count
+        let (reps, after) = take_number(rest)?;
+        rest = after.trim_start();
+
+        // Optional byte count after a slash
+        let mut bcnt = None;
+        if let Some(after) = rest.strip_prefix('/') {
+            let (count, after) = take_number(after.trim_start())?;
+            if count.is_none() {
+                return Err(ParseError::new("missing byte count after '/'"));
+            }
+            bcnt = count;
+            rest = after.trim_start();
+        }
+
+        // Quoted format text
+        let body = rest
+            .strip_prefix('"')
+            .ok_or_else(|| ParseError::new(format!("expected '\"' at '{}'", rest)))?;
+        let end = closing_quote(body)
+            .ok_or_else(|| ParseError::new(format!("unterminated format '\"{}'", body)))?;
+        units.push(parse_unit(reps, bcnt, &unescape(&body[..end]))?);
+        rest = body[end + 1..].trim_start();
+    }
+
+    if units.is_empty() {
+        return Err(ParseError::new("empty format string"));
+    }
+    let string = FormatString {
+        units,
+        tail: Tail::Pad,
+        endian: Endian::Little,
+    };
+    // Counts are at most MAX_BLOCK, so the size can't overflow
+    if string.size() > MAX_BLOCK {
+        return Err(ParseError::new(format!(
+            "format reads {} bytes at a time (at most {})",
+            string.size(),
+            MAX_BLOCK
+        )));
+    }
+    Ok(string)
+}
+
+// Splits a leading decimal number off 's', rejecting counts no block can hold
+fn take_number(s: &str) -> Result<(Option<usize>, &str), ParseError> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if end == 0 {
+        return Ok((None, s));
+    }
+    match s[..end].parse() {
+        Ok(count) if count <= MAX_BLOCK => Ok((Some(count), &s[end..])),
+        _ => Err(ParseError::new(format!(
+            "count {} is too large (at most {})",
+            &s[..end],
+            MAX_BLOCK
+        ))),
+    }
+}
+
+// Finds the closing quote of a format, skipping escaped characters
+fn closing_quote(body: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in body.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+// Replaces C escape sequences in format text
+fn unescape(text: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('a') => '\x07',
+                Some('b') => '\x08',
+                Some('f') => '\x0c',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('v') => '\x0b',
+                Some(other) => other, // '\\', '\"' and anything else stand for themselves
+                None => '\\',
+            },
+            c => c,
+        };
+        let mut buf = [0; 4];
+        out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    }
+    out
+}
+
+// Function to split unit text into literal text and conversions
+fn parse_unit(reps: Option<usize>, bcnt: Option<usize>, text: &[u8]) -> Result<Unit, ParseError> {
+    let mut items = Vec::new();
+    let mut literal = Vec::new();
+    let mut i = 0;
+
+    while i < text.len() {
+        if text[i] != b'%' {
+            literal.push(text[i]);
+            i += 1;
+            continue;
+        }
+        if text.get(i + 1) == Some(&b'%') {
+            literal.push(b'%'); // '%%' is a literal percent sign
+            i += 2;
+            continue;
+        }
+        let (conv, len) = parse_conv(&text[i + 1..])?;
+        items.push(Item {
+            text: std::mem::take(&mut literal),
+            conv: Some(conv),
+        });
+        i += 1 + len;
+    }
+    if !literal.is_empty() {
+        items.push(Item {
+            text: literal,
+            conv: None,
+        });
+    }
+
+    // A byte count applies to the one conversion that reads input
+    let readers = items
+        .iter()
+        .filter_map(|item| item.conv.as_ref())
+        .filter(|conv| !matches!(conv.kind, Kind::Address(_) | Kind::EndAddress(_)))
+        .count();
+    if bcnt.is_some() && readers > 1 {
+        return Err(ParseError::new(
+            "byte count with multiple conversion characters",
+        ));
+    }
+
+    let mut size = 0;
+    for conv in items.iter_mut().filter_map(|item| item.conv.as_mut()) {
+        if matches!(conv.kind, Kind::Address(_) | Kind::EndAddress(_)) {
+            continue;
+        }
+        conv.size = match (bcnt, conv.kind) {
+            (Some(n), _) => n,
+            (None, Kind::Str) => conv
+                .spec
+                .precision
+                .ok_or_else(|| ParseError::new("%s requires a precision or a byte count"))?,
+            (None, kind) => kind.default_size().unwrap_or(0),
+        };
+        if !conv.kind.accepts_size(conv.size) {
+            return Err(ParseError::new(format!(
+                "bad byte count {} for conversion",
+                conv.size
+            )));
+        }
+        size += conv.size;
+    }
+
+    Ok(Unit {
+        reps: reps.unwrap_or(1),
+        explicit_reps: reps.is_some(),
+        size: bcnt.unwrap_or(size),
+        items,
+    })
+}
+
+// Function to parse the conversion after a '%', returning it and its length
+fn parse_conv(text: &[u8]) -> Result<(Conv, usize), ParseError> {
+    let mut spec = Spec::default();
+    let mut i = 0;
+
+    // Flags
+    while let Some(&c) = text.get(i) {
+        match c {
+            b'-' => spec.minus = true,
+            b'+' => spec.plus = true,
+            b' ' => spec.space = true,
+            b'#' => spec.hash = true,
+            b'0' => spec.zero = true,
+            _ => break,
+        }
+        i += 1;
+    }
+
+    // Field width and precision
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while text.get(*i).is_some_and(u8::is_ascii_digit) {
+            *i += 1;
+        }
+        std::str::from_utf8(&text[start..*i]).ok()?.parse().ok()
+    };
+    spec.width = digits(&mut i);
+    if text.get(i) == Some(&b'.') {
+        i += 1;
+        spec.precision = Some(digits(&mut i).unwrap_or(0));
+    }
+
+    // Conversion character, or '_' and a hexdump extension
+    let radix = |c: Option<&u8>| match c {
+        Some(b'd') => Some(Radix::Decimal),
+        Some(b'o') => Some(Radix::Octal),
+        Some(b'x') => Some(Radix::Hex { upper: false }),
+        _ => None,
+    };
+    let (kind, len) = match text.get(i) {
+        Some(b'd' | b'i') => (Kind::Signed, 1),
+        Some(b'o') => (Kind::Unsigned(Radix::Octal), 1),
+        Some(b'u') => (Kind::Unsigned(Radix::Decimal), 1),
+        Some(b'x') => (Kind::Unsigned(Radix::Hex { upper: false }), 1),
+        Some(b'X') => (Kind::Unsigned(Radix::Hex { upper: true }), 1),
+        Some(b'c') => (Kind::Char, 1),
+        Some(b's') => (Kind::Str, 1),
+        Some(b'e') => (Kind::Float(FloatStyle::Exp { upper: false }), 1),
+        Some(b'E') => (Kind::Float(FloatStyle::Exp { upper: true }), 1),
+        Some(b'f') => (Kind::Float(FloatStyle::Fixed), 1),
+        Some(b'g') => (Kind::Float(FloatStyle::General { upper: false }), 1),
+        Some(b'G') => (Kind::Float(FloatStyle::General { upper: true }), 1),
+        Some(b'_') => match text.get(i + 1) {
+            Some(b'a') => match radix(text.get(i + 2)) {
+                Some(r) => (Kind::Address(r), 3),
+                None => return Err(ParseError::new("bad conversion character %_a")),
+            },
+            Some(b'A') => match radix(text.get(i + 2)) {
+                Some(r) => (Kind::EndAddress(r), 3),
+                None => return Err(ParseError::new("bad conversion character %_A")),
+            },
+            Some(b'c') => (Kind::Escaped, 2),
+            Some(b'p') => (Kind::Printable, 2),
+            Some(b'u') => (Kind::Named, 2),
+            _ => return Err(ParseError::new("bad conversion character %_")),
+        },
+        Some(&c) => {
+            return Err(ParseError::new(format!(
+                "bad conversion character %{}",
+                c as char
+            )))
+        }
+        None => return Err(ParseError::new("missing conversion character after %")),
+    };
+
+    Ok((
+        Conv {
+            spec,
+            kind,
+            size: 0, // Filled in once the unit's byte count is known
+        },
+        i + len,
+    ))
+}
+
+// A format ready to render blocks of input
+#[derive(Debug)]
+pub(crate) struct Program {
+    strings: Vec<FormatString>,
+    block_size: usize,
+    end: End,
+}
+
+impl Program {
+    // Number of input bytes shown per block
+    pub(crate) fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    // When the end offset is printed
+    pub(crate) fn end(&self) -> End {
+        self.end
+    }
+
+    // Whether the format prints the end offset with '_A'
+    pub(crate) fn has_end(&self) -> bool {
+        self.strings
+            .iter()
+            .any(|string| string.units.iter().any(Unit::is_end))
+    }
+
+    // Renders one block starting at 'address', of which only 'len' bytes are real input
+    pub(crate) fn render_block<W: Write>(
+        &self,
+        writer: &mut W,
+        block: &[u8],
+        len: usize,
+        address: u64,
+        palette: &Palette,
+    ) -> io::Result<()> {
+        let mut out = Vec::new();
+        for string in &self.strings {
+            let mut pos = 0; // Position within the block, restarted for each format string
+            for unit in string.units.iter().take_while(|unit| !unit.is_end()) {
+                for rep in 0..unit.reps {
+                    let last_rep = unit.reps > 1 && rep == unit.reps - 1;
+                    for (i, item) in unit.items.iter().enumerate() {
+                        let conv = match &item.conv {
+                            Some(conv) => conv,
+                            None => {
+                                // Like util-linux, the last iteration drops one trailing blank
+                                let mut text = &item.text[..];
+                                if last_rep && i == unit.items.len() - 1 {
+                                    if let Some((last, init)) = text.split_last() {
+                                        if last.is_ascii_whitespace() {
+                                            text = init;
+                                        }
+                                    }
+                                }
+                                palette.text(&mut out, text);
+                                continue;
+                            }
+                        };
+
+                        // Conversions entirely past the end of input are blanked or skipped
+                        if len < block.len() && pos >= len {
+                            match string.tail {
+                                Tail::Pad | Tail::Align => {
+                                    palette.text(&mut out, &item.text);
+                                    pad(&mut out, &conv.spec, "", "", b"", false);
+                                }
+                                Tail::Truncate | Tail::Skip => {}
+                            }
+                            pos += conv.size;
+                            continue;
+                        }
+
+                        let raw = &block[pos..(pos + conv.size).min(len)];
+                        let address = address + pos as u64;
+
+                        // Truncated layouts show a partial integer as a narrower one
+                        if matches!(string.tail, Tail::Truncate | Tail::Align)
+                            && raw.len() < conv.size
+                            && conv.kind.is_integer()
+                        {
+                            let mut partial = conv.clone();
+                            partial.size = raw.len();
+                            partial.spec.width = conv.spec.width.map(|w| w * raw.len() / conv.size);
+                            // Aligned layouts fill the rest of the field on the side of the missing bytes
+                            let missing = match string.tail {
+                                Tail::Align => {
+                                    conv.spec.width.unwrap_or(0) - partial.spec.width.unwrap_or(0)
+                                }
+                                _ => 0,
+                            };
+                            palette.text(&mut out, &item.text);
+                            if string.endian == Endian::Little {
+                                out.resize(out.len() + missing, b' ');
+                            }
+                            paint_conv(&mut out, palette, conv.kind, address, raw, |out| {
+                                render_conv(out, &partial, string.endian, raw, raw, address)
+                            });
+                            if string.endian == Endian::Big {
+                                out.resize(out.len() + missing, b' ');
+                            }
+                            pos += conv.size;
+                            continue;
+                        }
+
+                        // Other conversions running past the end of input see zero bytes there
+                        let mut data = [0u8; 16];
+                        let n = raw.len().min(data.len());
+                        data[..n].copy_from_slice(&raw[..n]);
+                        let data = &data[..conv.size.min(data.len())];
+
+                        palette.text(&mut out, &item.text);
+                        paint_conv(&mut out, palette, conv.kind, address, raw, |out| {
+                            render_conv(out, conv, string.endian, data, raw, address)
+                        });
+                        pos += conv.size;
+                    }
+                }
+            }
+        }
+        writer.write_all(&out)
+    }
+
+    // Renders the '_A' unit once all input has been shown; like util-linux,
+    // only the last one is used when several formats define one
+    pub(crate) fn render_end<W: Write>(
+        &self,
+        writer: &mut W,
+        address: u64,
+        palette: &Palette,
+    ) -> io::Result<()> {
+        let unit = self
+            .strings
+            .iter()
+            .flat_map(|string| &string.units)
+            .rfind(|unit| unit.is_end());
+        let mut out = Vec::new();
+        for item in unit.map_or(&[][..], |unit| &unit.items) {
+            palette.text(&mut out, &item.text);
+            if let Some(Conv {
+                spec,
+                kind: Kind::Address(radix) | Kind::EndAddress(radix),
+                ..
+            }) = &item.conv
+            {
+                palette.offset(&mut out, address, |out| {
+                    write_uint(out, spec, *radix, address as u128)
+                });
+            }
+        }
+        writer.write_all(&out)
+    }
+}
+
+// Function to write a conversion in its colors: offsets have their own, values follow their bytes
+fn paint_conv(
+    out: &mut Vec<u8>,
+    palette: &Palette,
+    kind: Kind,
+    address: u64,
+    raw: &[u8],
+    write: impl FnOnce(&mut Vec<u8>),
+) {
+    match kind {
+        Kind::Address(_) | Kind::EndAddress(_) => palette.offset(out, address, write),
+        _ => palette.value(out, address, raw, write),
+    }
+}
+
+// Colors for offsets, if any
+pub(crate) fn offset_color(theme: Option<&Theme>) -> &str {
+    theme.map_or("", |theme| &theme.offset)
+}
+
+// Function to render one conversion; 'data' is zero-padded, 'raw' only holds real input
+fn render_conv(
+    out: &mut Vec<u8>,
+    conv: &Conv,
+    endian: Endian,
+    data: &[u8],
+    raw: &[u8],
+    address: u64,
+) {
+    let spec = &conv.spec;
+    match conv.kind {
+        Kind::Address(radix) | Kind::EndAddress(radix) => {
+            write_uint(out, spec, radix, address as u128)
+        }
+        Kind::Unsigned(radix) => write_uint(out, spec, radix, read_uint(data, endian)),
+        Kind::Signed => {
+            // Sign-extend from the conversion's size
+            let bits = 8 * data.len() as u32;
+            let value = read_uint(data, endian);
+            let value = if bits < 128 && value >> (bits - 1) & 1 == 1 {
+                (value | !0u128 << bits) as i128
+            } else {
+                value as i128
+            };
+            let sign = if value < 0 {
+                "-"
+            } else if spec.plus {
+                "+"
+            } else if spec.space {
+                " "
+            } else {
+                ""
+            };
+            let digits = int_digits(spec, value.unsigned_abs().to_string());
+            pad(
+                out,
+                spec,
+                sign,
+                "",
+                digits.as_bytes(),
+                spec.precision.is_none(),
+            );
+        }
+        Kind::Float(style) => {
+            let value = match (data.len(), endian) {
+                (4, Endian::Big) => f32::from_be_bytes(data.try_into().expect("4 bytes")) as f64,
+                (4, _) => f32::from_le_bytes(data.try_into().expect("4 bytes")) as f64,
+                (_, Endian::Big) => f64::from_be_bytes(data[..8].try_into().expect("8 bytes")),
+                _ => f64::from_le_bytes(data[..8].try_into().expect("8 bytes")),
+            };
+            match style {
+                FloatStyle::Shortest => write_shortest(out, spec, value, data.len() == 4),
+                _ => write_float(out, spec, style, value),
+            }
+        }
+        Kind::Char => pad(out, spec, "", "", &data[..1], false),
+        Kind::Str => {
+            // Stops at the first NUL, like printf's %s
+            let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+            let end = spec.precision.map_or(end, |p| end.min(p));
+            pad(out, spec, "", "", &raw[..end], false);
+        }
+        Kind::Escaped => {
+            let b = data[0];
+            let text = match b {
+                0 => "\\0".to_string(),
+                0x07 => "\\a".to_string(),
+                0x08 => "\\b".to_string(),
+                0x0c => "\\f".to_string(),
+                b'\n' => "\\n".to_string(),
+                b'\r' => "\\r".to_string(),
+                b'\t' => "\\t".to_string(),
+                0x0b => "\\v".to_string(),
+                _ if is_printable(b) => (b as char).to_string(),
+                _ => format!("{:03o}", b),
+            };
+            pad(out, spec, "", "", text.as_bytes(), false);
+        }
+        Kind::Printable => {
+            let b = if is_printable(data[0]) { data[0] } else { b'.' };
+            pad(out, spec, "", "", &[b], false);
+        }
+        Kind::Named => {
+            let b = data[0];
+            if let Some(name) = control_name(b) {
+                pad(out, spec, "", "", name.as_bytes(), false);
+            } else if is_printable(b) {
+                pad(out, spec, "", "", &[b], false);
+            } else {
+                write_uint(out, spec, Radix::Hex { upper: false }, b as u128);
+            }
+        }
+        Kind::Ascii => {
+            let b = data[0] & 0x7f;
+            let name = match b {
+                b' ' => Some("sp"),
+                b'\n' => Some("nl"),
+                _ => control_name(b),
+            };
+            pad(
+                out,
+                spec,
+                "",
+                "",
+                name.map_or(&[b][..], str::as_bytes),
+                false,
+            );
+        }
+    }
+}
+
+// Whether a byte is printable ASCII, as in the C locale
+fn is_printable(b: u8) -> bool {
+    b == b' ' || b.is_ascii_graphic()
+}
+
+// US-ASCII names used by '%_u'
+fn control_name(b: u8) -> Option<&'static str> {
+    const NAMES: [&str; 32] = [
+        "nul", "soh", "stx", "etx", "eot", "enq", "ack", "bel", "bs", "ht", "lf", "vt", "ff", "cr",
+        "so", "si", "dle", "dc1", "dc2", "dc3", "dc4", "nak", "syn", "etb", "can", "em", "sub",
+        "esc", "fs", "gs", "rs", "us",
+    ];
+    match b {
+        0x00..=0x1f => Some(NAMES[b as usize]),
+        0x7f => Some("del"),
+        _ => None,
+    }
+}
+
+// Function to read an unsigned value of up to 16 bytes in the given byte order
+fn read_uint(data: &[u8], endian: Endian) -> u128 {
+    let push = |value: u128, &b: &u8| value << 8 | b as u128;
+    match endian {
+        Endian::Big => data.iter().fold(0, push),
+        _ => data.iter().rev().fold(0, push),
+    }
+}
+
+// Applies the precision (minimum digits) to an integer's digits
+fn int_digits(spec: &Spec, digits: String) -> String {
+    match spec.precision {
+        Some(0) if digits == "0" => String::new(),
+        Some(p) if p > digits.len() => format!("{}{}", "0".repeat(p - digits.len()), digits),
+        _ => digits,
+    }
+}
+
+// Function to write an unsigned integer in the given radix
+fn write_uint(out: &mut Vec<u8>, spec: &Spec, radix: Radix, value: u128) {
+    let digits = match radix {
+        Radix::Octal => format!("{:o}", value),
+        Radix::Decimal => value.to_string(),
+        Radix::Hex { upper: false } => format!("{:x}", value),
+        Radix::Hex { upper: true } => format!("{:X}", value),
+    };
+    let mut digits = int_digits(spec, digits);
+    let mut prefix = "";
+    if spec.hash {
+        match radix {
+            Radix::Octal if !digits.starts_with('0') => digits.insert(0, '0'),
+            Radix::Hex { upper: false } if value != 0 => prefix = "0x",
+            Radix::Hex { upper: true } if value != 0 => prefix = "0X",
+            _ => {}
+        }
+    }
+    pad(
+        out,
+        spec,
+        "",
+        prefix,
+        digits.as_bytes(),
+        spec.precision.is_none(),
+    );
+}
+
+// Function to write a floating-point value like C's printf
+fn write_float(out: &mut Vec<u8>, spec: &Spec, style: FloatStyle, value: f64) {
+    let sign = if value.is_sign_negative() {
+        "-"
+    } else if spec.plus {
+        "+"
+    } else if spec.space {
+        " "
+    } else {
+        ""
+    };
+    let upper = matches!(
+        style,
+        FloatStyle::Exp { upper: true } | FloatStyle::General { upper: true }
+    );
+    let abs = value.abs();
+
+    let body = if !abs.is_finite() {
+        let text = if abs.is_nan() { "nan" } else { "inf" };
+        let text = if upper {
+            text.to_uppercase()
+        } else {
+            text.to_string()
+        };
+        return pad(out, spec, sign, "", text.as_bytes(), false);
+    } else {
+        let precision = spec.precision.unwrap_or(6);
+        match style {
+            FloatStyle::Fixed => fixed(abs, precision, spec.hash),
+            FloatStyle::Exp { .. } => exponent(abs, precision, spec.hash),
+            FloatStyle::General { .. } | FloatStyle::Shortest => {
+                // %g picks %e or %f from the exponent, then drops trailing zeros
+                let precision = precision.max(1);
+                let exp = exponent_of(abs, precision - 1);
+                let text = if exp < -4 || exp >= precision as i32 {
+                    exponent(abs, precision - 1, spec.hash)
+                } else {
+                    fixed(abs, (precision as i32 - 1 - exp) as usize, spec.hash)
+                };
+                if spec.hash {
+                    text
+                } else {
+                    strip_zeros(&text)
+                }
+            }
+        }
+    };
+    let body = if upper { body.to_uppercase() } else { body };
+    pad(out, spec, sign, "", body.as_bytes(), true);
+}
+
+// Function to write a float with the fewest '%g' digits that read back as the
+// same value, like GNU od; 'single' values are read back as f32
+fn write_shortest(out: &mut Vec<u8>, spec: &Spec, value: f64, single: bool) {
+    let (min, digits, max) = if single {
+        (f32::MIN_POSITIVE as f64, 6, 9)
+    } else {
+        (f64::MIN_POSITIVE, 15, 17)
+    };
+    let first = if value.abs() < min { 1 } else { digits };
+    let general = FloatStyle::General { upper: false };
+    for precision in first..=max {
+        let spec = Spec {
+            precision: Some(precision),
+            ..spec.clone()
+        };
+        let mut text = Vec::new();
+        write_float(&mut text, &spec, general, value);
+        let number = String::from_utf8_lossy(&text);
+        let number = number.trim_start();
+        let exact = if single {
+            number.parse::<f32>() == Ok(value as f32)
+        } else {
+            number.parse::<f64>() == Ok(value)
+        };
+        if exact || precision == max {
+            out.extend_from_slice(&text);
+            return;
+        }
+    }
+}
+
+// Greatest common divisor, for block sizes that hold whole values
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// '%f' body for a non-negative value
+fn fixed(value: f64, precision: usize, hash: bool) -> String {
+    let mut text = format!("{:.*}", precision, value);
+    if hash && precision == 0 {
+        text.push('.');
+    }
+    text
+}
+
+// '%e' body for a non-negative value, with a signed two-digit exponent
+fn exponent(value: f64, precision: usize, hash: bool) -> String {
+    let text = format!("{:.*e}", precision, value);
+    let (mantissa, exp) = text.split_once('e').expect("exponent present");
+    let exp: i32 = exp.parse().expect("exponent is numeric");
+    let dot = if hash && precision == 0 { "." } else { "" };
+    let sign = if exp < 0 { '-' } else { '+' };
+    format!("{}{}e{}{:02}", mantissa, dot, sign, exp.abs())
+}
+
+// Decimal exponent of a value once rounded to 'precision' fractional digits in %e form
+fn exponent_of(value: f64, precision: usize) -> i32 {
+    let text = format!("{:.*e}", precision, value);
+    text.split_once('e')
+        .and_then(|(_, exp)| exp.parse().ok())
+        .unwrap_or(0)
+}
+
+// Removes trailing fractional zeros (and a bare point) from %g output
+fn strip_zeros(text: &str) -> String {
+    let (number, exp) = match text.find('e') {
+        Some(i) => text.split_at(i),
+        None => (text, ""),
+    };
+    let number = if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    };
+    format!("{}{}", number, exp)
+}
+
+// Function to pad a converted field to its width, honoring '-' and '0'
+fn pad(out: &mut Vec<u8>, spec: &Spec, sign: &str, prefix: &str, body: &[u8], zero_ok: bool) {
+    let len = sign.len() + prefix.len() + body.len();
+    let fill = spec.width.unwrap_or(0).saturating_sub(len);
+    if spec.minus {
+        out.extend_from_slice(sign.as_bytes());
+        out.extend_from_slice(prefix.as_bytes());
+        out.extend_from_slice(body);
+        out.resize(out.len() + fill, b' ');
+    } else if spec.zero && zero_ok {
+        out.extend_from_slice(sign.as_bytes());
+        out.extend_from_slice(prefix.as_bytes());
+        out.resize(out.len() + fill, b'0');
+        out.extend_from_slice(body);
+    } else {
+        out.resize(out.len() + fill, b' ');
+        out.extend_from_slice(sign.as_bytes());
+        out.extend_from_slice(prefix.as_bytes());
+        out.extend_from_slice(body);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper to render a whole input with a format, like the dump engine does
+    fn render(format: &Format, input: &[u8]) -> String {
+        let program = format.compile().unwrap();
+        let mut out = Vec::new();
+        let size = program.block_size();
+        for (i, chunk) in input.chunks(size).enumerate() {
+            let mut block = chunk.to_vec();
+            block.resize(size, 0);
+            program
+                .render_block(
+                    &mut out,
+                    &block,
+                    chunk.len(),
+                    (i * size) as u64,
+                    &Palette::default(),
+                )
+                .unwrap();
+        }
+        if !input.is_empty() {
+            program
+                .render_end(&mut out, input.len() as u64, &Palette::default())
+                .unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_parse_units() {
+        // Iteration and byte counts are read from each unit
+        let format = Format::parse(r#""%07.7_ax " 8/2 "%04x " "\n""#).unwrap();
+        let units = &format.strings[0].units;
+        assert_eq!(units.len(), 3);
+        assert_eq!((units[1].reps, units[1].size), (8, 2));
+        assert_eq!(format.compile().unwrap().block_size(), 16);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        // Malformed formats are rejected with a description
+        let cases = [
+            (r#""%y""#, "bad conversion character %y"),
+            (r#""%02x"#, "unterminated format '\"%02x'"),
+            (r#"8/ "%x""#, "missing byte count after '/'"),
+            (
+                r#"/2 "%x %x""#,
+                "byte count with multiple conversion characters",
+            ),
+            (r#"/3 "%x""#, "bad byte count 3 for conversion"),
+            (r#""%s""#, "%s requires a precision or a byte count"),
+            (r#""%_az""#, "bad conversion character %_a"),
+            ("x", "expected '\"' at 'x'"),
+        ];
+        for (text, message) in cases {
+            assert_eq!(Format::parse(text).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_util_linux_default() {
+        // util-linux's default format zero-pads the odd byte and blank-pads the line
+        let format =
+            Format::parse_file("\"%07.7_Ax\\n\"\n\"%07.7_ax \" 8/2 \"%04x \" \"\\n\"\n").unwrap();
+        let expected = "0000000 6261 0063                              \n0000003\n";
+        assert_eq!(render(&format, b"abc"), expected);
+    }
+
+    #[test]
+    fn test_character_conversions() {
+        // '_c', '_p' and '_u' render bytes as characters
+        let format = Format::parse_file(
+            r#"
+            4/1 "%3_c "
+            4/1 "%_p"
+            4/1 " %_u" "\n"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            render(&format, b"\0\nA\x80"),
+            " \\0  \\n   A 200..A. nul lf A 80\n"
+        );
+    }
+
+    #[test]
+    fn test_signed_and_decimal() {
+        // Signed conversions are sign-extended from their byte count
+        let format = Format::parse(r#"/1 "%4d" /2 " %+d" /1 " %u" /1 " %#o" /1 " %#X""#).unwrap();
+        assert_eq!(
+            render(&format, &[0xff, 0x00, 0x80, 200, 8, 0xab]),
+            "  -1 -32768 200 010 0XAB"
+        );
+    }
+
+    #[test]
+    fn test_float_conversions() {
+        // Floats follow C printf formatting
+        let mut input = 1.5f64.to_le_bytes().to_vec();
+        input.extend_from_slice(&0.0001234f64.to_le_bytes());
+        input.extend_from_slice(&(-250.0f32).to_le_bytes());
+        let format = Format::parse(r#"/8 "%e" /8 " %g" /4 " %.1f""#).unwrap();
+        assert_eq!(render(&format, &input), "1.500000e+00 0.0001234 -250.0");
+        let format = Format::parse(r#""%g %G""#).unwrap();
+        let mut input = 1e20f64.to_le_bytes().to_vec();
+        input.extend_from_slice(&f64::INFINITY.to_le_bytes());
+        assert_eq!(render(&format, &input), "1e+20 INF");
+    }
+
+    #[test]
+    fn test_string_conversion() {
+        // '%s' prints up to its byte count, stopping at NUL
+        let format = Format::parse(r#"/6 "[%-8s]""#).unwrap();
+        assert_eq!(render(&format, b"abc\0de"), "[abc     ]");
+    }
+
+    #[test]
+    fn test_last_unit_fills_block() {
+        // A trailing unit without an iteration count repeats to fill the block
+        let mut format = Mode::Canonical.format();
+        format.append(Format::parse(r#""%08x\n""#).unwrap());
+        let program = format.compile().unwrap();
+        assert_eq!(program.block_size(), 16);
+        assert_eq!(program.strings[3].units[0].reps, 4);
+    }
+
+    #[test]
+    fn test_format_file_comments() {
+        // Blank lines and '#' comments are ignored in format files
+        let format = Format::parse_file("# hex bytes\n\n2/1 \"%02x\" \"\\n\"\n").unwrap();
+        assert_eq!(render(&format, b"\x01\x02"), "0102\n");
+        let err = Format::parse_file("\"%x\"\n\"%q\"\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: bad conversion character %q");
+    }
+
+    #[test]
+    fn test_zero_sized_format() {
+        // A format that reads no input can't be used for a dump
+        for text in [r#""hello\n""#, r#""%_ad\n""#] {
+            let format = Format::parse(text).unwrap();
+            let err = format.check().unwrap_err();
+            assert_eq!(err.to_string(), "format does not consume any input");
+            assert!(format.compile().is_err());
+        }
+        assert!(Format::parse_file("# only a comment\n")
+            .unwrap()
+            .check()
+            .is_err());
+    }
+
+    #[test]
+    fn test_oversized_counts() {
+        // Counts that overflow or make blocks too large to hold are rejected
+        for text in [
+            r#"99999999999999999999/1 "%x""#,
+            r#"99999999999999/1 "%x""#,
+            r#"1/99999999999999999999 "%x""#,
+            r#"4096/1024 "%x""#,
+            r#"2048/1024 "%x" 2048/1024 "%x""#,
+        ] {
+            assert!(Format::parse(text).is_err(), "{}", text);
+        }
+        assert!(Format::parse(r#"4096/1 "%02x""#).is_ok());
+    }
+
+    #[test]
+    fn test_grouped_sizes_and_endianness() {
+        // Every group size reads whole groups in the chosen byte order
+        let input: Vec<u8> = (0..16).collect();
+        let cases = [
+            (
+                1,
+                Endian::Little,
+                "00000000 00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f\n",
+            ),
+            (
+                4,
+                Endian::Little,
+                "00000000 03020100 07060504 0b0a0908 0f0e0d0c\n",
+            ),
+            (
+                4,
+                Endian::Big,
+                "00000000 00010203 04050607 08090a0b 0c0d0e0f\n",
+            ),
+            (
+                8,
+                Endian::Big,
+                "00000000 0001020304050607 08090a0b0c0d0e0f\n",
+            ),
+            (
+                16,
+                Endian::Little,
+                "00000000 0f0e0d0c0b0a09080706050403020100\n",
+            ),
+            (
+                16,
+                Endian::Big,
+                "00000000 000102030405060708090a0b0c0d0e0f\n",
+            ),
+        ];
+        for (size, endian, expected) in cases {
+            let mut format = Format::grouped(size).unwrap();
+            format.set_endian(endian);
+            assert_eq!(
+                render(&format, &input),
+                expected,
+                "group {} {:?}",
+                size,
+                endian
+            );
+        }
+    }
+
+    #[test]
+    fn test_grouped_partial_group() {
+        // A trailing partial group shows only the bytes that exist, in the chosen order
+        let input = [0xaa, 0xbb, 0xcc, 0xdd, 0xee];
+        let mut format = Format::grouped(4).unwrap();
+        assert_eq!(render(&format, &input), "00000000 ddccbbaa ee\n");
+        format.set_endian(Endian::Big);
+        assert_eq!(render(&format, &input), "00000000 aabbccdd ee\n");
+        let mut format = Format::grouped(8).unwrap();
+        assert_eq!(render(&format, &input[..3]), "00000000 ccbbaa\n");
+        format.set_endian(Endian::Big);
+        assert_eq!(render(&format, &input[..3]), "00000000 aabbcc\n");
+
+        // Every group size and byte order
+        let cases = [
+            (1, "aa bb cc dd ee", "aa bb cc dd ee"),
+            (2, "bbaa ddcc ee", "aabb ccdd ee"),
+            (4, "ddccbbaa ee", "aabbccdd ee"),
+            (8, "eeddccbbaa", "aabbccddee"),
+            (16, "eeddccbbaa", "aabbccddee"),
+        ];
+        for (size, little, big) in cases {
+            let mut format = Format::grouped(size).unwrap();
+            assert_eq!(render(&format, &input), format!("00000000 {}\n", little));
+            format.set_endian(Endian::Big);
+            assert_eq!(render(&format, &input), format!("00000000 {}\n", big));
+        }
+    }
+
+    #[test]
+    fn test_grouped_bad_size() {
+        // Only sizes that divide the 16-byte line into whole numbers are accepted
+        assert!(Format::grouped(3).is_err());
+        assert_eq!(Format::grouped(2).unwrap(), Mode::Words.format());
+    }
+
+    #[test]
+    fn test_big_endian_user_format() {
+        // The byte order applies to user formats and floats too
+        let mut format = Format::parse(r#"/2 "%u " /4 "%g""#).unwrap();
+        format.set_endian(Endian::Big);
+        let mut input = vec![0x01, 0x00];
+        input.extend_from_slice(&2.5f32.to_be_bytes());
+        assert_eq!(render(&format, &input), "256 2.5");
+    }
+
+    #[test]
+    fn test_util_linux_modes() {
+        // Golden output of `printf 'ab\n\0\x80\xff' | hexdump -b -c -d -o -x`
+        let mut format = Mode::OctalBytes.format();
+        for mode in [
+            Mode::Chars,
+            Mode::DecimalWords,
+            Mode::OctalWords,
+            Mode::HexWords,
+        ] {
+            format.append(mode.format());
+        }
+        let expected = concat!(
+            "0000000 141 142 012 000 200 377                                        \n",
+            "0000000   a   b  \\n  \\0 200 377                                        \n",
+            "0000000   25185   00010   65408                                        \n",
+            "0000000  061141  000012  177600                                        \n",
+            "0000000    6261    000a    ff80                                        \n",
+            "0000006\n",
+        );
+        assert_eq!(render(&format, b"ab\n\0\x80\xff"), expected);
+    }
+
+    #[test]
+    fn test_xxd_layouts() {
+        // Golden output of `xxd`, `xxd -g1 -c10`, `xxd -g3 -u` and `xxd -p -c8`
+        let input = b"Hello, world! 0123456789\x00\xff";
+        let expected = "\
+00000000: 4865 6c6c 6f2c 2077 6f72 6c64 2120 3031  Hello, world! 01
+00000010: 3233 3435 3637 3839 00ff                 23456789..
+";
+        assert_eq!(render(&Format::xxd(16, 2, false).unwrap(), input), expected);
+        let expected = "\
+00000000: 48 65 6c 6c 6f 2c 20 77 6f 72  Hello, wor
+0000000a: 6c 64 21 20 30 31 32 33 34 35  ld! 012345
+00000014: 36 37 38 39 00 ff              6789..
+";
+        assert_eq!(render(&Format::xxd(10, 1, false).unwrap(), input), expected);
+        let expected = "\
+00000000: 48656C 6C6F2C 20776F 726C64 212030 31  Hello, world! 01
+00000010: 323334 353637 383900 FF                23456789..
+";
+        assert_eq!(render(&Format::xxd(16, 3, true).unwrap(), input), expected);
+        let expected = "48656c6c6f2c2077\n6f726c6421203031\n3233343536373839\n00ff\n";
+        assert_eq!(
+            render(&Format::xxd_plain(8, false).unwrap(), input),
+            expected
+        );
+        assert!(Format::xxd(0, 2, false).is_err());
+    }
+
+    // Helper to build an od layout from '-t' type strings
+    fn od_format(address: od::Address, types: &[&str], width: Option<usize>) -> Format {
+        let types: Vec<od::Type> = types
+            .iter()
+            .flat_map(|text| od::Type::parse_list(text).unwrap())
+            .collect();
+        Format::od(address, &types, width).unwrap()
+    }
+
+    #[test]
+    fn test_od_layouts() {
+        // Golden output of `od -A x -t x1z -t x2`, with values aligned across lines
+        let expected = "\
+000000 61 62 63 64 65 66 67 68 69 6a 6b 6c 6d 6e 6f 70  >abcdefghijklmnop<
+        6261  6463  6665  6867  6a69  6c6b  6e6d  706f
+000010 71 72 73 74 75                                   >qrstu<
+        7271  7473  0075
+000015
+";
+        let format = od_format(od::Address::Hex, &["x1z", "x2"], None);
+        assert_eq!(render(&format, b"abcdefghijklmnopqrstu"), expected);
+
+        // Golden output of `od -t x2z -t d1`: the trailer stays in its column
+        let expected = "\
+0000000      6261      0063                                                              >abc<
+          97   98   99
+0000003
+";
+        let format = od_format(od::Address::Octal, &["x2z", "d1"], None);
+        assert_eq!(render(&format, b"abc"), expected);
+
+        // Golden output of `od -An -tx1`
+        let format = od_format(od::Address::None, &["x1"], None);
+        assert_eq!(render(&format, b"abc"), " 61 62 63\n");
+    }
+
+    #[test]
+    fn test_od_floats_and_characters() {
+        // Golden output of `od -t f4 -t d2 -w12`: floats use the fewest digits that round-trip
+        let expected = "\
+0000000               1  -1.5881868e-23       1.4499999
+              0   16256  -26214  -26215  -26215   16313
+0000014      9.1475e-41
+           -257
+0000016
+";
+        let input = b"\x00\x00\x80\x3f\x9a\x99\x99\x99\x99\x99\xb9\x3f\xff\xfe";
+        let format = od_format(od::Address::Octal, &["f4", "d2"], Some(12));
+        assert_eq!(render(&format, input), expected);
+
+        // Golden output of `od -a -c`
+        let expected = "\
+0000000 nul nul del  sp   h   i  nl del
+        200  \\0 177       h   i  \\n 377
+0000010
+";
+        let format = od_format(od::Address::Octal, &["a", "c"], None);
+        assert_eq!(render(&format, b"\x80\x00\x7f hi\n\xff"), expected);
+    }
+
+    #[test]
+    fn test_od_width() {
+        // The width must hold whole values of every type
+        assert!(Format::od(od::Address::Octal, &[], Some(8)).is_ok());
+        assert!(Format::od(od::Address::Octal, &[], Some(7)).is_err());
+        assert!(Format::od(od::Address::Octal, &[], Some(0)).is_err());
+        assert!(Format::od(od::Address::Octal, &[], Some(99999999998)).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+//! Self-contained HTML reports of dumps, for sharing in reviews and
+//! write-ups.
+//!
+//! The dump is rendered line by line by the same formats as text output,
+//! with HTML elements in place of color escapes: values are classed by the
+//! class of their bytes, and every line's offset is an anchor named after it,
+//! such as `#0x1f40`. Hovering over a value highlights every value showing
+//! the same bytes, such as a byte's hex and its character in the canonical
+//! layout. Styles and scripts are inline, so the file needs nothing else.
+
+use std::io::{Read, Write}; // I/O operations
+use std::ops::Range; // Annotated byte ranges
+use std::str::FromStr; // Annotations given as text
+
+use crate::color::{self, Markup}; // HTML elements and escaping
+use crate::input::parse_size; // Offsets of annotations
+use crate::{Config, Result}; // Layout and errors
+
+// Styles, after the default colors of text output
+const STYLE: &str = "\
+body { background: #1e1e1e; color: #d4d4d4; font-family: ui-monospace, Menlo, Consolas, monospace; }
+h1 { font-size: 1.2em; }
+a { color: inherit; }
+.offset { color: #808080; text-decoration: none; }
+.nul { color: #808080; }
+.print { color: #4ec9d0; }
+.space { color: #4ec94e; }
+.ctrl { color: #c94ec9; }
+.ff { color: #e05252; }
+.high { color: #d7ba4e; }
+.string { text-decoration: underline; }
+.note { background: #3a3d5c; }
+.hover { background: #5a5a5a; outline: 1px solid #d4d4d4; }
+.target { background: #7a6a1e; }
+";
+
+// Script highlighting every value showing the bytes under the pointer or named by the address
+const SCRIPT: &str = "\
+const at = new Map();
+for (const el of document.querySelectorAll('[data-o]')) {
+  const o = +el.dataset.o;
+  for (let i = o; i < o + +(el.dataset.n || 1); i++) {
+    if (!at.has(i)) at.set(i, []);
+    at.get(i).push(el);
+  }
+}
+const lit = { hover: [], target: [] };
+function light(name, el) {
+  for (const x of lit[name]) x.classList.remove(name);
+  lit[name] = [];
+  if (!el) return;
+  const o = +el.dataset.o;
+  for (let i = o; i < o + +(el.dataset.n || 1); i++) {
+    for (const x of at.get(i) || []) {
+      x.classList.add(name);
+      lit[name].push(x);
+    }
+  }
+}
+document.addEventListener('mouseover', e => light('hover', e.target.closest('[data-o]')));
+function go() {
+  const m = /^#0x([0-9a-f]+)$/i.exec(location.hash);
+  if (!m) return;
+  const el = (at.get(parseInt(m[1], 16)) || [])[0];
+  light('target', el);
+  if (el && !document.getElementById(location.hash.slice(1))) el.scrollIntoView({ block: 'center' });
+}
+window.addEventListener('hashchange', go);
+go();
+";
+
+/// A note on a range of bytes, shown when hovering over them and listed
+/// above the dump.
+///
+/// Annotations parse from `OFFSET[+LENGTH]:TEXT`, with sizes as accepted
+/// by [`parse_size`] and a length of one byte if not given.
+///
+/// ```
+/// use hexdump::html::Annotation;
+/// let annotation: Annotation = "0x10+4:magic".parse().unwrap();
+/// assert_eq!(annotation.range, 0x10..0x14);
+/// assert_eq!(annotation.text, "magic");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    /// Offsets of the bytes annotated.
+    pub range: Range<u64>,
+    /// The note.
+    pub text: String,
+}
+
+impl FromStr for Annotation {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        let (at, text) = s.split_once(':').ok_or(())?;
+        let (start, len) = match at.split_once('+') {
+            Some((start, len)) => (start, parse_size(len).ok_or(())?),
+            None => (at, 1),
+        };
+        let start = parse_size(start).ok_or(())?;
+        Ok(Annotation {
+            range: start..start.checked_add(len).ok_or(())?,
+            text: text.to_string(),
+        })
+    }
+}
+
+/// Settings for HTML reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Html {
+    /// Title of the page, such as the name of the input.
+    pub title: String,
+    /// Notes on the bytes of the input.
+    pub annotations: Vec<Annotation>,
+}
+
+impl Html {
+    /// A report titled `title`, without annotations.
+    pub fn new(title: &str) -> Self {
+        Html {
+            title: title.to_string(),
+            annotations: Vec::new(),
+        }
+    }
+
+    /// Writes the report of `reader`, positioned [`skip`](Config::skip)
+    /// bytes into the input, dumped the way `config` says. The theme is
+    /// left out: colors come with the page, and strings are marked whenever
+    /// [`mark_strings`](Config::mark_strings) is set.
+    ///
+    /// ```
+    /// use hexdump::html::Html;
+    /// let mut out = Vec::new();
+    /// Html::new("a&b").write(&hexdump::Config::default(), &b"<"[..], &mut out).unwrap();
+    /// let out = String::from_utf8(out).unwrap();
+    /// assert!(out.contains("<title>a&amp;b</title>"));
+    /// assert!(out.contains(r#"<span class="print" data-o="0">3c</span>"#));
+    /// ```
+    pub fn write<R: Read, W: Write>(
+        &self,
+        config: &Config,
+        reader: R,
+        mut writer: W,
+    ) -> Result<()> {
+        let mut title = Vec::new();
+        color::escape(&mut title, self.title.as_bytes());
+        let title = String::from_utf8_lossy(&title);
+        write!(
+            writer,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+            title, STYLE, title
+        )?;
+        if !self.annotations.is_empty() {
+            writeln!(writer, "<ul>")?;
+            for annotation in &self.annotations {
+                let Range { start, end } = annotation.range;
+                let mut item = format!("<li><a href=\"#0x{:x}\">0x{:x}</a>", start, start);
+                if end > start + 1 {
+                    item.push_str(&format!("&ndash;0x{:x}", end - 1));
+                }
+                item.push(' ');
+                let mut item = item.into_bytes();
+                color::escape(&mut item, annotation.text.as_bytes());
+                item.extend_from_slice(b"</li>\n");
+                writer.write_all(&item)?;
+            }
+            writeln!(writer, "</ul>")?;
+        }
+
+        // The first newline after '<pre>' would be dropped, so the dump starts right after it
+        write!(writer, "<pre>")?;
+        let config = Config {
+            theme: None,
+            ..config.clone()
+        };
+        let limit = config.length.unwrap_or(u64::MAX);
+        crate::write_dump(
+            &config,
+            reader.take(limit),
+            &mut writer,
+            Markup::Html(&self.annotations),
+        )?;
+        write!(
+            writer,
+            "</pre>\n<script>\n{}</script>\n</body>\n</html>\n",
+            SCRIPT
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mode;
+
+    // Helper to write the part of the report between '<pre>' and '</pre>' into a string
+    fn dump(html: &Html, config: &Config, input: &[u8]) -> String {
+        let mut out = Vec::new();
+        html.write(config, input, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let start = out.find("<pre>").unwrap() + "<pre>".len();
+        out[start..out.find("</pre>").unwrap()].to_string()
+    }
+
+    #[test]
+    fn test_annotation_parse() {
+        // Annotations take an offset, an optional length and a note that may hold colons
+        let annotation: Annotation = "16:a: b".parse().unwrap();
+        assert_eq!(annotation.range, 16..17);
+        assert_eq!(annotation.text, "a: b");
+        assert_eq!(
+            "0x10+0x10:".parse::<Annotation>().unwrap().range,
+            0x10..0x20
+        );
+        assert!("0x10".parse::<Annotation>().is_err());
+        assert!("x:note".parse::<Annotation>().is_err());
+        assert!("0x10+y:note".parse::<Annotation>().is_err());
+    }
+
+    #[test]
+    fn test_canonical() {
+        // Lines keep the text layout, with anchored offsets and bytes linked across columns
+        let config = Config {
+            format: Mode::Canonical.format(),
+            ..Config::default()
+        };
+        let html = Html {
+            annotations: vec!["1:second".parse().unwrap()],
+            ..Html::new("t")
+        };
+        let out = dump(&html, &config, b"A\n");
+        let expected = concat!(
+            r##"<a class="offset" id="0x0" href="#0x0">00000000</a>  "##,
+            r#"<span class="print" data-o="0">41</span> "#,
+            r#"<span class="space note" data-o="1" title="second">0a</span>"#,
+            "                                             |",
+            r#"<span class="print" data-o="0">A</span>"#,
+            r#"<span class="space note" data-o="1" title="second">.</span>"#,
+            "|\n",
+            r##"<a class="offset" id="0x2" href="#0x2">00000002</a>"##,
+            "\n",
+        );
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_squeeze_and_strings() {
+        // Squeezed lines and strings follow the text dump, strings without a theme
+        let config = Config {
+            format: Mode::Canonical.format(),
+            mark_strings: Some(4),
+            ..Config::default()
+        };
+        let mut input = vec![0u8; 48];
+        input[40..44].copy_from_slice(b"abcd");
+        let out = dump(&Html::new("t"), &config, &input);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[1], "*");
+        assert!(lines[2].starts_with(r##"<a class="offset" id="0x20" href="#0x20">"##));
+        assert!(lines[2].contains(r#"<span class="print string" data-o="40">61</span>"#));
+        assert!(lines[2].contains(r#"<span class="print string" data-o="43">d</span>"#));
+    }
+}
@@ -0,0 +1,226 @@
+//! Input sources that are dumped as one continuous stream.
+
+use std::fmt; // Display formatting
+use std::fs::File; // File Handling
+use std::io::{self, BufReader, Read, Seek}; // I/O operations
+use std::path::PathBuf; // File paths
+
+/// A single input: standard input or a named file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Standard input, selected with `-` or by giving no files.
+    Stdin,
+    /// A file on disk.
+    File(PathBuf),
+}
+
+impl Source {
+    /// Interprets a command-line operand, treating `-` as standard input.
+    pub fn from_arg(arg: &str) -> Self {
+        match arg {
+            "-" => Source::Stdin,
+            path => Source::File(PathBuf::from(path)),
+        }
+    }
+
+    /// Opens the source for buffered reading.
+    pub fn open(&self) -> io::Result<Box<dyn Read>> {
+        Ok(Box::new(self.open_reader()?))
+    }
+
+    // Opens the source, keeping track of whether it supports seeking
+    fn open_reader(&self) -> io::Result<Reader> {
+        match self {
+            Source::Stdin => Ok(Reader::Stream(Box::new(io::stdin().lock()))),
+            Source::File(path) => {
+                let file = File::open(path)?;
+                if file.metadata()?.is_file() {
+                    Ok(Reader::File(BufReader::new(file)))
+                } else {
+                    Ok(Reader::Stream(Box::new(BufReader::new(file)))) // Devices and FIFOs can't seek
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Stdin => write!(f, "-"),
+            Source::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+// An opened source: a regular file that can seek, or a stream that can't
+enum Reader {
+    File(BufReader<File>),
+    Stream(Box<dyn Read>),
+}
+
+impl Reader {
+    // Moves up to 'n' bytes forward, returning how far it got before EOF
+    fn skip(&mut self, n: u64) -> io::Result<u64> {
+        match self {
+            Reader::File(file) => {
+                let len = file.get_ref().metadata()?.len();
+                let pos = file.stream_position()?;
+                let step = n.min(len.saturating_sub(pos)); // Never seek past EOF
+                file.seek(io::SeekFrom::Current(step as i64))?;
+                Ok(step)
+            }
+            Reader::Stream(stream) => io::copy(&mut stream.take(n), &mut io::sink()), // Read and discard
+        }
+    }
+}
+
+impl Read for Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Reader::File(file) => file.read(buf),
+            Reader::Stream(stream) => stream.read(buf),
+        }
+    }
+}
+
+/// Reader that concatenates several sources, opening each one only when the
+/// previous one is exhausted.
+///
+/// A source that fails to open or to be read is reported through the
+/// callback and skipped, so the remaining sources are still read.
+pub struct Concat<F> {
+    sources: std::vec::IntoIter<Source>,
+    current: Option<(Source, Reader)>,
+    on_error: F,
+    failures: usize,
+}
+
+impl<F: FnMut(&Source, io::Error)> Concat<F> {
+    /// Creates a reader over `sources`, calling `on_error` for each one that can't be opened or read.
+    pub fn new(sources: Vec<Source>, on_error: F) -> Self {
+        Concat {
+            sources: sources.into_iter(),
+            current: None,
+            on_error,
+            failures: 0,
+        }
+    }
+
+    /// Number of sources that failed to open or to be read so far.
+    pub fn failures(&self) -> usize {
+        self.failures
+    }
+
+    /// Moves `n` bytes forward through the concatenated sources, seeking in
+    /// regular files and reading and discarding from pipes.
+    ///
+    /// Returns the number of bytes skipped, which is less than `n` only if
+    /// the end of the last source was reached.
+    pub fn skip(&mut self, n: u64) -> io::Result<u64> {
+        let mut remaining = n;
+        while remaining > 0 && self.open_next() {
+            let (_, reader) = self.current.as_mut().expect("source opened above");
+            match reader.skip(remaining) {
+                Ok(skipped) => remaining -= skipped,
+                Err(e) => self.fail(e)?,
+            }
+            if remaining > 0 {
+                self.current = None; // This source is exhausted, move on
+            }
+        }
+        Ok(n - remaining)
+    }
+
+    // Reports a read error of the current source and drops it, unless the read can be retried
+    fn fail(&mut self, e: io::Error) -> io::Result<()> {
+        if e.kind() == io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+        let (source, _) = self.current.take().expect("a source is open");
+        (self.on_error)(&source, e);
+        self.failures += 1;
+        Ok(())
+    }
+
+    // Makes sure a source is open, returning false once all are exhausted
+    fn open_next(&mut self) -> bool {
+        while self.current.is_none() {
+            let source = match self.sources.next() {
+                Some(source) => source,
+                None => return false, // All sources exhausted
+            };
+            match source.open_reader() {
+                Ok(reader) => self.current = Some((source, reader)),
+                Err(e) => {
+                    (self.on_error)(&source, e);
+                    self.failures += 1;
+                }
+            }
+        }
+        true
+    }
+}
+
+impl<F: FnMut(&Source, io::Error)> Read for Concat<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.open_next() {
+            // Read from the current source, moving on once it hits EOF
+            let (_, reader) = self.current.as_mut().expect("source opened above");
+            match reader.read(buf) {
+                Ok(0) if !buf.is_empty() => self.current = None,
+                Ok(n) => return Ok(n),
+                Err(e) => self.fail(e)?,
+            }
+        }
+        Ok(0)
+    }
+}
+
+/// Parses a byte count or offset the way util-linux does: decimal, `0x`
+/// hexadecimal or `0`-prefixed octal, optionally followed by a multiplier.
+///
+/// Supported multipliers are `b` (512), `k`/`K`/`KiB` (1024), `m`/`M`/`MiB`,
+/// `g`/`G`/`GiB` (powers of 1024) and `KB`/`MB`/`GB` (powers of 1000).
+///
+/// ```
+/// use hexdump::input::parse_size;
+/// assert_eq!(parse_size("0x7f000"), Some(0x7f000));
+/// assert_eq!(parse_size("010"), Some(8));
+/// assert_eq!(parse_size("4k"), Some(4096));
+/// assert_eq!(parse_size("1MB"), Some(1_000_000));
+/// assert_eq!(parse_size("ten"), None);
+/// ```
+pub fn parse_size(arg: &str) -> Option<u64> {
+    // Pick the radix from the prefix
+    let (radix, digits) = if let Some(hex) = arg.strip_prefix("0x").or(arg.strip_prefix("0X")) {
+        (16, hex)
+    } else if arg.len() > 1 && arg.starts_with('0') {
+        (8, &arg[1..])
+    } else {
+        (10, arg)
+    };
+
+    // Split the number from its multiplier suffix
+    let end = digits
+        .find(|c: char| !c.is_digit(radix))
+        .unwrap_or(digits.len());
+    let (number, suffix) = digits.split_at(end);
+    if number.is_empty() {
+        return None;
+    }
+    let value = u64::from_str_radix(number, radix).ok()?;
+
+    let multiplier: u64 = match suffix {
+        "" => 1,
+        "b" => 512,
+        "k" | "K" | "KiB" => 1 << 10,
+        "m" | "M" | "MiB" => 1 << 20,
+        "g" | "G" | "GiB" => 1 << 30,
+        "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        _ => return None,
+    };
+    value.checked_mul(multiplier)
+}
@@ -0,0 +1,231 @@
+//! JSON output for programs reading dumps.
+//!
+//! The input is split into records of a fixed number of bytes, like the
+//! lines of a dump, each giving its offset, its bytes in hex and as an
+//! array of numbers, its ASCII rendering and, when asked for, the words
+//! decoded from it. The schema is versioned by [`SCHEMA_VERSION`], which
+//! only changes when a field is removed or changes meaning; fields may be
+//! added without a new version.
+//!
+//! Record fields (schema version 1):
+//!
+//! | Field | Type | Content |
+//! | --- | --- | --- |
+//! | `offset` | number | Offset of the first byte in the input |
+//! | `hex` | string | The bytes as lowercase hex digits, two per byte |
+//! | `bytes` | array of numbers | The bytes, 0 to 255 |
+//! | `ascii` | string | The bytes as printable ASCII, others as `.` |
+//! | `words` | array of numbers, or of strings for 8-byte words | Unsigned words of the chosen size and byte order; only present when words are asked for, and without a partial last word |
+//!
+//! 8-byte words are written as strings of decimal digits, such as
+//! `"18446744073709551615"`: most JSON readers keep numbers as doubles,
+//! which are only exact up to 2^53.
+
+use std::io::{self, Read, Write}; // I/O operations
+
+use crate::{Config, Endian}; // Skip and length, word byte order
+
+/// Version of the record and document layout, written in every output.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How records are put together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// One JSON document: an object holding `schema_version`, `offset`
+    /// (where the records start), `records` and `length` (the number of
+    /// bytes in them), with one record per line.
+    Document,
+    /// Newline-delimited JSON: one record per line, each with its own
+    /// `schema_version` field.
+    Lines,
+}
+
+/// Settings for JSON output.
+///
+/// ```
+/// use hexdump::json::{Json, Style};
+/// let json = Json { width: 4, words: Some(2), ..Json::new(Style::Lines) };
+/// let mut out = Vec::new();
+/// json.write(&hexdump::Config::default(), &b"\x7fELF\x02"[..], &mut out).unwrap();
+/// let expected = r#"{"schema_version":1,"offset":0,"hex":"7f454c46","bytes":[127,69,76,70],"ascii":".ELF","words":[17791,17996]}
+/// {"schema_version":1,"offset":4,"hex":"02","bytes":[2],"ascii":".","words":[]}
+/// "#;
+/// assert_eq!(String::from_utf8(out).unwrap(), expected);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Json {
+    /// One document or one record per line.
+    pub style: Style,
+    /// Bytes per record.
+    pub width: usize,
+    /// Size in bytes (1, 2, 4 or 8) of the words to decode, if any.
+    pub words: Option<usize>,
+    /// Byte order of the words.
+    pub endian: Endian,
+}
+
+impl Json {
+    /// JSON output in `style` with 16 bytes per record and no words.
+    pub fn new(style: Style) -> Self {
+        Json {
+            style,
+            width: 16,
+            words: None,
+            endian: Endian::Little,
+        }
+    }
+
+    /// Writes the records of `reader`, positioned [`skip`](Config::skip)
+    /// bytes into the input. Returns the number of bytes written out.
+    pub fn write<R: Read, W: Write>(
+        &self,
+        config: &Config,
+        reader: R,
+        mut writer: W,
+    ) -> io::Result<u64> {
+        let mut reader = reader.take(config.length.unwrap_or(u64::MAX));
+        let mut line = vec![0u8; self.width.max(1)];
+        let mut offset = config.skip;
+        let mut first = true; // Whether no record was written yet
+        if self.style == Style::Document {
+            write!(
+                writer,
+                "{{\"schema_version\":{},\"offset\":{},\"records\":[",
+                SCHEMA_VERSION, offset
+            )?;
+        }
+        loop {
+            let len = crate::read_line(&mut reader, &mut line)?;
+            if len == 0 {
+                break;
+            }
+            let mut record = match (self.style, first) {
+                (Style::Document, true) => "\n{".to_string(),
+                (Style::Document, false) => ",\n{".to_string(),
+                (Style::Lines, _) => format!("{{\"schema_version\":{},", SCHEMA_VERSION),
+            };
+            self.record(&mut record, offset, &line[..len]);
+            if self.style == Style::Lines {
+                record.push('\n');
+            }
+            writer.write_all(record.as_bytes())?;
+            offset += len as u64;
+            first = false;
+            if len < line.len() {
+                break; // A short line can only be the last one
+            }
+        }
+        if self.style == Style::Document {
+            writeln!(writer, "\n],\"length\":{}}}", offset - config.skip)?;
+        }
+        Ok(offset - config.skip)
+    }
+
+    // Function to add the fields of the record of 'bytes' at 'offset', and its closing brace
+    fn record(&self, out: &mut String, offset: u64, bytes: &[u8]) {
+        out.push_str(&format!("\"offset\":{},\"hex\":\"", offset));
+        for b in bytes {
+            out.push_str(&format!("{:02x}", b));
+        }
+        let numbers: Vec<String> = bytes.iter().map(u8::to_string).collect();
+        out.push_str(&format!(
+            "\",\"bytes\":[{}],\"ascii\":\"",
+            numbers.join(",")
+        ));
+        for &b in bytes {
+            match b {
+                b'"' => out.push_str("\\\""),
+                b'\\' => out.push_str("\\\\"),
+                0x20..=0x7e => out.push(b as char),
+                _ => out.push('.'),
+            }
+        }
+        out.push('"');
+        if let Some(size) = self.words {
+            let size = size.clamp(1, 8);
+            let words: Vec<String> = bytes
+                .chunks_exact(size)
+                .map(|word| {
+                    let mut value = [0u8; 8];
+                    match self.endian.resolve() {
+                        Endian::Big => value[8 - size..].copy_from_slice(word),
+                        _ => value[..size].copy_from_slice(word),
+                    }
+                    let value = match self.endian.resolve() {
+                        Endian::Big => u64::from_be_bytes(value),
+                        _ => u64::from_le_bytes(value),
+                    };
+                    // Quoted when doubles can't hold every value of the size
+                    if size == 8 {
+                        format!("\"{}\"", value)
+                    } else {
+                        value.to_string()
+                    }
+                })
+                .collect();
+            out.push_str(&format!(",\"words\":[{}]", words.join(",")));
+        }
+        out.push('}');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper to write the JSON of an input into a string
+    fn json(settings: &Json, config: &Config, input: &[u8]) -> String {
+        let mut out = Vec::new();
+        settings.write(config, input, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_document() {
+        // A document holds the records one per line, with offsets from the skipped position
+        let settings = Json {
+            width: 2,
+            ..Json::new(Style::Document)
+        };
+        let config = Config {
+            skip: 0x10,
+            ..Config::default()
+        };
+        let expected = r#"{"schema_version":1,"offset":16,"records":[
+{"offset":16,"hex":"2241","bytes":[34,65],"ascii":"\"A"},
+{"offset":18,"hex":"5c","bytes":[92],"ascii":"\\"}
+],"length":3}
+"#;
+        assert_eq!(json(&settings, &config, b"\"A\\"), expected);
+        let expected = "{\"schema_version\":1,\"offset\":16,\"records\":[\n],\"length\":0}\n";
+        assert_eq!(json(&settings, &config, b""), expected);
+    }
+
+    #[test]
+    fn test_words() {
+        // Words follow the byte order, and a partial last word is left out; 8-byte ones are strings
+        let settings = Json {
+            words: Some(4),
+            endian: Endian::Big,
+            ..Json::new(Style::Lines)
+        };
+        let out = json(
+            &settings,
+            &Config::default(),
+            b"\x00\x00\x01\x00\xff\xff\xff\xff\x01",
+        );
+        assert!(out.ends_with(",\"words\":[256,4294967295]}\n"), "{}", out);
+        let settings = Json {
+            words: Some(8),
+            ..settings
+        };
+        let mut input = vec![0xff; 8];
+        input.extend_from_slice(&(1u64 << 53 | 1).to_be_bytes());
+        let out = json(&settings, &Config::default(), &input);
+        assert!(
+            out.ends_with(",\"words\":[\"18446744073709551615\",\"9007199254740993\"]}\n"),
+            "{}",
+            out
+        );
+    }
+}
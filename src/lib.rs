@@ -0,0 +1,789 @@
+//! Streaming hexdump formatter modeled after the Linux `hexdump` utility.
+//!
+//! The input is read one line at a time and written to any [`io::Write`]
+//! sink, so memory use does not depend on the size of the input.
+//!
+//! ```
+//! let mut out = Vec::new();
+//! hexdump::hexdump(&[0x00u8, 0x01, 0x02, 0x03][..], &mut out).unwrap();
+//! assert_eq!(out, b"00000000 0100 0302\n");
+//! ```
+
+pub mod color;
+pub mod diff;
+pub mod dumper;
+pub mod encoding;
+pub mod entropy;
+pub mod export;
+pub mod find;
+pub mod format;
+pub mod html;
+pub mod input;
+pub mod json;
+pub mod od;
+pub mod records;
+mod regex;
+pub mod reverse;
+pub mod strings;
+pub mod viewer;
+pub mod xxd;
+
+use std::collections::VecDeque; // Lines waiting for their strings
+use std::error; // Error trait
+use std::fmt; // Display formatting
+use std::io::{self, Read, Write}; // I/O operations
+use std::ops::Range; // String byte ranges
+
+pub use color::Theme; // Output colors
+pub use dumper::{Dumper, HexDumper, OffsetBase}; // Layout builder
+pub use export::Emitter; // Whole-input outputs
+pub use format::{Endian, Format, Mode}; // Output layouts
+
+use format::End; // End offset behavior
+
+/// Errors returned while producing a dump.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading the input or writing the output failed.
+    Io(io::Error),
+    /// The output format is invalid.
+    Format(format::ParseError),
+    /// A dump or a file of Intel HEX or S-records being converted back to
+    /// binary can't be parsed.
+    Reverse(reverse::ParseError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Format(e) => write!(f, "{}", e),
+            Error::Reverse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Format(e) => Some(e),
+            Error::Reverse(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<format::ParseError> for Error {
+    fn from(e: format::ParseError) -> Self {
+        Error::Format(e)
+    }
+}
+
+impl From<reverse::ParseError> for Error {
+    fn from(e: reverse::ParseError) -> Self {
+        Error::Reverse(e)
+    }
+}
+
+/// Result type used throughout the crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Settings controlling what part of the input is dumped and how.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Number of bytes at the start of the input to skip. Printed offsets
+    /// stay relative to the start of the input, not to the skipped position.
+    pub skip: u64,
+    /// Maximum number of input bytes to dump, or `None` to read until EOF.
+    pub length: Option<u64>,
+    /// Format applied to each block of input.
+    pub format: Format,
+    /// Replace runs of identical lines with a single `*` line (on by default).
+    pub squeeze: bool,
+    /// Replace runs of lines of zero bytes like `xxd -a` (off by default): the
+    /// first line of a run is shown, then `*` in place of the rest if there
+    /// are two or more of them. A run ending the input keeps its last line
+    /// after the `*`, so the length stays visible.
+    pub autoskip: bool,
+    /// Colors to highlight offsets and bytes with, or `None` for plain output.
+    pub theme: Option<Theme>,
+    /// Underline the bytes of printable strings of at least this many
+    /// characters, as listed by [`strings`]. Only shown with a theme.
+    pub mark_strings: Option<usize>,
+    /// Show the entropy of the block of this many bytes each line starts
+    /// in, as measured by [`entropy`], at the end of the line.
+    pub entropy_column: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            skip: 0,
+            length: None,
+            format: Format::default(),
+            squeeze: true,
+            autoskip: false,
+            theme: None,
+            mark_strings: None,
+            entropy_column: None,
+        }
+    }
+}
+
+impl Config {
+    /// Streams `reader` to `writer` in hexdump format using these settings.
+    ///
+    /// The first [`skip`](Config::skip) bytes are read and discarded. Use
+    /// [`dump_positioned`](Config::dump_positioned) for a reader that has
+    /// already been moved past them, for example by seeking.
+    pub fn dump<R: Read, W: Write>(&self, mut reader: R, writer: W) -> Result<()> {
+        io::copy(&mut (&mut reader).take(self.skip), &mut io::sink())?;
+        self.dump_positioned(reader, writer)
+    }
+
+    /// Like [`dump`](Config::dump), but for a reader already positioned
+    /// [`skip`](Config::skip) bytes into the input.
+    pub fn dump_positioned<R: Read, W: Write>(&self, reader: R, writer: W) -> Result<()> {
+        let limit = self.length.unwrap_or(u64::MAX);
+        write_dump(self, reader.take(limit), writer, color::Markup::Ansi)
+    }
+
+    /// Streams the part of `reader` selected by [`skip`](Config::skip) and
+    /// [`length`](Config::length) to `writer` through `emitter`, in place of
+    /// the line by line [`format`](Config::format).
+    pub fn emit<R: Read, W: Write>(
+        &self,
+        emitter: &dyn Emitter,
+        mut reader: R,
+        writer: W,
+    ) -> Result<()> {
+        io::copy(&mut (&mut reader).take(self.skip), &mut io::sink())?;
+        self.emit_positioned(emitter, reader, writer)
+    }
+
+    /// Like [`emit`](Config::emit), but for a reader already positioned
+    /// [`skip`](Config::skip) bytes into the input.
+    pub fn emit_positioned<R: Read, W: Write>(
+        &self,
+        emitter: &dyn Emitter,
+        reader: R,
+        writer: W,
+    ) -> Result<()> {
+        let limit = self.length.unwrap_or(u64::MAX);
+        export::emit(emitter, reader.take(limit), writer)?;
+        Ok(())
+    }
+}
+
+/// Streams the whole of `reader` to `writer` using the default [`Config`].
+pub fn hexdump<R: Read, W: Write>(reader: R, writer: W) -> Result<()> {
+    Config::default().dump(reader, writer)
+}
+
+// Function to stream the reader content to the writer, one block at a time, as text or HTML
+fn write_dump<R: Read, W: Write>(
+    config: &Config,
+    mut reader: R,
+    mut writer: W,
+    markup: color::Markup,
+) -> Result<()> {
+    let program = config.format.compile()?;
+    let theme = config.theme.as_ref();
+    let palette = color::Palette {
+        theme,
+        markup,
+        ..Default::default()
+    };
+    let size = program.block_size();
+    let mut block = vec![0u8; size]; // Only one block of input is held at a time, unless looking ahead
+    let mut lines = Lines {
+        squeeze: config.squeeze,
+        autoskip: config.autoskip,
+        run: 0,
+        prev: vec![0u8; size],
+        have_prev: false,
+        squeezing: false,
+        offset: config.skip,
+        width: 0,
+    };
+
+    // Underlining strings and the entropy column need the bytes after a line, so lines wait for them
+    let mut scanners = config
+        .mark_strings
+        .filter(|_| theme.is_some() || palette.is_html())
+        .map(|min| strings::Scanners::new(min, config.skip));
+    let mut blocks = config
+        .entropy_column
+        .map(|size| entropy::Blocks::new(size, config.skip));
+    let lookahead = scanners.is_some() || blocks.is_some();
+    let mut waiting: VecDeque<Vec<u8>> = VecDeque::new(); // Lines read but not shown
+    let mut found = Vec::new(); // Strings that ended, not all shown yet
+    let mut marked: Vec<Range<u64>> = Vec::new(); // Their bytes
+
+    loop {
+        let len = read_line(&mut reader, &mut block)?;
+        let end = len < size; // A short block can only be the last one
+        if !lookahead {
+            if len > 0 {
+                lines.show(&program, &mut block, len, &palette, None, &mut writer)?;
+            }
+            if end {
+                break;
+            }
+            continue;
+        }
+
+        if let Some(scanners) = &mut scanners {
+            scanners.feed(&block[..len], &mut found);
+            if end {
+                scanners.finish(&mut found);
+            }
+            marked.extend(found.drain(..).map(|string| string.range()));
+        }
+        if let Some(blocks) = &mut blocks {
+            blocks.feed(&block[..len]);
+            if end {
+                blocks.finish();
+            }
+        }
+        if len > 0 {
+            waiting.push_back(block[..len].to_vec());
+        }
+
+        // Lines are shown once their strings are known and the entropy block they start in is complete
+        let settled = match &scanners {
+            Some(scanners) if !end => scanners.settled(),
+            _ => u64::MAX,
+        };
+        let finished = match &blocks {
+            Some(blocks) if !end => blocks.finished(),
+            _ => u64::MAX,
+        };
+        while waiting.front().is_some_and(|line| {
+            lines.offset + line.len() as u64 <= settled && lines.offset < finished
+        }) {
+            let line = waiting.pop_front().unwrap();
+            marked.retain(|range| range.end > lines.offset);
+            let mut strings = marked.clone();
+            if let Some(scanners) = &scanners {
+                strings.extend(scanners.runs()); // Long enough already, though not ended
+            }
+            let palette = color::Palette {
+                strings: &strings,
+                ..palette
+            };
+            let column = blocks
+                .as_mut()
+                .and_then(|blocks| blocks.at(lines.offset))
+                .map(|block| block.column());
+            block[..line.len()].copy_from_slice(&line);
+            lines.show(
+                &program,
+                &mut block,
+                line.len(),
+                &palette,
+                column.as_deref(),
+                &mut writer,
+            )?;
+        }
+        if end {
+            break;
+        }
+    }
+    lines.end_run(true, &program, &palette, &mut writer)?;
+    let Lines {
+        offset, squeezing, ..
+    } = lines;
+
+    // Print the end offset ('_A'), or a plain one after a squeezed tail so the length stays visible
+    match program.end() {
+        End::Always => program.render_end(&mut writer, offset, &palette)?,
+        End::Dumped if offset > config.skip => {
+            if program.has_end() {
+                program.render_end(&mut writer, offset, &palette)?;
+            } else if squeezing {
+                let mut line = Vec::new();
+                palette.offset(&mut line, offset, |out| {
+                    out.extend_from_slice(format!("{:08x}", offset).as_bytes())
+                });
+                line.push(b'\n');
+                writer.write_all(&line)?;
+            }
+        }
+        End::Dumped => {}
+    }
+
+    Ok(())
+}
+
+// Lines of a dump shown so far
+struct Lines {
+    squeeze: bool,   // Whether repeated lines are squeezed
+    autoskip: bool,  // Whether repeated lines of zeros are skipped like 'xxd -a'
+    run: u64,        // Lines of zeros skipped since the last one shown
+    prev: Vec<u8>,   // Last full block printed, for squeezing
+    have_prev: bool, // Whether 'prev' holds a printed block
+    squeezing: bool, // Whether we are inside a run of repeated blocks
+    offset: u64,     // Offset of the next line in the input
+    width: usize,    // Width of the first output line of a full block, to line up columns after it
+}
+
+impl Lines {
+    // Function to show a line of 'len' bytes at the start of 'block', a full block buffer,
+    // adding 'column' at the end of its first output line
+    fn show<W: Write>(
+        &mut self,
+        program: &format::Program,
+        block: &mut Vec<u8>,
+        len: usize,
+        palette: &color::Palette,
+        column: Option<&str>,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        // Like util-linux, only full blocks identical to the previous one are squeezed
+        let size = block.len();
+        let repeated = len == size && self.have_prev && *block == self.prev;
+        if repeated && self.autoskip && block.iter().all(|&b| b == 0) {
+            self.run += 1; // Shown or replaced by '*' once the run ends
+            self.offset += len as u64;
+            return Ok(());
+        }
+        self.end_run(false, program, palette, writer)?;
+        if self.squeeze && repeated {
+            if !self.squeezing {
+                writeln!(writer, "*")?; // Mark the start of the repeated run once
+                self.squeezing = true;
+            }
+            self.offset += len as u64;
+            return Ok(());
+        }
+
+        block[len..].fill(0); // Conversions overlapping the end of input see zeros
+        match column {
+            None => program.render_block(writer, block, len, self.offset, palette)?,
+            Some(column) => {
+                let mut out = Vec::new();
+                program.render_block(&mut out, block, len, self.offset, palette)?;
+                let at = out.iter().position(|&b| b == b'\n').unwrap_or(out.len());
+                let width = visible_width(&out[..at], palette.is_html());
+                if len == size {
+                    self.width = width;
+                }
+                let pad = self.width.saturating_sub(width) + 2;
+                let text = format!("{:pad$}{}", "", column, pad = pad);
+                out.splice(at..at, text.bytes());
+                writer.write_all(&out)?;
+            }
+        }
+        self.offset += len as u64;
+        self.squeezing = false;
+        if len == size {
+            std::mem::swap(&mut self.prev, block);
+            self.have_prev = true;
+        }
+        Ok(())
+    }
+
+    // Function to end a run of skipped lines of zeros like 'xxd -a': a single skipped line is
+    // shown, more become '*', and at the end of input the last one is shown after them
+    fn end_run<W: Write>(
+        &mut self,
+        at_end: bool,
+        program: &format::Program,
+        palette: &color::Palette,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let run = std::mem::take(&mut self.run);
+        if run == 0 {
+            return Ok(());
+        }
+        let size = self.prev.len() as u64; // 'prev' is the line of zeros the run repeats
+        let before = if at_end { run - 1 } else { run }; // Skipped lines before any kept last
+        match before {
+            0 => {}
+            1 => program.render_block(
+                writer,
+                &self.prev,
+                size as usize,
+                self.offset - run * size,
+                palette,
+            )?,
+            _ => writeln!(writer, "*")?,
+        }
+        if at_end {
+            program.render_block(
+                writer,
+                &self.prev,
+                size as usize,
+                self.offset - size,
+                palette,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Function to count the characters shown by a line of output, leaving out color escapes,
+// or HTML tags and counting entities as one character
+fn visible_width(line: &[u8], html: bool) -> usize {
+    let mut width = 0;
+    let mut until = None; // Byte ending the escape, tag or entity being skipped
+    for &b in line {
+        match (until, b) {
+            (Some(end), _) if b == end => until = None,
+            (Some(_), _) => {}
+            (None, 0x1b) => until = Some(b'm'),
+            (None, b'<') if html => until = Some(b'>'),
+            (None, b'&') if html => {
+                width += 1;
+                until = Some(b';');
+            }
+            (None, 0x80..=0xbf) => {} // Continuation bytes of UTF-8 characters
+            _ => width += 1,
+        }
+    }
+    width
+}
+
+// Function to fill 'buf' from the reader, returning fewer bytes only at end of input
+fn read_line<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break, // End of input
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue, // Retry interrupted reads
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // Helper to run 'hexdump' into memory and return the output as a string
+    fn dump<R: Read>(reader: R) -> String {
+        let mut output = Vec::new();
+        hexdump(reader, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    // Reader that hands out a single byte per call, like a slow pipe
+    struct OneByteReader(Cursor<Vec<u8>>);
+
+    impl Read for OneByteReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn test_hexdump_empty() {
+        // Test case for an empty input file
+        let input = Cursor::new(vec![]);
+        assert_eq!(dump(input), ""); // Expect empty string
+    }
+
+    #[test]
+    fn test_hexdump_single_line() {
+        // Test case for a small file that fits on a single line of output
+        let input = Cursor::new(vec![0x00, 0x01, 0x02, 0x03]);
+        assert_eq!(dump(input), "00000000 0100 0302\n"); // Expected hex format
+    }
+
+    #[test]
+    fn test_hexdump_multiple_lines() {
+        // Test case for a file with multiple lines of hexdump
+        let input = Cursor::new((0..32).collect::<Vec<u8>>());
+        let expected = "\
+            00000000 0100 0302 0504 0706 0908 0b0a 0d0c 0f0e\n\
+            00000010 1110 1312 1514 1716 1918 1b1a 1d1c 1f1e\n";
+        assert_eq!(dump(input), expected); // Expected hex format
+    }
+
+    #[test]
+    fn test_hexdump_partial_line() {
+        // Test case for a file with a partial final line
+        let input = Cursor::new((0..20).collect::<Vec<u8>>());
+        let expected = "\
+            00000000 0100 0302 0504 0706 0908 0b0a 0d0c 0f0e\n\
+            00000010 1110 1312\n";
+        assert_eq!(dump(input), expected); // Expected hex format
+    }
+
+    #[test]
+    fn test_entropy_column() {
+        // Lines show the entropy of the block they start in, lined up after a partial line
+        let config = Config {
+            entropy_column: Some(32),
+            ..Config::default()
+        };
+        let mut input = vec![0u8; 32];
+        input.extend_from_slice(b"abcd");
+        let mut output = Vec::new();
+        config
+            .dump(OneByteReader(Cursor::new(input)), &mut output)
+            .unwrap();
+        let expected = "\
+            00000000 0000 0000 0000 0000 0000 0000 0000 0000  0.00 ▁\n\
+            *\n\
+            00000020 6261 6463                                2.00 ▃\n";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_hexdump_short_reads() {
+        // Test case for a reader that returns fewer bytes than requested
+        let input = OneByteReader(Cursor::new((0..20).collect::<Vec<u8>>()));
+        let expected = "\
+            00000000 0100 0302 0504 0706 0908 0b0a 0d0c 0f0e\n\
+            00000010 1110 1312\n";
+        assert_eq!(dump(input), expected); // Lines are assembled across reads
+    }
+
+    #[test]
+    fn test_config_length() {
+        // Test case for limiting the number of bytes dumped
+        let config = Config {
+            length: Some(5),
+            ..Config::default()
+        };
+        let mut output = Vec::new();
+        config
+            .dump(Cursor::new((0..32).collect::<Vec<u8>>()), &mut output)
+            .unwrap();
+        assert_eq!(output, b"00000000 0100 0302 04\n"); // Only 5 bytes dumped
+    }
+
+    #[test]
+    fn test_hexdump_odd_length() {
+        // Test case for an input ending with a single unpaired byte
+        let input = Cursor::new(vec![0x00, 0x01, 0x02]);
+        assert_eq!(dump(input), "00000000 0100 02\n"); // Last byte printed alone
+    }
+
+    // Helper to run a canonical ('-C') dump into memory
+    fn dump_canonical(bytes: &[u8]) -> String {
+        let config = Config {
+            format: Mode::Canonical.format(),
+            ..Config::default()
+        };
+        let mut output = Vec::new();
+        config.dump(bytes, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_canonical_empty() {
+        // util-linux prints nothing at all for an empty input
+        assert_eq!(dump_canonical(&[]), "");
+    }
+
+    #[test]
+    fn test_canonical_full_lines() {
+        // Golden output of `printf 'ABCDEFGHIJKLMNOP\0\x01\x7f\x80 ~\n\t@@@@@@@@' | hexdump -C`
+        let input = b"ABCDEFGHIJKLMNOP\x00\x01\x7f\x80 ~\n\t@@@@@@@@";
+        let expected = "\
+00000000  41 42 43 44 45 46 47 48  49 4a 4b 4c 4d 4e 4f 50  |ABCDEFGHIJKLMNOP|
+00000010  00 01 7f 80 20 7e 0a 09  40 40 40 40 40 40 40 40  |.... ~..@@@@@@@@|
+00000020
+";
+        assert_eq!(dump_canonical(input), expected);
+    }
+
+    #[test]
+    fn test_canonical_partial_line() {
+        // Golden output of `printf 'ABCDEFGHIJKLMNOPQR' | hexdump -C`
+        let expected = "\
+00000000  41 42 43 44 45 46 47 48  49 4a 4b 4c 4d 4e 4f 50  |ABCDEFGHIJKLMNOP|
+00000010  51 52                                             |QR|
+00000012
+";
+        assert_eq!(dump_canonical(b"ABCDEFGHIJKLMNOPQR"), expected);
+    }
+
+    #[test]
+    fn test_canonical_partial_second_group() {
+        // Golden output of `printf '0123456789' | hexdump -C`
+        let expected = "\
+00000000  30 31 32 33 34 35 36 37  38 39                    |0123456789|
+0000000a
+";
+        assert_eq!(dump_canonical(b"0123456789"), expected);
+    }
+
+    // Helper to run a dump with squeezing switched on or off
+    fn dump_squeeze(mode: Mode, squeeze: bool, bytes: &[u8]) -> String {
+        let config = Config {
+            format: mode.format(),
+            squeeze,
+            ..Config::default()
+        };
+        let mut output = Vec::new();
+        config.dump(bytes, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_squeeze_canonical_repeated_lines() {
+        // Golden output of `head -c 64 /dev/zero | hexdump -C`
+        let expected = "\
+00000000  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|
+*
+00000040
+";
+        assert_eq!(dump_squeeze(Mode::Canonical, true, &[0; 64]), expected);
+    }
+
+    #[test]
+    fn test_squeeze_resumes_after_run() {
+        // A differing line after a run is printed with its real offset
+        let mut input = vec![0u8; 48];
+        input.extend_from_slice(b"ABCDEFGHIJKLMNOPQR");
+        let expected = "\
+00000000  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|
+*
+00000030  41 42 43 44 45 46 47 48  49 4a 4b 4c 4d 4e 4f 50  |ABCDEFGHIJKLMNOP|
+00000040  51 52                                             |QR|
+00000042
+";
+        assert_eq!(dump_squeeze(Mode::Canonical, true, &input), expected);
+    }
+
+    #[test]
+    fn test_squeeze_keeps_partial_last_line() {
+        // The short final line is never squeezed, even if it matches the previous bytes
+        let expected = "\
+00000000 0000 0000 0000 0000 0000 0000 0000 0000
+*
+00000020 0000
+";
+        assert_eq!(dump_squeeze(Mode::Words, true, &[0; 34]), expected);
+    }
+
+    #[test]
+    fn test_squeeze_words_tail_prints_length() {
+        // A squeezed tail in the default mode still shows where the input ends
+        let expected = "\
+00000000 0000 0000 0000 0000 0000 0000 0000 0000
+*
+00000030
+";
+        assert_eq!(dump_squeeze(Mode::Words, true, &[0; 48]), expected);
+    }
+
+    #[test]
+    fn test_autoskip_nul_lines() {
+        // Like 'xxd -a', only lines of zeros are skipped, and one skipped line is still shown
+        let dump = |input: &[u8]| {
+            let config = Config {
+                format: Format::xxd(4, 4, false).unwrap(),
+                squeeze: false,
+                autoskip: true,
+                ..Config::default()
+            };
+            let mut output = Vec::new();
+            config.dump(input, &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        assert_eq!(
+            dump(b"AAAAAAAA"),
+            "00000000: 41414141  AAAA\n00000004: 41414141  AAAA\n"
+        );
+        let mut input = vec![0; 8];
+        input.extend_from_slice(b"A");
+        let expected =
+            "00000000: 00000000  ....\n00000004: 00000000  ....\n00000008: 41        A\n";
+        assert_eq!(dump(&input), expected);
+        let mut input = vec![0; 12];
+        input.extend_from_slice(b"A");
+        let expected = "00000000: 00000000  ....\n*\n0000000c: 41        A\n";
+        assert_eq!(dump(&input), expected);
+
+        // At the end of input, the last line of zeros is shown after the '*'
+        let expected = "\
+00000000: 00000000  ....
+00000004: 00000000  ....
+00000008: 00000000  ....
+";
+        assert_eq!(dump(&[0; 12]), expected);
+        let expected = "00000000: 00000000  ....\n*\n0000000c: 00000000  ....\n";
+        assert_eq!(dump(&[0; 16]), expected);
+    }
+
+    #[test]
+    fn test_squeeze_disabled() {
+        // With squeezing off ('-v') every line is printed
+        let expected = "\
+00000000 0000 0000 0000 0000 0000 0000 0000 0000
+00000010 0000 0000 0000 0000 0000 0000 0000 0000
+";
+        assert_eq!(dump_squeeze(Mode::Words, false, &[0; 32]), expected);
+    }
+
+    #[test]
+    fn test_skip_keeps_real_offsets() {
+        // Skipped bytes are discarded but still count towards printed offsets
+        let config = Config {
+            skip: 0x12,
+            length: Some(4),
+            format: Mode::Canonical.format(),
+            ..Config::default()
+        };
+        let mut output = Vec::new();
+        config
+            .dump(&(0..64).collect::<Vec<u8>>()[..], &mut output)
+            .unwrap();
+        let expected = "\
+00000012  12 13 14 15                                       |....|
+00000016
+";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_skip_past_end() {
+        // Skipping beyond the end of input prints nothing
+        let config = Config {
+            skip: 100,
+            format: Mode::Canonical.format(),
+            ..Config::default()
+        };
+        let mut output = Vec::new();
+        config.dump(&[0u8; 10][..], &mut output).unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_od_end_offset() {
+        // Like GNU od, the end offset is printed even for empty input, and after a squeezed tail
+        let config = Config {
+            format: Format::od(od::Address::Octal, &[], None).unwrap(),
+            ..Config::default()
+        };
+        let mut output = Vec::new();
+        config.dump(&[][..], &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "0000000\n");
+
+        let mut output = Vec::new();
+        config.dump(&[0u8; 40][..], &mut output).unwrap();
+        let expected = "\
+0000000 000000 000000 000000 000000 000000 000000 000000 000000
+*
+0000040 000000 000000 000000 000000
+0000050
+";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
@@ -1,136 +1,1544 @@
+mod args; // Command-line option parsing
+
 use std::env; // Environment
-use std::fs::File; // File Handling
-use std::io::{self, Read}; // I/O operations
+use std::fs::{File, OpenOptions}; // Files for the viewer
+use std::io::{self, BufReader, BufWriter, IsTerminal, Read, Write}; // I/O operations
+use std::path::Path; // Program name handling
+use std::process::Command; // 'stty' for the viewer's terminal mode
+
+use args::{ArgError, Opt, Parsed, Value}; // Option parsing
+use hexdump::diff::SideBySide; // Side-by-side comparison
+use hexdump::encoding::{Decoder, Encoding, TextEncoder}; // Text encodings
+use hexdump::entropy; // Entropy analysis
+use hexdump::export::{self, Export, Language}; // Source code arrays
+use hexdump::find::{Pattern, Search}; // Pattern search
+use hexdump::html::{Annotation, Html}; // HTML pages
+use hexdump::input::{parse_size, Concat, Source}; // Input handling
+use hexdump::json::{self, Json}; // JSON records
+use hexdump::od; // od output types
+use hexdump::records::{self, Encoder, Image, Kind}; // Intel HEX and S-records
+use hexdump::reverse::{self, Style}; // Dump to binary conversion
+use hexdump::strings; // Strings listing
+use hexdump::viewer::{self, Viewer}; // Full-screen viewer
+use hexdump::xxd::Include; // C include output
+use hexdump::{color, Config, Endian, Format, Mode, Theme}; // Dump engine
+
+// What size and offset values look like, for error messages
+const SIZE: &str = "a byte count such as 512, 0x200 or 4k";
+
+// What '--color' accepts, for error messages
+const WHEN: &str = "auto, always or never";
+
+// Shorthand to declare an option
+const fn opt(short: Option<char>, long: &'static str, value: Value, help: &'static str) -> Opt {
+    Opt {
+        short,
+        long: Some(long),
+        value,
+        help,
+    }
+}
+
+// Options of the hexdump personality, in the order shown by '--help'
+const HEXDUMP_OPTS: [Opt; 43] = [
+    opt(
+        Some('b'),
+        "one-byte-octal",
+        Value::None,
+        "one-byte octal display",
+    ),
+    opt(
+        Some('c'),
+        "one-byte-char",
+        Value::None,
+        "one-byte character display",
+    ),
+    opt(
+        Some('C'),
+        "canonical",
+        Value::None,
+        "canonical hex+ASCII display",
+    ),
+    opt(
+        Some('d'),
+        "two-bytes-decimal",
+        Value::None,
+        "two-byte decimal display",
+    ),
+    opt(
+        Some('o'),
+        "two-bytes-octal",
+        Value::None,
+        "two-byte octal display",
+    ),
+    opt(
+        Some('x'),
+        "two-bytes-hex",
+        Value::None,
+        "two-byte hexadecimal display",
+    ),
+    opt(
+        Some('e'),
+        "format",
+        Value::Required("FORMAT"),
+        "format string to be used for displaying data",
+    ),
+    opt(
+        Some('f'),
+        "format-file",
+        Value::Required("FILE"),
+        "file that contains format strings",
+    ),
+    opt(
+        Some('g'),
+        "group-size",
+        Value::Required("SIZE"),
+        "bytes per group in the default layout: 1, 2, 4, 8 or 16",
+    ),
+    opt(
+        Some('E'),
+        "endian",
+        Value::Required("ENDIAN"),
+        "byte order: little, big or native",
+    ),
+    opt(
+        Some('n'),
+        "length",
+        Value::Required("LEN"),
+        "interpret only LEN bytes of input",
+    ),
+    opt(
+        Some('s'),
+        "skip",
+        Value::Required("OFFSET"),
+        "skip OFFSET bytes from the beginning",
+    ),
+    opt(
+        Some('v'),
+        "no-squeezing",
+        Value::None,
+        "output identical lines",
+    ),
+    opt(
+        Some('r'),
+        "reverse",
+        Value::None,
+        "convert a dump back into binary",
+    ),
+    opt(
+        Some('L'),
+        "color",
+        Value::Optional("WHEN"),
+        "color the output: auto (default), always or never",
+    ),
+    Opt {
+        short: None,
+        long: Some("interactive"),
+        value: Value::None,
+        help: "browse and edit FILE full-screen",
+    },
+    Opt {
+        short: None,
+        long: Some("diff"),
+        value: Value::None,
+        help: "compare FILEs side by side with the first",
+    },
+    Opt {
+        short: None,
+        long: Some("context"),
+        value: Value::Optional("LINES"),
+        help: "with --diff, show only differing lines and LINES (3) around them; \
+               with --find, show LINES around matches",
+    },
+    Opt {
+        short: None,
+        long: Some("find"),
+        value: Value::Required("PATTERN"),
+        help: "show only the lines matching hex bytes (?? for any), text:, utf16: or re:",
+    },
+    opt(
+        Some('A'),
+        "after-context",
+        Value::Required("LINES"),
+        "with --find, show LINES after each match",
+    ),
+    opt(
+        Some('B'),
+        "before-context",
+        Value::Required("LINES"),
+        "with --find, show LINES before each match",
+    ),
+    Opt {
+        short: None,
+        long: Some("strings"),
+        value: Value::Optional("MIN"),
+        help: "list strings of at least MIN (4) characters in ASCII, UTF-8 or UTF-16",
+    },
+    Opt {
+        short: None,
+        long: Some("mark-strings"),
+        value: Value::Optional("MIN"),
+        help: "underline strings of at least MIN (4) characters in colored dumps",
+    },
+    Opt {
+        short: None,
+        long: Some("entropy"),
+        value: Value::Optional("BLOCK"),
+        help: "show the entropy of each BLOCK (1k) bytes and a byte histogram",
+    },
+    Opt {
+        short: None,
+        long: Some("entropy-column"),
+        value: Value::Optional("BLOCK"),
+        help: "show the entropy of the BLOCK (256) bytes each line starts in",
+    },
+    Opt {
+        short: None,
+        long: Some("export"),
+        value: Value::Required("LANG"),
+        help: "write an array literal: c, rust, python, go, js, java, csharp or zig",
+    },
+    Opt {
+        short: None,
+        long: Some("name"),
+        value: Value::Required("NAME"),
+        help: "with --export, name the array NAME instead of after FILE",
+    },
+    Opt {
+        short: None,
+        long: Some("cols"),
+        value: Value::Required("COLS"),
+        help: "with --export or --json, write COLS (12, 16 for JSON) bytes per line",
+    },
+    Opt {
+        short: None,
+        long: Some("no-length"),
+        value: Value::None,
+        help: "with --export, leave out the length constant",
+    },
+    Opt {
+        short: None,
+        long: Some("const"),
+        value: Value::None,
+        help: "with --export, make the array read-only",
+    },
+    Opt {
+        short: None,
+        long: Some("static"),
+        value: Value::None,
+        help: "with --export, give the array static storage",
+    },
+    Opt {
+        short: None,
+        long: Some("json"),
+        value: Value::None,
+        help: "write the input as one JSON document of records",
+    },
+    Opt {
+        short: None,
+        long: Some("ndjson"),
+        value: Value::None,
+        help: "write the input as JSON records, one per line",
+    },
+    Opt {
+        short: None,
+        long: Some("words"),
+        value: Value::Required("SIZE"),
+        help: "with --json, add the words of SIZE bytes (1, 2, 4 or 8) in each record",
+    },
+    Opt {
+        short: None,
+        long: Some("html"),
+        value: Value::None,
+        help: "write a self-contained HTML page of the dump (canonical by default)",
+    },
+    Opt {
+        short: None,
+        long: Some("annotate"),
+        value: Value::Required("NOTE"),
+        help: "with --html, note OFFSET[+LENGTH]:TEXT on those bytes (repeatable)",
+    },
+    Opt {
+        short: None,
+        long: Some("input-encoding"),
+        value: Value::Required("ENC"),
+        help: "decode the input from ENC (hex, base64, ...), or dump the memory ihex or srec records load",
+    },
+    Opt {
+        short: None,
+        long: Some("output"),
+        value: Value::Required("ENC"),
+        help: "write the input as ENC (hex, base64, ..., ihex or srec) instead of dumping it",
+    },
+    Opt {
+        short: None,
+        long: Some("record-size"),
+        value: Value::Required("SIZE"),
+        help: "with --output, put SIZE (16) bytes in each record",
+    },
+    Opt {
+        short: None,
+        long: Some("base"),
+        value: Value::Required("ADDRESS"),
+        help: "with --output, load the input at ADDRESS (0)",
+    },
+    Opt {
+        short: None,
+        long: Some("wrap"),
+        value: Value::Required("COLS"),
+        help: "with --output, wrap text at COLS (76) characters, or never with 0",
+    },
+    opt(Some('h'), "help", Value::None, "display this help"),
+    opt(Some('V'), "version", Value::None, "display version"),
+];
+
+// Options of the xxd personality
+const XXD_OPTS: [Opt; 14] = [
+    opt(
+        Some('a'),
+        "autoskip",
+        Value::None,
+        "toggle autoskip: a single '*' replaces nul-lines",
+    ),
+    opt(
+        Some('c'),
+        "cols",
+        Value::Required("COLS"),
+        "format COLS octets per line",
+    ),
+    opt(
+        Some('g'),
+        "groupsize",
+        Value::Required("BYTES"),
+        "number of octets per group in normal output",
+    ),
+    opt(
+        Some('i'),
+        "include",
+        Value::None,
+        "output in C include file style",
+    ),
+    opt(
+        Some('l'),
+        "len",
+        Value::Required("LEN"),
+        "stop after LEN octets",
+    ),
+    opt(
+        Some('p'),
+        "postscript",
+        Value::None,
+        "output in plain hexdump style",
+    ),
+    opt(None, "plain", Value::None, "same as -p"),
+    opt(None, "ps", Value::None, "same as -p"),
+    opt(
+        Some('r'),
+        "revert",
+        Value::None,
+        "reverse operation: convert hexdump into binary",
+    ),
+    opt(
+        Some('s'),
+        "seek",
+        Value::Required("OFFSET"),
+        "start at OFFSET bytes",
+    ),
+    opt(
+        Some('u'),
+        "uppercase",
+        Value::None,
+        "use upper case hex letters",
+    ),
+    opt(
+        Some('R'),
+        "color",
+        Value::Required("WHEN"),
+        "color the output: auto (default), always or never",
+    ),
+    opt(Some('h'), "help", Value::None, "display this help"),
+    opt(Some('v'), "version", Value::None, "display version"),
+];
+
+// Shorthand to declare a traditional od option standing for an output type
+const fn od_type(short: char, help: &'static str) -> Opt {
+    Opt {
+        short: Some(short),
+        long: None,
+        value: Value::None,
+        help,
+    }
+}
+
+// Options of the od personality
+const OD_OPTS: [Opt; 19] = [
+    opt(
+        Some('A'),
+        "address-radix",
+        Value::Required("RADIX"),
+        "offset radix: d, o, x or n",
+    ),
+    opt(
+        Some('j'),
+        "skip-bytes",
+        Value::Required("BYTES"),
+        "skip BYTES input bytes first",
+    ),
+    opt(
+        Some('N'),
+        "read-bytes",
+        Value::Required("BYTES"),
+        "limit dump to BYTES input bytes",
+    ),
+    opt(
+        Some('t'),
+        "format",
+        Value::Required("TYPE"),
+        "select output format or formats",
+    ),
+    opt(
+        Some('v'),
+        "output-duplicates",
+        Value::None,
+        "do not use * to mark line suppression",
+    ),
+    opt(
+        Some('w'),
+        "width",
+        Value::Optional("BYTES"),
+        "output BYTES bytes per line; 32 if BYTES is omitted",
+    ),
+    od_type('a', "same as -t a, select named characters"),
+    od_type('b', "same as -t o1, select octal bytes"),
+    od_type(
+        'c',
+        "same as -t c, select printable characters or backslash escapes",
+    ),
+    od_type('d', "same as -t u2, select unsigned decimal 2-byte units"),
+    od_type('f', "same as -t fF, select floats"),
+    od_type('i', "same as -t dI, select decimal ints"),
+    od_type('l', "same as -t dL, select decimal longs"),
+    od_type('o', "same as -t o2, select octal 2-byte units"),
+    od_type('s', "same as -t d2, select decimal 2-byte units"),
+    od_type('x', "same as -t x2, select hexadecimal 2-byte units"),
+    Opt {
+        short: None,
+        long: Some("color"),
+        value: Value::Optional("WHEN"),
+        help: "color the output: auto, always or never (default)",
+    },
+    opt(None, "help", Value::None, "display this help"),
+    opt(None, "version", Value::None, "display version"),
+];
+
+// The tool the program behaves like
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Personality {
+    Hexdump,
+    Xxd,
+    Od,
+}
+
+impl Personality {
+    // Name used in messages
+    fn name(self) -> &'static str {
+        match self {
+            Personality::Hexdump => "hexdump",
+            Personality::Xxd => "xxd",
+            Personality::Od => "od",
+        }
+    }
+
+    // Options accepted by this personality
+    fn opts(self) -> &'static [Opt] {
+        match self {
+            Personality::Hexdump => &HEXDUMP_OPTS,
+            Personality::Xxd => &XXD_OPTS,
+            Personality::Od => &OD_OPTS,
+        }
+    }
+
+    // '--help' text for this personality
+    fn help(self, program: &str) -> String {
+        let (summary, notes) = match self {
+            Personality::Hexdump => (
+                "Display file contents in hexadecimal, decimal, octal, or ascii.",
+                "LEN and OFFSET may be hexadecimal (0x), octal (leading 0) and take a\n\
+                 b, k, m, g, KiB, MiB, GiB, KB, MB or GB multiplier.\n\
+                 Run as 'xxd' or with '--xxd' first for xxd options, as 'od' or\n\
+                 with '--od' first for od options.",
+            ),
+            Personality::Xxd => (
+                "Make a hex dump or do the reverse.",
+                "Options can also be written with a single dash, as in '-cols 8'.",
+            ),
+            Personality::Od => (
+                "Write an unambiguous representation, octal bytes by default, of FILE.",
+                "TYPE is made up of one or more of a (named character), c (character),\n\
+                 d[SIZE] (signed decimal), f[SIZE] (floating point), o[SIZE] (octal),\n\
+                 u[SIZE] (unsigned decimal) or x[SIZE] (hexadecimal), each optionally\n\
+                 followed by z to show printable characters at the end of each line.\n\
+                 SIZE is 1, 2, 4 or 8 (4 or 8 for floats), or C, S, I, L (F, D).",
+            ),
+        };
+        format!(
+            "Usage: {} [OPTION]... [FILE]...\n{}\n\nOptions:\n{}\nWith no FILE, or when FILE is -, read standard input.\n{}\n",
+            program,
+            summary,
+            args::help(self.opts()),
+            notes
+        )
+    }
+}
+
+// When to color the output
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum When {
+    Auto,   // When writing to a terminal and NO_COLOR isn't set
+    Always, // Even when writing to a file or pipe
+    Never,  // Plain output
+}
+
+impl When {
+    // Function to read a '--color' value; a bare '--color' means always
+    fn parse(option: &Parsed) -> Result<When, ArgError> {
+        match option.value() {
+            "auto" => Ok(When::Auto),
+            "always" | "" => Ok(When::Always),
+            "never" => Ok(When::Never),
+            _ => Err(option.invalid(WHEN)),
+        }
+    }
 
+    // The theme from HEXDUMP_COLORS if output to 'terminal' or not should be colored
+    fn theme(self, terminal: bool) -> Option<Theme> {
+        let enabled = match self {
+            When::Always => true,
+            When::Never => false,
+            When::Auto => terminal && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+        };
+        enabled.then(|| Theme::parse(&env::var(color::THEME_VAR).unwrap_or_default()))
+    }
+}
+
+// What the program has been asked to do with its input
 #[derive(Debug, PartialEq)]
-// Defining custom errors to handle argument parsing errors
-enum ArgError {
-    InvalidUsage,  // Error for incorrect usage of CLI arguments
-    InvalidLength, // Error for invalid length argument
+enum Action {
+    Dump(Config, When),                // Dump the input using a format, colored or not
+    View(u64, When),                   // Browse and edit a file full-screen, starting at an offset
+    Diff(Config, Option<usize>, When), // Compare inputs side by side, maybe with only some context
+    Find(Config, Search, When),        // Dump only the lines with matches, and some context
+    Strings(Config, usize, When),      // List strings of at least some characters, with offsets
+    Entropy(Config, u64, When),        // Show the entropy of each block, and the byte frequencies
+    Include(Config, Include),          // Write the input as a C array, like 'xxd -i'
+    Export(Config, Export),            // Write the input as an array in some language
+    Json(Config, Json),                // Write the input as JSON records
+    Html(Config, Html),                // Write the dump as an HTML page
+    Load(Config, Kind, When),          // Dump the memory image loaded by a record file
+    Encode(Config, Encoder),           // Write the input as records
+    Text(Config, TextEncoder),         // Write the input as encoded text
+    Decoded(Encoding, Box<Action>),    // Do another action with the input decoded from text
+    Reverse(Style),                    // Convert a dump back into binary
+    Help,                              // Print the usage text
+    Version,                           // Print the version
 }
 
 fn main() -> io::Result<()> {
     // Collect CLI args
     let args: Vec<String> = env::args().collect();
+    let personality = personality(&args);
+    let name = personality.name();
 
-    // Parse the args and handle errors
-    let (filename, max_bytes) = match parse_args(&args) {
+    // Parse the args, naming the offending argument on errors
+    let (sources, action) = match parse_args(&args) {
         Ok(result) => result, // On success, return parsed result
-        Err(ArgError::InvalidUsage) => {
-            // Display usage message if the argument format is incorrect
-            eprintln!("Usage: {} [-n LEN] FILE", args[0]);
-            std::process::exit(1);
+        Err(e) => {
+            eprintln!("{}: {}", name, e);
+            eprintln!("Try '{} --help' for more information.", program(&args));
+            std::process::exit(usage_status(&args));
         }
-        Err(ArgError::InvalidLength) => {
-            // Display error if the length argument is invalid
-            eprintln!("Invalid length argument");
-            std::process::exit(1);
+    };
+
+    match action {
+        Action::Help => {
+            print!("{}", personality.help(&program(&args)));
+            return Ok(());
+        }
+        Action::Version => {
+            let version = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+            match personality {
+                Personality::Hexdump => println!("{}", version),
+                _ => println!("{} ({})", name, version), // Says which tool provides it
+            }
+            return Ok(());
         }
+        _ => {}
+    }
+
+    // Input encoded as text is decoded as it is read
+    let (decoding, mut action) = match action {
+        Action::Decoded(encoding, action) => (Some(encoding), *action),
+        action => (None, action),
     };
 
-    // Open the file based on the parsed filename
-    let mut file = File::open(filename)?;
+    // Colors depend on where the output goes, so they are picked here
+    if let Action::Dump(config, when)
+    | Action::Diff(config, _, when)
+    | Action::Find(config, _, when)
+    | Action::Strings(config, _, when)
+    | Action::Entropy(config, _, when)
+    | Action::Load(config, _, when) = &mut action
+    {
+        config.theme = when.theme(io::stdout().is_terminal());
+    }
+
+    // Strings are marked by their color, so a plain dump would quietly show none
+    if let Action::Dump(config, _) | Action::Load(config, ..) = &action {
+        if config.mark_strings.is_some() && config.theme.is_none() {
+            eprintln!(
+                "{}: --mark-strings needs colored output (try --color=always)",
+                name
+            );
+        }
+    }
+
+    // The viewer reads its one file lazily instead of streaming the input
+    if let Action::View(start, when) = action {
+        let result = match sources.as_slice() {
+            _ if decoding.is_some() => {
+                eprintln!("{}: --interactive can't decode its FILE", name);
+                std::process::exit(1);
+            }
+            [Source::File(path)] => view(path, start, when.theme(true)),
+            _ => {
+                eprintln!("{}: --interactive needs exactly one FILE", name);
+                std::process::exit(1);
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("{}: {}", name, e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Comparisons read every input whole, as insertions can be anywhere
+    if let Action::Diff(config, context, _) = action {
+        match diff(&sources, config, context, decoding) {
+            Ok(differs) => std::process::exit(differs as i32),
+            Err(e) if closed_output(&e) => std::process::exit(0),
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                std::process::exit(2); // Like diff and cmp, 1 only means the inputs differ
+            }
+        }
+    }
 
-    // Buffer to hold file content
-    let mut buffer = Vec::new();
+    // Chain all inputs into one stream, reporting files that can't be opened
+    let input = Concat::new(sources, |source, e| {
+        eprintln!("{}: {}: {}", name, source, e);
+    });
+    let mut input = match decoding {
+        Some(encoding) => Input::Decoded(Decoder::new(encoding, input)),
+        None => Input::Raw(input),
+    };
 
-    // Determine whether to read the whole file to limit by 'max_bytes'
-    let bytes_read = match max_bytes {
-        Some(len) => file.take(len as u64).read_to_end(&mut buffer)?, // Limit bytes
-        None => file.read_to_end(&mut buffer)?, // Read entire file if no length is provided
+    // Stream the input straight to stdout, after moving past the skipped
+    // bytes (seeking where the input allows it)
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut matched = true; // Whether '--find' found anything
+    let result = match &action {
+        Action::Dump(config, _) => match input.skip(config.skip) {
+            // Like GNU od, skipping past the end of the input is an error for od
+            Ok(skipped) if skipped < config.skip && personality == Personality::Od => {
+                eprintln!("{}: cannot skip past end of combined input", name);
+                std::process::exit(1);
+            }
+            Ok(_) => config.dump_positioned(&mut input, &mut out),
+            Err(e) => Err(e.into()),
+        },
+        Action::Find(config, search, _) => match input.skip(config.skip) {
+            Ok(_) => search
+                .write(config, &mut input, &mut out)
+                .map(|count| matched = count > 0),
+            Err(e) => Err(e.into()),
+        },
+        Action::Strings(config, min, _) => input
+            .skip(config.skip)
+            .and_then(|_| strings::write(config, *min, &mut input, &mut out))
+            .map(|_| ())
+            .map_err(hexdump::Error::from),
+        Action::Entropy(config, block, _) => input
+            .skip(config.skip)
+            .and_then(|_| entropy::write(config, *block, &mut input, &mut out))
+            .map_err(hexdump::Error::from),
+        Action::Include(config, include) => match input.skip(config.skip) {
+            Ok(_) => config.emit_positioned(include, &mut input, &mut out),
+            Err(e) => Err(e.into()),
+        },
+        Action::Export(config, export) => match input.skip(config.skip) {
+            Ok(_) => config.emit_positioned(export, &mut input, &mut out),
+            Err(e) => Err(e.into()),
+        },
+        Action::Json(config, settings) => input
+            .skip(config.skip)
+            .and_then(|_| settings.write(config, &mut input, &mut out))
+            .map(|_| ())
+            .map_err(hexdump::Error::from),
+        Action::Html(config, html) => match input.skip(config.skip) {
+            Ok(_) => html.write(config, &mut input, &mut out),
+            Err(e) => Err(e.into()),
+        },
+        Action::Load(config, kind, _) => Image::read(*kind, BufReader::new(&mut input))
+            .and_then(|image| image.dump(config, &mut out)), // Skip and length select addresses
+        Action::Encode(config, encoder) => input
+            .skip(config.skip)
+            .and_then(|_| encoder.write(config, &mut input, &mut out))
+            .map(|_| ())
+            .map_err(hexdump::Error::from),
+        Action::Text(config, encoder) => input
+            .skip(config.skip)
+            .and_then(|_| encoder.write(config, &mut input, &mut out))
+            .map(|_| ())
+            .map_err(hexdump::Error::from),
+        Action::Reverse(style) => reverse::reverse(*style, BufReader::new(&mut input), &mut out),
+        Action::View(..)
+        | Action::Diff(..)
+        | Action::Decoded(..)
+        | Action::Help
+        | Action::Version => Ok(()),
     };
+    // A reader that stopped early, as in 'hexdump FILE | head', ends the dump quietly
+    let result = result.and_then(|_| out.flush().map_err(hexdump::Error::from));
+    if let Err(hexdump::Error::Io(e)) = &result {
+        if closed_output(e) {
+            std::process::exit(0);
+        }
+    }
+    if let Err(e) = result {
+        eprintln!("{}: {}", name, e);
+        // Like grep, a search exits with 1 only when nothing matched
+        std::process::exit(if matches!(action, Action::Find(..)) {
+            2
+        } else {
+            1
+        });
+    }
 
-    // Call 'hexdump' function to convert the file content to hexadecimal format
-    let output = hexdump(&buffer[..bytes_read])?;
-    print!("{}", output);
+    // Exit with an error if any file was skipped, like util-linux
+    if input.failures() > 0 {
+        std::process::exit(if matches!(action, Action::Find(..)) {
+            2
+        } else {
+            1
+        });
+    }
+    if !matched {
+        std::process::exit(1);
+    }
 
     Ok(())
 }
 
-// Function to parse CLI arguments
-fn parse_args(args: &[String]) -> Result<(&str, Option<usize>), ArgError> {
-    match args.len() {
-        2 => Ok((&args[1], None)), // If only filename is provided, no byte limit
-        4 if args[1] == "-n" => {
-            // Parse length arguemnt and ensure it's a valid number
-            let len = args[2].parse().map_err(|_| ArgError::InvalidLength)?;
-            Ok((&args[3], Some(len))) // Return filename and length if valid
+// The chained inputs, maybe decoded from text
+enum Input<F> {
+    Raw(Concat<F>),              // Read as they are
+    Decoded(Decoder<Concat<F>>), // Read through a decoder
+}
+
+impl<F: FnMut(&Source, io::Error)> Input<F> {
+    // Function to move past 'n' bytes, seeking only when the bytes are the input's own
+    fn skip(&mut self, n: u64) -> io::Result<u64> {
+        match self {
+            Input::Raw(input) => input.skip(n),
+            Input::Decoded(input) => io::copy(&mut input.take(n), &mut io::sink()),
+        }
+    }
+
+    // Number of files that couldn't be read
+    fn failures(&self) -> usize {
+        match self {
+            Input::Raw(input) => input.failures(),
+            Input::Decoded(input) => input.get_ref().failures(),
+        }
+    }
+}
+
+impl<F: FnMut(&Source, io::Error)> Read for Input<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::Raw(input) => input.read(buf),
+            Input::Decoded(input) => input.read(buf),
+        }
+    }
+}
+
+// Function to run the full-screen viewer and editor on a file, with the terminal in raw mode meanwhile
+fn view(path: &Path, start: u64, theme: Option<Theme>) -> io::Result<()> {
+    let mut viewer = Viewer::new(File::open(path)?, theme)?.with_path(path);
+    viewer.goto(start);
+
+    // Keys are read from and the screen drawn on the terminal, whatever stdin and stdout are
+    let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let saved = stty(&tty, &["-g"])?;
+    stty(&tty, &["raw", "-echo"])?;
+    let result = viewer::run(&mut viewer, &tty, &tty, || {
+        let size = stty(&tty, &["size"]).ok()?;
+        size.split_whitespace().next()?.parse().ok()
+    });
+    stty(&tty, &[saved.trim()])?; // Restore the terminal even if the viewer failed
+    result
+}
+
+// Function to compare each input with the first, returning whether any differ
+fn diff(
+    sources: &[Source],
+    config: Config,
+    context: Option<usize>,
+    decoding: Option<Encoding>,
+) -> io::Result<bool> {
+    if sources.len() < 2 {
+        return Err(io::Error::other("--diff needs at least two FILEs"));
+    }
+    // The skipped bytes and length apply to every input, after decoding
+    let read = |source: &Source| -> io::Result<Vec<u8>> {
+        let mut reader = match decoding {
+            Some(encoding) => Box::new(Decoder::new(encoding, source.open()?)),
+            None => source.open()?,
+        };
+        io::copy(&mut reader.by_ref().take(config.skip), &mut io::sink())?;
+        let mut bytes = Vec::new();
+        reader
+            .take(config.length.unwrap_or(u64::MAX))
+            .read_to_end(&mut bytes)?;
+        Ok(bytes)
+    };
+    let with_name = |source: &Source| {
+        read(source).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", source, e)))
+    };
+
+    let settings = SideBySide {
+        context,
+        theme: config.theme,
+        start: config.skip, // Offsets are the inputs' own
+    };
+    let first = with_name(&sources[0])?;
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut differs = false;
+    for (i, source) in sources.iter().enumerate().skip(1) {
+        if i > 1 {
+            writeln!(out)?;
+        }
+        let names = (sources[0].to_string(), source.to_string());
+        differs |= settings.write((&names.0, &names.1), &first, &with_name(source)?, &mut out)?;
+    }
+    out.flush()?;
+    Ok(differs)
+}
+
+// Function to run 'stty' on the terminal, returning what it prints
+fn stty(tty: &File, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(tty.try_clone()?)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "stty {}: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Picks the personality from the program name ('xxd', 'od') or a first '--xxd' or '--od'
+fn personality(args: &[String]) -> Personality {
+    let name = args.first().and_then(|arg| Path::new(arg).file_stem());
+    let first = args.get(1).map(String::as_str);
+    if name.is_some_and(|name| name == "xxd") || first == Some("--xxd") {
+        Personality::Xxd
+    } else if name.is_some_and(|name| name == "od") || first == Some("--od") {
+        Personality::Od
+    } else {
+        Personality::Hexdump
+    }
+}
+
+// Whether writing failed because standard output was closed by its reader
+fn closed_output(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::BrokenPipe
+}
+
+// The command as typed, including a personality switch, for usage messages
+fn program(args: &[String]) -> String {
+    let program = args.first().map_or("hexdump", String::as_str);
+    match args.get(1).map(String::as_str) {
+        Some(switch @ ("--xxd" | "--od")) => format!("{} {}", program, switch),
+        _ => program.to_string(),
+    }
+}
+
+// The arguments after the program name and any personality switch
+fn option_args(args: &[String]) -> &[String] {
+    let rest = args.get(1..).unwrap_or_default();
+    // The personality switch itself is not an option of that personality
+    match rest.first().map(String::as_str) {
+        Some("--xxd" | "--od") => &rest[1..],
+        _ => rest,
+    }
+}
+
+// Exit status for arguments that can't be used: 2 for a search or a comparison,
+// where 1 means nothing matched or the inputs differ (like grep and diff), and
+// 1 otherwise (like util-linux)
+fn usage_status(args: &[String]) -> i32 {
+    const MODES: [&str; 2] = ["find", "diff"];
+    let rest = option_args(args);
+    let personality = personality(args);
+    let modal = match args::parse(personality.opts(), personality == Personality::Xxd, rest) {
+        Ok((options, _)) => options
+            .iter()
+            .any(|option| option.opt.long.is_some_and(|long| MODES.contains(&long))),
+        // Options that don't parse are looked for as typed
+        Err(_) => rest.iter().take_while(|arg| *arg != "--").any(|arg| {
+            let name = arg.split('=').next().unwrap_or_default();
+            MODES
+                .iter()
+                .any(|mode| name.strip_prefix("--") == Some(mode))
+        }),
+    };
+    if modal {
+        2
+    } else {
+        1
+    }
+}
+
+// Function to parse CLI arguments for the selected personality
+fn parse_args(args: &[String]) -> Result<(Vec<Source>, Action), ArgError> {
+    let rest = option_args(args);
+    let personality = personality(args);
+    let (options, operands) =
+        args::parse(personality.opts(), personality == Personality::Xxd, rest)?;
+
+    // '--help' and '--version' win over everything else
+    for option in &options {
+        match option.opt.long {
+            Some("help") => return Ok((Vec::new(), Action::Help)),
+            Some("version") => return Ok((Vec::new(), Action::Version)),
+            _ => {}
         }
-        _ => Err(ArgError::InvalidUsage), // Error for incorrect usage
     }
+
+    // Read stdin when no FILE is given
+    let sources = if operands.is_empty() {
+        vec![Source::Stdin]
+    } else {
+        operands.iter().map(|arg| Source::from_arg(arg)).collect()
+    };
+
+    let action = match personality {
+        Personality::Hexdump => hexdump_action(&options, &sources)?,
+        Personality::Xxd => xxd_action(&options, &sources)?,
+        Personality::Od => od_action(&options)?,
+    };
+
+    // Input encoded as text is decoded before any action sees it
+    let input_encoding = options
+        .iter()
+        .rfind(|option| option.opt.long == Some("input-encoding"));
+    if let Some(option) = input_encoding {
+        if let Coding::Text(encoding) = coding_value(option)? {
+            if encoding != Encoding::Raw {
+                return Ok((sources, Action::Decoded(encoding, Box::new(action))));
+            }
+        }
+    }
+    Ok((sources, action))
+}
+
+// Function to parse a byte count or offset value
+fn size_value(option: &Parsed) -> Result<u64, ArgError> {
+    parse_size(option.value()).ok_or_else(|| option.invalid(SIZE))
+}
+
+// Function to parse a number of context lines
+fn lines_value(option: &Parsed) -> Result<usize, ArgError> {
+    option
+        .value()
+        .parse()
+        .map_err(|_| option.invalid("a number of lines"))
+}
+
+// Function to parse a minimum string length, 4 if not given
+fn min_value(option: &Parsed) -> Result<usize, ArgError> {
+    match option.value() {
+        "" => Ok(4),
+        value => value
+            .parse()
+            .ok()
+            .filter(|&min| min > 0)
+            .ok_or_else(|| option.invalid("a number of characters")),
+    }
+}
+
+// Names accepted by '--input-encoding' and '--output'
+const CODINGS: &str = "raw, hex, base64, base64url, base32, ascii85, z85, ihex or srec";
+
+// An encoding of the input or output: records or text
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Coding {
+    Records(Kind),  // Intel HEX or S-records, addressed
+    Text(Encoding), // The bytes as text, in order
 }
 
-// Function to convert the file content into a hexadecimal dump format
-fn hexdump<R: Read>(mut reader: R) -> io::Result<String> {
-    let mut buffer = Vec::new();
-    reader.read_to_end(&mut buffer)?; // Read the content into buffer
+// Function to parse the name of a record format or text encoding
+fn coding_value(option: &Parsed) -> Result<Coding, ArgError> {
+    let value = option.value();
+    match value.parse() {
+        Ok(kind) => Ok(Coding::Records(kind)),
+        Err(_) => value
+            .parse()
+            .map(Coding::Text)
+            .map_err(|_| option.invalid(CODINGS)),
+    }
+}
 
-    let mut output = String::new(); // String to store the final output
+// Function to parse a block size, 'default' if not given
+fn block_value(option: &Parsed, default: u64) -> Result<u64, ArgError> {
+    match option.value() {
+        "" => Ok(default),
+        _ => Some(size_value(option)?)
+            .filter(|&size| size > 0)
+            .ok_or_else(|| option.invalid(SIZE)),
+    }
+}
 
-    // Process buffer in chunks of 16 bytes
-    for (i, chunk) in buffer.chunks(16).enumerate() {
-        output.push_str(&format!("{:08x}", i * 16)); // Print address offset
+// Long options that do something else than dumping the input, so only one of them can be used
+const MODES: [&str; 11] = [
+    "reverse",
+    "interactive",
+    "diff",
+    "strings",
+    "entropy",
+    "export",
+    "json",
+    "ndjson",
+    "html",
+    "output",
+    "find",
+];
+
+// Modes writing something else than the lines of a dump
+const UNLAID_MODES: [&str; 9] = [
+    "reverse",
+    "interactive",
+    "diff",
+    "strings",
+    "entropy",
+    "export",
+    "json",
+    "ndjson",
+    "output",
+];
+
+// Long options shaping the lines of a dump
+const LAYOUT_OPTIONS: [&str; 12] = [
+    "one-byte-octal",
+    "one-byte-char",
+    "canonical",
+    "two-bytes-decimal",
+    "two-bytes-octal",
+    "two-bytes-hex",
+    "format",
+    "format-file",
+    "group-size",
+    "no-squeezing",
+    "mark-strings",
+    "entropy-column",
+];
+
+// Function to reject options asking for different things to be done with the input,
+// such as '--diff' and '--json', naming the later one first, and layout options given
+// to a mode that writes no dump, such as '-x' with '--strings'
+fn check_modes(options: &[Parsed]) -> Result<(), ArgError> {
+    let mut first: Option<&Parsed> = None;
+    for option in options {
+        let is_mode = match option.opt.long {
+            // Record files are dumped as memory images, while decoded text can be used any way
+            Some("input-encoding") => matches!(coding_value(option)?, Coding::Records(_)),
+            Some(long) => MODES.contains(&long),
+            None => false,
+        };
+        match first {
+            _ if !is_mode => {}
+            Some(first) if first.opt.long != option.opt.long => {
+                return Err(ArgError::Conflict(option.name.clone(), first.name.clone()));
+            }
+            Some(_) => {} // The same option again, whose last value is used
+            None => first = Some(option),
+        }
+    }
+    let Some(mode) = first.filter(|mode| {
+        mode.opt
+            .long
+            .is_some_and(|long| UNLAID_MODES.contains(&long))
+    }) else {
+        return Ok(());
+    };
+    match options.iter().find(|option| {
+        option
+            .opt
+            .long
+            .is_some_and(|long| LAYOUT_OPTIONS.contains(&long))
+    }) {
+        Some(layout) => Err(ArgError::Conflict(layout.name.clone(), mode.name.clone())),
+        None => Ok(()),
+    }
+}
+
+// Function to build the hexdump action from its options
+fn hexdump_action(options: &[Parsed], sources: &[Source]) -> Result<Action, ArgError> {
+    check_modes(options)?;
+    let mut config = Config::default();
+    let mut reverse = false; // Convert a dump back to binary instead
+    let mut format: Option<Format> = None; // Formats from '-C', '-e' and '-f', in the order given
+    let mut group = None; // Group size for the default layout
+    let mut endian = None; // Byte order for multi-byte values
+    let mut color = When::Auto; // '-L': when to color the output
+    let mut interactive = false; // Browse the file instead of dumping it
+    let mut diff = false; // Compare the inputs instead of dumping them
+    let mut context = None; // Lines shown around differences or matches
+    let mut find = None; // Pattern to show the matches of
+    let mut before = None; // '-B': lines shown before matches
+    let mut after = None; // '-A': lines shown after matches
+    let mut strings = None; // Minimum length of the strings to list instead of dumping
+    let mut entropy = None; // Block size to measure the entropy of instead of dumping
+    let mut export = None; // Language to write the input as an array in
+    let mut name = None; // '--name': the array's name
+    let mut cols = None; // '--cols': bytes per line of the array
+    let mut length = true; // Whether to add a length constant
+    let mut is_const = false; // '--const'
+    let mut is_static = false; // '--static'
+    let mut style = None; // '--json' or '--ndjson': write JSON records
+    let mut words = None; // '--words': size of the words in JSON records
+    let mut html = false; // '--html': write the dump as an HTML page
+    let mut annotations = Vec::new(); // '--annotate': notes on bytes of the page
+    let mut load = None; // '--input-encoding': record format to read the input as
+    let mut output = None; // '--output': records or text to write the input as
+    let mut wrap = 76; // '--wrap': characters per line of text output
+    let mut record_size = None; // '--record-size': data bytes per record
+    let mut base = 0; // '--base': address the input loads at
+    let mut add_format = |next: Format| match &mut format {
+        Some(format) => format.append(next),
+        None => format = Some(next),
+    };
 
-        // Handle bytes in pairs for better readability. Can extend function to include big_endian format
-        for pair in chunk.chunks(2) {
-            output.push(' ');
-            match pair.len() {
-                2 => output.push_str(&format!("{:02x}{:02x}", pair[1], pair[0])), // Reverse byte order for little_endian format
-                1 => output.push_str(&format!("{:02x}", pair[0])), // Handle single bytes
-                _ => unreachable!(),                               // Sanity check
+    for option in options {
+        match option.opt.short {
+            // util-linux canonical, octal, character, decimal and hex displays
+            Some('C') => add_format(Mode::Canonical.format()),
+            Some('b') => add_format(Mode::OctalBytes.format()),
+            Some('c') => add_format(Mode::Chars.format()),
+            Some('d') => add_format(Mode::DecimalWords.format()),
+            Some('o') => add_format(Mode::OctalWords.format()),
+            Some('x') => add_format(Mode::HexWords.format()),
+            Some('g') => {
+                // Bytes per group in the default layout
+                let size = option.value().parse().ok();
+                let grouped = size.and_then(|size| Format::grouped(size).ok());
+                group = Some(grouped.ok_or_else(|| option.invalid("1, 2, 4, 8 or 16"))?);
             }
+            Some('E') => {
+                // Byte order used for every multi-byte value
+                endian = Some(match option.value() {
+                    "little" => Endian::Little,
+                    "big" => Endian::Big,
+                    "native" => Endian::Native,
+                    _ => return Err(option.invalid("little, big or native")),
+                });
+            }
+            Some('e') => {
+                // Format string given on the command line
+                let parsed = Format::parse(option.value())
+                    .map_err(|e| ArgError::InvalidFormat(e.to_string()))?;
+                add_format(parsed);
+            }
+            Some('f') => {
+                // Format strings read from a file, one per line
+                let path = option.value();
+                let text = std::fs::read_to_string(path)
+                    .map_err(|e| ArgError::InvalidFormat(format!("{}: {}", path, e)))?;
+                let parsed = Format::parse_file(&text)
+                    .map_err(|e| ArgError::InvalidFormat(format!("{}: {}", path, e)))?;
+                add_format(parsed);
+            }
+            Some('v') => config.squeeze = false, // Print every line, even repeated ones
+            Some('r') => reverse = true,         // Read a dump and write the bytes it shows
+            Some('L') => color = When::parse(option)?,
+            None => match option.opt.long {
+                // Long-only options
+                Some("interactive") => interactive = true,
+                Some("diff") => diff = true,
+                Some("context") if option.value().is_empty() => context = Some(3),
+                Some("context") => context = Some(lines_value(option)?),
+                Some("find") => {
+                    let pattern = Pattern::parse(option.value())
+                        .map_err(|e| ArgError::InvalidPattern(e.to_string()))?;
+                    find = Some(pattern);
+                }
+                Some("strings") => strings = Some(min_value(option)?),
+                Some("mark-strings") => config.mark_strings = Some(min_value(option)?),
+                Some("entropy") => entropy = Some(block_value(option, 1024)?),
+                Some("entropy-column") => {
+                    config.entropy_column = Some(block_value(option, 256)?);
+                }
+                Some("export") => {
+                    let language: Option<Language> = option.value().parse().ok();
+                    export = Some(language.ok_or_else(|| option.invalid(export::LANGUAGES))?);
+                }
+                Some("name") => {
+                    if !Export::is_name(option.value()) {
+                        return Err(option.invalid("a letter or '_', then letters, digits or '_'"));
+                    }
+                    name = Some(option.value().to_string());
+                }
+                Some("cols") => {
+                    let value = option.value().parse().ok();
+                    cols = Some(
+                        value
+                            .filter(|&cols| cols > 0)
+                            .ok_or_else(|| option.invalid("a number of bytes"))?,
+                    );
+                }
+                Some("no-length") => length = false,
+                Some("const") => is_const = true,
+                Some("static") => is_static = true,
+                Some("json") => style = Some(json::Style::Document),
+                Some("ndjson") => style = Some(json::Style::Lines),
+                Some("words") => {
+                    words = Some(match option.value() {
+                        "1" => 1,
+                        "2" => 2,
+                        "4" => 4,
+                        "8" => 8,
+                        _ => return Err(option.invalid("1, 2, 4 or 8")),
+                    });
+                }
+                Some("html") => html = true,
+                Some("annotate") => {
+                    let annotation: Option<Annotation> = option.value().parse().ok();
+                    annotations
+                        .push(annotation.ok_or_else(|| option.invalid("OFFSET[+LENGTH]:TEXT"))?);
+                }
+                Some("input-encoding") => {
+                    // Text is decoded whatever the action, see 'parse_args'
+                    load = match coding_value(option)? {
+                        Coding::Records(kind) => Some(kind),
+                        Coding::Text(_) => None,
+                    };
+                }
+                Some("output") => output = Some(coding_value(option)?),
+                Some("record-size") => {
+                    let value = option.value().parse().ok();
+                    record_size = Some(
+                        value
+                            .filter(|size| (1..=records::MAX_RECORD_SIZE).contains(size))
+                            .ok_or_else(|| option.invalid("a number of bytes from 1 to 250"))?,
+                    );
+                }
+                Some("base") => base = size_value(option)?,
+                Some("wrap") => {
+                    wrap = option
+                        .value()
+                        .parse()
+                        .map_err(|_| option.invalid("a number of characters"))?;
+                }
+                _ => {}
+            },
+            Some('A') => after = Some(lines_value(option)?),
+            Some('B') => before = Some(lines_value(option)?),
+            Some('n') => config.length = Some(size_value(option)?),
+            Some('s') => config.skip = size_value(option)?,
+            _ => {}
         }
+    }
 
-        output.push('\n'); // Formatting (newline after each 16-byte chunk)
+    if reverse {
+        return Ok(Action::Reverse(Style::Auto)); // Layout detected from the dump
+    }
+    if interactive {
+        return Ok(Action::View(config.skip, color)); // Starts at the skipped offset
+    }
+    if diff {
+        return Ok(Action::Diff(config, context, color));
+    }
+    if let Some(min) = strings {
+        return Ok(Action::Strings(config, min, color));
+    }
+    if let Some(block) = entropy {
+        return Ok(Action::Entropy(config, block, color));
+    }
+    if let Some(language) = export {
+        // Qualifiers the language has no way to write are refused rather than dropped
+        let given = format!("--export={}", language);
+        let refused = if is_const && !language.has_const() {
+            Some(("--const", given))
+        } else if is_static && !language.has_static() {
+            Some(("--static", given))
+        } else if is_static && is_const && language == Language::Rust {
+            Some(("--static", "--const".to_string())) // Rust arrays are one or the other
+        } else {
+            None
+        };
+        if let Some((option, with)) = refused {
+            return Err(ArgError::Conflict(option.to_string(), with));
+        }
+        // Named after the input file like 'xxd -i', or 'data' without one
+        let name = name.unwrap_or_else(|| match sources {
+            [Source::File(path)] => Export::name_for_path(&path.to_string_lossy()),
+            _ => "data".to_string(),
+        });
+        let export = Export {
+            cols: cols.unwrap_or(12),
+            length,
+            is_const,
+            is_static,
+            ..Export::new(language, &name)
+        };
+        return Ok(Action::Export(config, export));
+    }
+    if let Some(style) = style {
+        let settings = Json {
+            width: cols.unwrap_or(16),
+            words,
+            endian: endian.unwrap_or_default(),
+            ..Json::new(style)
+        };
+        return Ok(Action::Json(config, settings));
     }
 
-    Ok(output)
+    if let Some(Coding::Text(encoding)) = output {
+        let encoder = TextEncoder {
+            wrap,
+            ..TextEncoder::new(encoding)
+        };
+        return Ok(Action::Text(config, encoder));
+    }
+    if let Some(Coding::Records(kind)) = output {
+        // S-records are headed by the name of the input file
+        let header = match sources {
+            [Source::File(path)] => path
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().into_owned()),
+            _ => String::new(),
+        };
+        let encoder = Encoder {
+            record_size: record_size.unwrap_or(16),
+            base,
+            header,
+            ..Encoder::new(kind)
+        };
+        return Ok(Action::Encode(config, encoder));
+    }
+
+    if html && format.is_none() && group.is_none() {
+        format = Some(Mode::Canonical.format()); // Offsets, hex and characters side by side
+    }
+    if let Some(format) = format {
+        // Strings reading nothing are checked together, as '"%_ad\n"' is only of use next to others
+        format
+            .check()
+            .map_err(|e| ArgError::InvalidFormat(e.to_string()))?;
+        config.format = format; // Replaces the default layout
+    } else if let Some(grouped) = group {
+        config.format = grouped; // Default layout with a different group size
+    }
+    if let Some(endian) = endian {
+        config.format.set_endian(endian);
+    }
+    if let Some(kind) = load {
+        return Ok(Action::Load(config, kind, color)); // Shown in the layout chosen
+    }
+    if html {
+        // Titled after the inputs
+        let names: Vec<String> = sources.iter().map(Source::to_string).collect();
+        let title = match names.as_slice() {
+            [] => "-".to_string(),
+            _ => names.join(" "),
+        };
+        let html = Html {
+            annotations,
+            ..Html::new(&title)
+        };
+        return Ok(Action::Html(config, html)); // Shown in the layout chosen
+    }
+    if let Some(pattern) = find {
+        let search = Search {
+            pattern,
+            before: before.or(context).unwrap_or(0),
+            after: after.or(context).unwrap_or(0),
+        };
+        return Ok(Action::Find(config, search, color)); // Shown in the layout chosen
+    }
+    Ok(Action::Dump(config, color))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Cursor;
+// Function to build the xxd action from its options
+fn xxd_action(options: &[Parsed], sources: &[Source]) -> Result<Action, ArgError> {
+    let mut config = Config {
+        squeeze: false, // xxd only skips lines of zeros, with '-a'
+        ..Config::default()
+    };
+    let mut cols = None; // Bytes per line, defaulting per layout
+    let mut group = 2; // Bytes per group in the hex column
+    let mut plain = false; // '-p': continuous hex
+    let mut include = false; // '-i': C include output
+    let mut upper = false; // '-u': uppercase hex digits
+    let mut reverse = false; // '-r': convert a dump back to binary
+    let mut color = When::Auto; // '-R': when to color the output
 
-    #[test]
-    fn test_hexdump_empty() {
-        // Test case for an empty input file
-        let input = Cursor::new(vec![]);
-        assert_eq!(hexdump(input).unwrap(), ""); // Expect empty string
+    for option in options {
+        match (option.opt.short, option.opt.long) {
+            (Some('a'), _) => config.autoskip = !config.autoskip, // Skip runs of nul-lines
+            (Some('p'), _) | (_, Some("plain" | "ps")) => plain = true,
+            (Some('i'), _) => include = true,
+            (Some('u'), _) => upper = true,
+            (Some('r'), _) => reverse = true,
+            (Some('R'), _) => color = When::parse(option)?,
+            (Some('c'), _) => {
+                let value = option.value().parse().ok();
+                cols = Some(
+                    value
+                        .filter(|cols| (1..=256).contains(cols))
+                        .ok_or_else(|| option.invalid("1 to 256"))?,
+                );
+            }
+            (Some('g'), _) => {
+                group = option
+                    .value()
+                    .parse()
+                    .map_err(|_| option.invalid("a number of bytes"))?
+            }
+            (Some('s'), _) => config.skip = size_value(option)?,
+            (Some('l'), _) => config.length = Some(size_value(option)?),
+            _ => {}
+        }
     }
 
-    #[test]
-    fn test_hexdump_single_line() {
-        // Test case for a small file that fits on a single line of output
-        let input = Cursor::new(vec![0x00, 0x01, 0x02, 0x03]);
-        assert_eq!(hexdump(input).unwrap(), "00000000 0100 0302\n"); // Expected hex format
+    if reverse {
+        // '-r -p' reads plain hex, otherwise the layout is detected
+        let style = if plain { Style::Plain } else { Style::Auto };
+        return Ok(Action::Reverse(style));
     }
 
-    #[test]
-    fn test_hexdump_multiple_lines() {
-        // Test case for a file with multiple lines of hexdump
-        let input = Cursor::new((0..32).collect::<Vec<u8>>());
-        let expected = "\
-            00000000 0100 0302 0504 0706 0908 0b0a 0d0c 0f0e\n\
-            00000010 1110 1312 1514 1716 1918 1b1a 1d1c 1f1e\n";
-        assert_eq!(hexdump(input).unwrap(), expected); // Expected hex format
+    if include {
+        // Named after the input file, like xxd; stdin gets no declarations
+        let mut settings = match sources {
+            [Source::File(path)] => Include::for_path(&path.to_string_lossy()),
+            _ => Include::default(),
+        };
+        settings.cols = cols.unwrap_or(settings.cols);
+        settings.upper = upper;
+        return Ok(Action::Include(config, settings));
     }
 
-    #[test]
-    fn test_hexdump_partial_line() {
-        // Test case for a file with a partial final line
-        let input = Cursor::new((0..20).collect::<Vec<u8>>());
-        let expected = "\
-            00000000 0100 0302 0504 0706 0908 0b0a 0d0c 0f0e\n\
-            00000010 1110 1312\n";
-        assert_eq!(hexdump(input).unwrap(), expected); // Expected hex format
+    config.autoskip &= !plain; // Like xxd, plain hex is never skipped
+    config.format = if plain {
+        Format::xxd_plain(cols.unwrap_or(30), upper)
+    } else {
+        Format::xxd(cols.unwrap_or(16), group, upper)
+    }
+    .map_err(|e| ArgError::InvalidFormat(e.to_string()))?;
+    Ok(Action::Dump(config, color))
+}
+
+// Function to build the od action from its options
+fn od_action(options: &[Parsed]) -> Result<Action, ArgError> {
+    let mut config = Config::default();
+    let mut address = od::Address::default(); // '-A': offset radix
+    let mut types = Vec::new(); // '-t' and shorthand output types, in the order given
+    let mut width = None; // '-w': bytes per line
+    let mut color = When::Never; // '--color': plain by default, like GNU od
+
+    for option in options {
+        match option.opt.short {
+            Some('A') => {
+                address = od::Address::parse(option.value())
+                    .ok_or_else(|| option.invalid("d, o, x or n"))?
+            }
+            Some('t') => types.extend(
+                od::Type::parse_list(option.value())
+                    .map_err(|e| ArgError::InvalidFormat(e.to_string()))?,
+            ),
+            Some('j') => config.skip = size_value(option)?,
+            Some('N') => config.length = Some(size_value(option)?),
+            Some('v') => config.squeeze = false, // Print repeated lines too
+            Some('w') => {
+                // od's width is 32 when none is given
+                let value = option
+                    .value
+                    .as_deref()
+                    .map_or(Some(32), |value| value.parse().ok());
+                width = Some(value.ok_or_else(|| option.invalid("a number of bytes"))?);
+            }
+            Some(short) => {
+                // Traditional shorthands for common output types
+                let spec = match short {
+                    'a' => "a",
+                    'b' => "o1",
+                    'c' => "c",
+                    'd' => "u2",
+                    'f' => "fF",
+                    'i' => "dI",
+                    'l' => "dL",
+                    'o' => "o2",
+                    's' => "d2",
+                    _ => "x2",
+                };
+                types.extend(od::Type::parse_list(spec).expect("shorthand types are valid"));
+            }
+            None => color = When::parse(option)?, // '--color', the only long-only option
+        }
+    }
+
+    config.format =
+        Format::od(address, &types, width).map_err(|e| ArgError::InvalidFormat(e.to_string()))?;
+    Ok(Action::Dump(config, color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper to build the expected source list for a single file
+    fn file(name: &str) -> Vec<Source> {
+        vec![Source::from_arg(name)]
+    }
+
+    // Helper to build the error for a rejected option value
+    fn invalid(option: &str, value: &str, expected: &'static str) -> ArgError {
+        ArgError::InvalidValue {
+            option: option.to_string(),
+            value: value.to_string(),
+            expected,
+        }
     }
 
     #[test]
     fn test_parse_args_file_only() {
         // Test case for argument parsing with only a file
         let args = vec!["program".to_string(), "file.txt".to_string()];
-        assert_eq!(parse_args(&args), Ok(("file.txt", None)));
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                file("file.txt"),
+                Action::Dump(Config::default(), When::Auto)
+            ))
+        );
     }
 
     #[test]
@@ -142,18 +1550,430 @@ mod tests {
             "100".to_string(),
             "file.txt".to_string(),
         ];
-        assert_eq!(parse_args(&args), Ok(("file.txt", Some(100))));
+        let config = Config {
+            length: Some(100),
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("file.txt"), Action::Dump(config, When::Auto)))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_canonical() {
+        // Test case for selecting the canonical display with '-C'
+        let args = vec![
+            "program".to_string(),
+            "-C".to_string(),
+            "-n".to_string(),
+            "16".to_string(),
+            "file.txt".to_string(),
+        ];
+        let config = Config {
+            length: Some(16),
+            format: Mode::Canonical.format(),
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("file.txt"), Action::Dump(config, When::Auto)))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_formats() {
+        // Test case for formats from '-C' and '-e' combined in order
+        let args = vec![
+            "program".to_string(),
+            "-C".to_string(),
+            "-e".to_string(),
+            r#""%_ad\n""#.to_string(),
+            "file.txt".to_string(),
+        ];
+        let mut format = Mode::Canonical.format();
+        format.append(Format::parse(r#""%_ad\n""#).unwrap());
+        let config = Config {
+            format,
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("file.txt"), Action::Dump(config, When::Auto)))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_grouping() {
+        // Test case for big-endian 4-byte groups
+        let args = vec![
+            "program".to_string(),
+            "-g".to_string(),
+            "4".to_string(),
+            "-E".to_string(),
+            "big".to_string(),
+            "file.txt".to_string(),
+        ];
+        let mut format = Format::grouped(4).unwrap();
+        format.set_endian(Endian::Big);
+        let config = Config {
+            format,
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("file.txt"), Action::Dump(config, When::Auto)))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_invalid_grouping() {
+        // Test case for unsupported group sizes and byte orders
+        let args = vec!["program".to_string(), "-g".to_string(), "3".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Err(invalid("-g", "3", "1, 2, 4, 8 or 16"))
+        );
+        let args = vec![
+            "program".to_string(),
+            "-E".to_string(),
+            "middle".to_string(),
+        ];
+        assert_eq!(
+            parse_args(&args),
+            Err(invalid("-E", "middle", "little, big or native"))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_multiple_modes() {
+        // Test case for several displays shown for every line
+        let args = vec!["program".to_string(), "-x".to_string(), "-c".to_string()];
+        let mut format = Mode::HexWords.format();
+        format.append(Mode::Chars.format());
+        let config = Config {
+            format,
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((vec![Source::Stdin], Action::Dump(config, When::Auto)))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_reverse() {
+        // Test case for converting a dump back to binary
+        let args = vec![
+            "program".to_string(),
+            "-r".to_string(),
+            "dump.txt".to_string(),
+        ];
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("dump.txt"), Action::Reverse(Style::Auto)))
+        );
+    }
+
+    #[test]
+    fn test_parse_xxd_args() {
+        // Test case for the xxd personality, selected by name or with '--xxd'
+        let args = vec![
+            "/usr/bin/xxd".to_string(),
+            "-c".to_string(),
+            "8".to_string(),
+            "-g".to_string(),
+            "1".to_string(),
+            "-u".to_string(),
+            "-s".to_string(),
+            "0x10".to_string(),
+            "file.txt".to_string(),
+        ];
+        let config = Config {
+            format: Format::xxd(8, 1, true).unwrap(),
+            skip: 0x10,
+            squeeze: false,
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("file.txt"), Action::Dump(config, When::Auto)))
+        );
+
+        let args = vec![
+            "program".to_string(),
+            "--xxd".to_string(),
+            "-p".to_string(),
+            "-a".to_string(),
+        ];
+        let config = Config {
+            format: Format::xxd_plain(30, false).unwrap(),
+            squeeze: false,
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((vec![Source::Stdin], Action::Dump(config, When::Auto)))
+        );
+
+        let args = vec!["program".to_string(), "--xxd".to_string(), "-a".to_string()];
+        let config = Config {
+            format: Format::xxd(16, 2, false).unwrap(),
+            squeeze: false,
+            autoskip: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((vec![Source::Stdin], Action::Dump(config, When::Auto)))
+        );
+    }
+
+    #[test]
+    fn test_parse_xxd_include_and_reverse() {
+        // Test case for 'xxd -i' naming and 'xxd -r -p'
+        let args = vec![
+            "xxd".to_string(),
+            "-i".to_string(),
+            "-l".to_string(),
+            "4".to_string(),
+            "x.bin".to_string(),
+        ];
+        let config = Config {
+            length: Some(4),
+            squeeze: false,
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                file("x.bin"),
+                Action::Include(config, Include::for_path("x.bin"))
+            ))
+        );
+
+        let args = vec!["xxd".to_string(), "-r".to_string(), "-p".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Ok((vec![Source::Stdin], Action::Reverse(Style::Plain)))
+        );
+    }
+
+    #[test]
+    fn test_parse_od_args() {
+        // Test case for the od personality, with attached and separate option values
+        let args: Vec<String> = [
+            "/bin/od", "-Ax", "-t", "x1z", "-c", "-j", "16", "-N16", "-w8", "-v", "file.txt",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let types = od::Type::parse_list("x1zc").unwrap();
+        let config = Config {
+            format: Format::od(od::Address::Hex, &types, Some(8)).unwrap(),
+            skip: 16,
+            length: Some(16),
+            squeeze: false,
+            autoskip: false,
+            theme: None,
+            mark_strings: None,
+            entropy_column: None,
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("file.txt"), Action::Dump(config, When::Never)))
+        );
+
+        // '--od' selects the personality; without types od shows octal words
+        let args = vec!["program".to_string(), "--od".to_string()];
+        let config = Config {
+            format: Format::od(od::Address::Octal, &[], None).unwrap(),
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((vec![Source::Stdin], Action::Dump(config, When::Never)))
+        );
+    }
+
+    #[test]
+    fn test_parse_od_args_invalid() {
+        // Test case for bad types, radixes and missing values
+        let parse = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            parse_args(&args)
+        };
+        assert!(matches!(
+            parse(&["od", "-t", "d3"]),
+            Err(ArgError::InvalidFormat(_))
+        ));
+        assert_eq!(
+            parse(&["od", "-Aq"]),
+            Err(invalid("-A", "q", "d, o, x or n"))
+        );
+        assert!(matches!(
+            parse(&["od", "-w3", "-tx2"]),
+            Err(ArgError::InvalidFormat(_))
+        ));
+        assert_eq!(
+            parse(&["od", "-t"]),
+            Err(ArgError::MissingValue("-t".into()))
+        );
+        assert_eq!(
+            parse(&["od", "-q"]),
+            Err(ArgError::UnknownOption("-q".into()))
+        );
+        assert_eq!(parse(&["od", "-j", "x"]), Err(invalid("-j", "x", SIZE)));
+    }
+
+    #[test]
+    fn test_parse_args_invalid_format() {
+        // Test case for a format string with a bad conversion
+        let args = vec![
+            "program".to_string(),
+            "-e".to_string(),
+            r#""%q""#.to_string(),
+        ];
+        assert_eq!(
+            parse_args(&args),
+            Err(ArgError::InvalidFormat(
+                "bad conversion character %q".to_string()
+            ))
+        );
+
+        // Formats reading no input, and counts too large to hold a block of, are usage errors
+        let parse = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            parse_args(&args)
+        };
+        assert_eq!(
+            parse(&["program", "-e", r#""%_ad\n""#]),
+            Err(ArgError::InvalidFormat(
+                "format does not consume any input".to_string()
+            ))
+        );
+        assert!(parse(&["program", "-e", r#""%_ad\n""#, "-e", r#"16/1 "%02x" "\n""#]).is_ok());
+        assert!(matches!(
+            parse(&["program", "-e", r#"99999999999999/1 "%x""#]),
+            Err(ArgError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_args_no_squeeze() {
+        // Test case for disabling duplicate-line squeezing with '-v'
+        let args = vec![
+            "program".to_string(),
+            "-v".to_string(),
+            "file.txt".to_string(),
+        ];
+        let config = Config {
+            squeeze: false,
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("file.txt"), Action::Dump(config, When::Auto)))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_stdin() {
+        // Test case for reading stdin when no file or '-' is given
+        let args = vec!["program".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                vec![Source::Stdin],
+                Action::Dump(Config::default(), When::Auto)
+            ))
+        );
+        let args = vec!["program".to_string(), "-".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                vec![Source::Stdin],
+                Action::Dump(Config::default(), When::Auto)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_multiple_files() {
+        // Test case for several files dumped as one stream
+        let args = vec![
+            "program".to_string(),
+            "a.bin".to_string(),
+            "-".to_string(),
+            "b.bin".to_string(),
+        ];
+        let sources = vec![
+            Source::from_arg("a.bin"),
+            Source::Stdin,
+            Source::from_arg("b.bin"),
+        ];
+        assert_eq!(
+            parse_args(&args),
+            Ok((sources, Action::Dump(Config::default(), When::Auto)))
+        );
     }
 
     #[test]
     fn test_parse_args_invalid_usage() {
         // Test case for invalid usage of arguments
+        let args = vec!["program".to_string(), "-n".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Err(ArgError::MissingValue("-n".to_string()))
+        ); // Length flag without a value
         let args = vec![
             "program".to_string(),
+            "file.txt".to_string(),
+            "-q".to_string(),
+        ];
+        assert_eq!(
+            parse_args(&args),
+            Err(ArgError::UnknownOption("-q".to_string()))
+        ); // Unknown option
+        let args = vec!["program".to_string(), "--reverse=yes".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Err(ArgError::UnexpectedValue("--reverse".to_string()))
+        ); // Flag given a value
+    }
+
+    #[test]
+    fn test_parse_args_skip() {
+        // Test case for a byte window selected with '-s' and '-n'
+        let args = vec![
+            "program".to_string(),
+            "-s".to_string(),
+            "0x7f000".to_string(),
             "-n".to_string(),
+            "0x100".to_string(),
+            "file.txt".to_string(),
+        ];
+        let config = Config {
+            skip: 0x7f000,
+            length: Some(0x100),
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("file.txt"), Action::Dump(config, When::Auto)))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_invalid_offset() {
+        // Test case for an offset with an unknown suffix
+        let args = vec![
+            "program".to_string(),
+            "-s".to_string(),
+            "12q".to_string(),
             "file.txt".to_string(),
         ];
-        assert_eq!(parse_args(&args), Err(ArgError::InvalidUsage)); // Expect usage error
+        assert_eq!(parse_args(&args), Err(invalid("-s", "12q", SIZE))); // Expect offset error
     }
 
     #[test]
@@ -165,6 +1985,651 @@ mod tests {
             "not_a_number".to_string(),
             "file.txt".to_string(),
         ];
-        assert_eq!(parse_args(&args), Err(ArgError::InvalidLength)); // Expect length error
+        assert_eq!(parse_args(&args), Err(invalid("-n", "not_a_number", SIZE)));
+        // Expect length error
+    }
+
+    #[test]
+    fn test_parse_args_option_forms() {
+        // Test case for options after the file, attached values, long options and clusters
+        let forms: [&[&str]; 5] = [
+            &["program", "file.txt", "-n", "10", "-v", "-C"],
+            &["program", "-n10", "file.txt", "-vC"],
+            &[
+                "program",
+                "--length=10",
+                "--no-squeezing",
+                "--canonical",
+                "file.txt",
+            ],
+            &["program", "--length", "10", "-Cv", "--", "file.txt"],
+            &["program", "--len=10", "--no-s", "--can", "file.txt"],
+        ];
+        let config = Config {
+            length: Some(10),
+            squeeze: false,
+            format: Mode::Canonical.format(),
+            ..Config::default()
+        };
+        for args in forms {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            assert_eq!(
+                parse_args(&args),
+                Ok((file("file.txt"), Action::Dump(config.clone(), When::Auto))),
+                "{:?}",
+                args
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_args_help_and_version() {
+        // Test case for '--help' and '--version', which win over other arguments
+        let args = vec![
+            "program".to_string(),
+            "-n".to_string(),
+            "x".to_string(),
+            "--help".to_string(),
+        ];
+        assert_eq!(parse_args(&args), Ok((Vec::new(), Action::Help)));
+        let args = vec!["program".to_string(), "-V".to_string()];
+        assert_eq!(parse_args(&args), Ok((Vec::new(), Action::Version)));
+        let args = vec!["xxd".to_string(), "-h".to_string()];
+        assert_eq!(parse_args(&args), Ok((Vec::new(), Action::Help)));
+        let args = vec!["od".to_string(), "--version".to_string()];
+        assert_eq!(parse_args(&args), Ok((Vec::new(), Action::Version)));
+
+        // Every personality lists its options
+        let help = Personality::Hexdump.help("hexdump");
+        assert!(help.starts_with("Usage: hexdump [OPTION]... [FILE]...\n"));
+        assert!(help.contains("  -n, --length=LEN"));
+        assert!(Personality::Xxd.help("xxd").contains("  -c, --cols=COLS"));
+        assert!(Personality::Od.help("od").contains("  -w, --width[=BYTES]"));
+    }
+
+    #[test]
+    fn test_usage_status() {
+        // Usage errors in a search or comparison exit with 2, as 1 means no match or a difference
+        let cases: [(&[&str], i32); 7] = [
+            (&["hexdump", "--find=zz", "f"], 2),
+            (&["hexdump", "-C", "--find"], 2),
+            (&["hexdump", "--find", "41", "--bogus"], 2),
+            (&["hexdump", "--xxd", "-q", "--find=41"], 2),
+            (&["hexdump", "--diff", "-n", "x", "a", "b"], 2),
+            (&["hexdump", "-n", "x"], 1),
+            (&["hexdump", "-n", "x", "--", "--find=41"], 1),
+        ];
+        for (args, status) in cases {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            assert!(parse_args(&args).is_err(), "{:?}", args);
+            assert_eq!(usage_status(&args), status, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_parse_args_conflicting_modes() {
+        // Options doing different things with the input can't be combined
+        let cases: [(&[&str], &str, &str); 6] = [
+            (&["--json", "--html", "--strings"], "--html", "--json"),
+            (&["--diff", "--json"], "--json", "--diff"),
+            (&["--find=abcd", "--export=c"], "--export=c", "--find=abcd"),
+            (&["-r", "--interactive"], "--interactive", "-r"),
+            (
+                &["--input-encoding=ihex", "--output=srec"],
+                "--output",
+                "--input-encoding",
+            ),
+            (&["--json", "--ndjson"], "--ndjson", "--json"),
+        ];
+        for (options, option, other) in cases {
+            let mut args = vec!["program".to_string()];
+            args.extend(options.iter().map(|arg| arg.to_string()));
+            let option = option.split('=').next().unwrap().to_string();
+            let other = other.split('=').next().unwrap().to_string();
+            assert_eq!(parse_args(&args), Err(ArgError::Conflict(option, other)));
+        }
+
+        // Repeating a mode, decoding text input and the options of a mode are fine
+        let cases: [&[&str]; 3] = [
+            &["--output=ihex", "--output=srec"],
+            &["--input-encoding=base64", "--find=abcd", "--context=2"],
+            &["--diff", "--context", "-s", "4"],
+        ];
+        for options in cases {
+            let mut args = vec!["program".to_string()];
+            args.extend(options.iter().map(|arg| arg.to_string()));
+            assert!(parse_args(&args).is_ok(), "{:?}", options);
+        }
+
+        // Layout options are refused by modes that write no dump, and kept by those that do
+        let cases: [(&[&str], &str, &str); 4] = [
+            (&["--strings", "-x"], "-x", "--strings"),
+            (&["-C", "--json"], "-C", "--json"),
+            (&["--diff", "-v"], "-v", "--diff"),
+            (&["-e", "\"%x\"", "--export=c"], "-e", "--export"),
+        ];
+        for (options, option, mode) in cases {
+            let mut args = vec!["program".to_string()];
+            args.extend(options.iter().map(|arg| arg.to_string()));
+            assert_eq!(
+                parse_args(&args),
+                Err(ArgError::Conflict(option.to_string(), mode.to_string()))
+            );
+        }
+        for options in [&["--find=41", "-C"][..], &["--html", "-x", "-v"]] {
+            let mut args = vec!["program".to_string()];
+            args.extend(options.iter().map(|arg| arg.to_string()));
+            assert!(parse_args(&args).is_ok(), "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn test_help_layout() {
+        // Every help line fits in 80 columns, and long option names keep apart from their text
+        for personality in [Personality::Hexdump, Personality::Xxd, Personality::Od] {
+            let help = personality.help(personality.name());
+            for line in help.lines() {
+                assert!(line.len() <= 80, "{}", line);
+            }
+        }
+        let help = Personality::Hexdump.help("hexdump");
+        assert!(help.contains(
+            "      --entropy-column[=BLOCK]\n                              show the entropy"
+        ));
+    }
+
+    #[test]
+    fn test_parse_args_color() {
+        // Test case for '--color' in its forms, and xxd's '-R'
+        let cases: [(&[&str], When); 5] = [
+            (&["program", "--color"], When::Always),
+            (&["program", "-L"], When::Always),
+            (&["program", "--color=never"], When::Never),
+            (&["xxd", "-R", "always"], When::Always),
+            (&["od", "--color=auto"], When::Auto),
+        ];
+        for (args, when) in cases {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            match parse_args(&args) {
+                Ok((_, Action::Dump(_, parsed))) => assert_eq!(parsed, when, "{:?}", args),
+                other => panic!("{:?}: {:?}", args, other),
+            }
+        }
+
+        // Other values are rejected
+        let args = vec!["program".to_string(), "-Lsometimes".to_string()];
+        assert_eq!(parse_args(&args), Err(invalid("-L", "sometimes", WHEN)));
+    }
+
+    #[test]
+    fn test_parse_args_interactive() {
+        // Test case for '--interactive', which starts at the skipped offset
+        let args: Vec<String> = [
+            "program",
+            "--interactive",
+            "-s",
+            "0x40",
+            "--color=never",
+            "f",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("f"), Action::View(0x40, When::Never)))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_find() {
+        // Test case for '--find' in the chosen layout, with '-A' winning over '--context'
+        let args: Vec<String> = [
+            "program",
+            "-C",
+            "--find=7f 45",
+            "--context=2",
+            "-A",
+            "5",
+            "f",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let config = Config {
+            format: Mode::Canonical.format(),
+            ..Config::default()
+        };
+        let search = Search {
+            pattern: Pattern::parse("7f45").unwrap(),
+            before: 2,
+            after: 5,
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("f"), Action::Find(config, search, When::Auto)))
+        );
+
+        // Patterns that can't be parsed are reported
+        let args: Vec<String> = ["program", "--find", "re:(x"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert!(matches!(
+            parse_args(&args),
+            Err(ArgError::InvalidPattern(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_args_strings() {
+        // Test case for '--strings', with a default minimum, and '--mark-strings' on a dump
+        let args: Vec<String> = ["program", "--strings", "-s", "16", "f"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let config = Config {
+            skip: 16,
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("f"), Action::Strings(config, 4, When::Auto)))
+        );
+        let args: Vec<String> = ["program", "-C", "--mark-strings=8", "f"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let config = Config {
+            format: Mode::Canonical.format(),
+            mark_strings: Some(8),
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("f"), Action::Dump(config, When::Auto)))
+        );
+        let args = vec!["program".to_string(), "--strings=0".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Err(invalid("--strings", "0", "a number of characters"))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_entropy() {
+        // Test case for '--entropy' with a block size, and '--entropy-column' defaulting to 256
+        let args: Vec<String> = ["program", "--entropy=4k", "f"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                file("f"),
+                Action::Entropy(Config::default(), 4096, When::Auto)
+            ))
+        );
+        let args: Vec<String> = ["program", "-C", "--entropy-column", "f"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let config = Config {
+            format: Mode::Canonical.format(),
+            entropy_column: Some(256),
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("f"), Action::Dump(config, When::Auto)))
+        );
+        let args = vec!["program".to_string(), "--entropy=0".to_string()];
+        assert_eq!(parse_args(&args), Err(invalid("--entropy", "0", SIZE)));
+    }
+
+    #[test]
+    fn test_parse_args_export() {
+        // Test case for '--export', named after the file unless '--name' is given
+        let args: Vec<String> = ["program", "--export=rust", "-n", "16", "--const", "fw.bin"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let config = Config {
+            length: Some(16),
+            ..Config::default()
+        };
+        let export = Export {
+            is_const: true,
+            ..Export::new(Language::Rust, "fw_bin")
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("fw.bin"), Action::Export(config, export)))
+        );
+        let args: Vec<String> = [
+            "program",
+            "--export",
+            "go",
+            "--name=vector",
+            "--cols=8",
+            "--no-length",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let export = Export {
+            cols: 8,
+            length: false,
+            ..Export::new(Language::Go, "vector")
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                vec![Source::Stdin],
+                Action::Export(Config::default(), export)
+            ))
+        );
+        // Qualifiers the language can't write and names that aren't identifiers are refused
+        let refused = [
+            (&["--export=go", "--const"][..], "--const", "--export=go"),
+            (
+                &["--export=python", "--static"],
+                "--static",
+                "--export=python",
+            ),
+            (&["--export=zig", "--static"], "--static", "--export=zig"),
+            (
+                &["--export=rust", "--static", "--const"],
+                "--static",
+                "--const",
+            ),
+        ];
+        for (options, option, with) in refused {
+            let args: Vec<String> = ["program"]
+                .iter()
+                .chain(options)
+                .map(|arg| arg.to_string())
+                .collect();
+            assert_eq!(
+                parse_args(&args),
+                Err(ArgError::Conflict(option.to_string(), with.to_string()))
+            );
+        }
+        let args: Vec<String> = ["program", "--export=rust", "--static"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert!(parse_args(&args).is_ok());
+        let args: Vec<String> = ["program", "--export=c", "--name=1bad"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert_eq!(
+            parse_args(&args),
+            Err(invalid(
+                "--name",
+                "1bad",
+                "a letter or '_', then letters, digits or '_'"
+            ))
+        );
+        let args = vec!["program".to_string(), "--export=cobol".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Err(invalid("--export", "cobol", export::LANGUAGES))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_json() {
+        // Test case for '--ndjson' with words in the chosen byte order
+        let args: Vec<String> = ["program", "--ndjson", "--words=4", "-E", "big", "--cols=8"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let settings = Json {
+            width: 8,
+            words: Some(4),
+            endian: Endian::Big,
+            ..Json::new(json::Style::Lines)
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                vec![Source::Stdin],
+                Action::Json(Config::default(), settings)
+            ))
+        );
+        let args = vec!["program".to_string(), "--json".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                vec![Source::Stdin],
+                Action::Json(Config::default(), Json::new(json::Style::Document))
+            ))
+        );
+        let args = vec!["program".to_string(), "--words=3".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Err(invalid("--words", "3", "1, 2, 4 or 8"))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_html() {
+        // Test case for '--html', canonical unless a layout is given, titled after the files
+        let args: Vec<String> = ["program", "--html", "--annotate=0x10+4:magic", "a", "b"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let config = Config {
+            format: Mode::Canonical.format(),
+            ..Config::default()
+        };
+        let html = Html {
+            annotations: vec!["16+4:magic".parse().unwrap()],
+            ..Html::new("a b")
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                vec![Source::File("a".into()), Source::File("b".into())],
+                Action::Html(config, html)
+            ))
+        );
+        let args: Vec<String> = ["program", "--html", "-x"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let config = Config {
+            format: Mode::HexWords.format(),
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((vec![Source::Stdin], Action::Html(config, Html::new("-"))))
+        );
+        let args = vec!["program".to_string(), "--annotate=16".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Err(invalid("--annotate", "16", "OFFSET[+LENGTH]:TEXT"))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_records() {
+        // Test case for '--output' with a record size and base, and '--input-encoding'
+        let args: Vec<String> = [
+            "program",
+            "--output=srec",
+            "--base=0x8000",
+            "--record-size=32",
+            "fw.bin",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let encoder = Encoder {
+            record_size: 32,
+            base: 0x8000,
+            header: "fw.bin".to_string(),
+            ..Encoder::new(Kind::SRecord)
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                vec![Source::File("fw.bin".into())],
+                Action::Encode(Config::default(), encoder)
+            ))
+        );
+        let args: Vec<String> = ["program", "--input-encoding=ihex", "-C"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let config = Config {
+            format: Mode::Canonical.format(),
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                vec![Source::Stdin],
+                Action::Load(config, Kind::IntelHex, When::Auto)
+            ))
+        );
+        let args = vec!["program".to_string(), "--output=elf".to_string()];
+        assert_eq!(parse_args(&args), Err(invalid("--output", "elf", CODINGS)));
+        let args = vec!["program".to_string(), "--record-size=251".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Err(invalid(
+                "--record-size",
+                "251",
+                "a number of bytes from 1 to 250"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_encodings() {
+        // Test case for '--output' as text, and '--input-encoding' decoding for any action
+        let args: Vec<String> = ["program", "--output=base64", "--wrap=0", "-s", "4"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let config = Config {
+            skip: 4,
+            ..Config::default()
+        };
+        let encoder = TextEncoder {
+            wrap: 0,
+            ..TextEncoder::new(Encoding::Base64)
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((vec![Source::Stdin], Action::Text(config, encoder)))
+        );
+        let args: Vec<String> = ["program", "--input-encoding=hex", "--strings"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let strings = Action::Strings(Config::default(), 4, When::Auto);
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                vec![Source::Stdin],
+                Action::Decoded(Encoding::Hex, Box::new(strings))
+            ))
+        );
+        let args: Vec<String> = ["program", "--input-encoding=z85", "--output=ihex"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let encode = Action::Encode(Config::default(), Encoder::new(Kind::IntelHex));
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                vec![Source::Stdin],
+                Action::Decoded(Encoding::Z85, Box::new(encode))
+            ))
+        );
+        let args = vec!["program".to_string(), "--input-encoding=raw".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                vec![Source::Stdin],
+                Action::Dump(Config::default(), When::Auto)
+            ))
+        );
+        let args = vec!["program".to_string(), "--wrap=-1".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Err(invalid("--wrap", "-1", "a number of characters"))
+        );
+    }
+
+    #[test]
+    fn test_parse_args_diff() {
+        // Test case for '--diff', with '--context' defaulting to 3 lines
+        let args: Vec<String> = ["program", "--diff", "--context", "-n", "64", "a", "b"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let config = Config {
+            length: Some(64),
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((
+                vec![Source::from_arg("a"), Source::from_arg("b")],
+                Action::Diff(config, Some(3), When::Auto)
+            ))
+        );
+        let args: Vec<String> = ["program", "--diff", "--context=0", "a", "b"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert!(matches!(
+            parse_args(&args),
+            Ok((_, Action::Diff(_, Some(0), When::Auto)))
+        ));
+        let args = vec!["program".to_string(), "--context=some".to_string()];
+        assert_eq!(
+            parse_args(&args),
+            Err(invalid("--context", "some", "a number of lines"))
+        );
+    }
+
+    #[test]
+    fn test_parse_xxd_single_dash_long_options() {
+        // Test case for xxd's '-cols 8', '-ps' and attached values like '-g1'
+        let args: Vec<String> = ["xxd", "-cols", "8", "-g1", "-seek", "4", "f"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let config = Config {
+            format: Format::xxd(8, 1, false).unwrap(),
+            skip: 4,
+            squeeze: false,
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((file("f"), Action::Dump(config, When::Auto)))
+        );
+
+        let args = vec!["xxd".to_string(), "-ps".to_string()];
+        let config = Config {
+            format: Format::xxd_plain(30, false).unwrap(),
+            squeeze: false,
+            ..Config::default()
+        };
+        assert_eq!(
+            parse_args(&args),
+            Ok((vec![Source::Stdin], Action::Dump(config, When::Auto)))
+        );
+
+        let args = vec!["xxd".to_string(), "-c".to_string(), "0".to_string()];
+        assert_eq!(parse_args(&args), Err(invalid("-c", "0", "1 to 256")));
     }
 }
@@ -0,0 +1,211 @@
+//! `od` output types and address radixes.
+//!
+//! The `od` layouts are plain formats (see [`Format::od`]); this module
+//! parses the `-A` radix and the `-t` type strings that describe them.
+//!
+//! [`Format::od`]: crate::Format::od
+
+use crate::format::ParseError; // Errors share the format error type
+
+/// How offsets are printed (`-A`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Address {
+    /// Decimal, at least 7 digits.
+    Decimal,
+    /// Octal, at least 7 digits (the default).
+    #[default]
+    Octal,
+    /// Hexadecimal, at least 6 digits.
+    Hex,
+    /// No offsets at all.
+    None,
+}
+
+impl Address {
+    /// Parses an `-A` argument: `d`, `o`, `x` or `n`.
+    pub fn parse(text: &str) -> Option<Address> {
+        match text {
+            "d" => Some(Address::Decimal),
+            "o" => Some(Address::Octal),
+            "x" => Some(Address::Hex),
+            "n" => Some(Address::None),
+            _ => None,
+        }
+    }
+
+    // Minimum number of digits in a printed offset
+    pub(crate) fn width(self) -> usize {
+        match self {
+            Address::Decimal | Address::Octal => 7,
+            Address::Hex => 6,
+            Address::None => 0,
+        }
+    }
+}
+
+/// What an output type shows each value as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// `a`: named characters, ignoring the high bit.
+    Named,
+    /// `c`: printable characters, C escapes or octal.
+    Char,
+    /// `d`: signed decimal.
+    Signed,
+    /// `f`: floating point.
+    Float,
+    /// `o`: octal.
+    Octal,
+    /// `u`: unsigned decimal.
+    Unsigned,
+    /// `x`: hexadecimal.
+    Hex,
+}
+
+/// One output type, as in `-t x1z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Type {
+    pub kind: Kind,
+    /// Bytes per value.
+    pub size: usize,
+    /// Append the printable characters of each line between `>` and `<`.
+    pub trailer: bool,
+}
+
+impl Type {
+    /// The default `od` output: two-byte octal words.
+    pub const DEFAULT: Type = Type {
+        kind: Kind::Octal,
+        size: 2,
+        trailer: false,
+    };
+
+    /// Parses a `-t` argument, which may hold several types (`x1z`, `d2u4`).
+    ///
+    /// ```
+    /// use hexdump::od::{Kind, Type};
+    /// let types = Type::parse_list("x1zfD").unwrap();
+    /// assert_eq!(types[0], Type { kind: Kind::Hex, size: 1, trailer: true });
+    /// assert_eq!(types[1], Type { kind: Kind::Float, size: 8, trailer: false });
+    /// assert!(Type::parse_list("d3").is_err());
+    /// ```
+    pub fn parse_list(text: &str) -> Result<Vec<Type>, ParseError> {
+        let mut types = Vec::new();
+        let mut rest = text;
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+            let kind = match c {
+                'a' => Kind::Named,
+                'c' => Kind::Char,
+                'd' => Kind::Signed,
+                'f' => Kind::Float,
+                'o' => Kind::Octal,
+                'u' => Kind::Unsigned,
+                'x' => Kind::Hex,
+                _ => {
+                    return Err(ParseError::new(format!(
+                        "invalid character '{}' in type string '{}'",
+                        c, text
+                    )))
+                }
+            };
+
+            // Size as a byte count or a C type letter
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let (size, used) = match (kind, rest.as_bytes().first()) {
+                (Kind::Named | Kind::Char, _) => (1, 0),
+                _ if digits > 0 => match rest[..digits].parse() {
+                    Ok(size) => (size, digits),
+                    Err(_) => (usize::MAX, digits),
+                },
+                (Kind::Float, Some(b'F')) => (4, 1),
+                (Kind::Float, Some(b'D')) => (8, 1),
+                (Kind::Float, Some(b'L')) => (16, 1),
+                (Kind::Float, _) => (8, 0),
+                (_, Some(b'C')) => (1, 1),
+                (_, Some(b'S')) => (2, 1),
+                (_, Some(b'I')) => (4, 1),
+                (_, Some(b'L')) => (8, 1),
+                _ => (4, 0),
+            };
+            rest = &rest[used..];
+
+            let supported = match kind {
+                Kind::Named | Kind::Char => true,
+                Kind::Float => matches!(size, 4 | 8),
+                _ => matches!(size, 1 | 2 | 4 | 8),
+            };
+            if !supported {
+                let what = if kind == Kind::Float {
+                    "floating point"
+                } else {
+                    "integral"
+                };
+                return Err(ParseError::new(format!(
+                    "invalid type string '{}': no {}-byte {} type",
+                    text, size, what
+                )));
+            }
+
+            let trailer = rest.starts_with('z');
+            if trailer {
+                rest = &rest[1..];
+            }
+            types.push(Type {
+                kind,
+                size,
+                trailer,
+            });
+        }
+        Ok(types)
+    }
+
+    // Widest value this type prints, not counting the separating blank
+    pub(crate) fn field_width(&self) -> usize {
+        // Indexed by size, as in GNU od
+        const OCTAL: [usize; 9] = [0, 3, 6, 8, 11, 14, 16, 19, 22];
+        const SIGNED: [usize; 9] = [1, 4, 6, 8, 11, 13, 16, 18, 20];
+        const UNSIGNED: [usize; 9] = [0, 3, 5, 8, 10, 13, 15, 17, 20];
+        match self.kind {
+            Kind::Named | Kind::Char => 3,
+            Kind::Float if self.size == 4 => 15,
+            Kind::Float => 24,
+            Kind::Octal => OCTAL[self.size],
+            Kind::Signed => SIGNED[self.size],
+            Kind::Unsigned => UNSIGNED[self.size],
+            Kind::Hex => 2 * self.size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sizes() {
+        // Sizes are byte counts or C type letters, with integer types defaulting to int
+        let sizes: Vec<usize> = Type::parse_list("xdCuSoIxLfFfDa")
+            .unwrap()
+            .iter()
+            .map(|ty| ty.size)
+            .collect();
+        assert_eq!(sizes, vec![4, 1, 2, 4, 8, 4, 8, 1]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        // Unknown letters and sizes without a matching type are rejected
+        assert_eq!(
+            Type::parse_list("q").unwrap_err().to_string(),
+            "invalid character 'q' in type string 'q'"
+        );
+        assert_eq!(
+            Type::parse_list("x16").unwrap_err().to_string(),
+            "invalid type string 'x16': no 16-byte integral type"
+        );
+        assert!(Type::parse_list("fL").is_err());
+        assert!(Type::parse_list("a1").is_err());
+        assert_eq!(Address::parse("q"), None);
+    }
+}
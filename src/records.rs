@@ -0,0 +1,725 @@
+//! Intel HEX and Motorola S-record files, as used to flash microcontrollers.
+//!
+//! Both formats are text files of records, one per line: a few bytes in hex
+//! with the address they load at and a checksum, along with records that set
+//! the addresses of the records after them, name the file or give the start
+//! address. [`Image::read`] rebuilds the memory image a file describes, and
+//! [`Encoder`] writes binary input as records.
+
+use std::fmt; // Display formatting
+use std::io::{self, BufRead, Read, Write}; // I/O operations
+use std::str::FromStr; // Record formats given by name
+
+use crate::reverse::ParseError; // Errors with line numbers
+use crate::{Config, Result}; // Dump settings and errors
+
+/// Largest number of data bytes [`Encoder`] puts in a record, the most an
+/// `S3` record holds.
+pub const MAX_RECORD_SIZE: usize = 250;
+
+/// Record file formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Intel HEX (`ihex`): `:`-prefixed records, with extended segment and
+    /// linear address records for addresses beyond 16 bits.
+    IntelHex,
+    /// Motorola S-records (`srec`): `S1`, `S2` and `S3` data records with
+    /// 16-, 24- and 32-bit addresses.
+    SRecord,
+}
+
+/// The names accepted by [`Kind::from_str`], for messages.
+pub const KINDS: &str = "ihex or srec";
+
+impl FromStr for Kind {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        match s {
+            "ihex" => Ok(Kind::IntelHex),
+            "srec" => Ok(Kind::SRecord),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::IntelHex => write!(f, "ihex"),
+            Kind::SRecord => write!(f, "srec"),
+        }
+    }
+}
+
+/// Where execution starts, as given by a record file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Start {
+    /// A 32-bit address: Intel HEX type 05, or an `S7`, `S8` or `S9` record.
+    Linear(u32),
+    /// A real-mode `CS:IP` pair: Intel HEX type 03.
+    Segment(u16, u16),
+}
+
+/// Consecutive bytes loaded at an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Address of the first byte.
+    pub address: u64,
+    /// The bytes.
+    pub bytes: Vec<u8>,
+}
+
+impl Segment {
+    /// Address just past the last byte.
+    pub fn end(&self) -> u64 {
+        self.address + self.bytes.len() as u64
+    }
+}
+
+/// The memory image described by a record file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Image {
+    /// Contents of the `S0` header record, if any.
+    pub header: Option<Vec<u8>>,
+    /// The bytes loaded, by address, with gaps between segments.
+    pub segments: Vec<Segment>,
+    /// Start address, if the file gives one.
+    pub start: Option<Start>,
+}
+
+impl Image {
+    /// Reads a record file of `kind`, checking every record's checksum and
+    /// byte count. The file must end with its end record (Intel HEX type 01,
+    /// or `S7`, `S8` or `S9`); lines after it are ignored. Bytes loaded more
+    /// than once are an error.
+    ///
+    /// ```
+    /// use hexdump::records::{Image, Kind};
+    /// let file = ":020000040801F1\n:0400100001020304E2\n:00000001FF\n";
+    /// let image = Image::read(Kind::IntelHex, file.as_bytes()).unwrap();
+    /// assert_eq!(image.segments[0].address, 0x0801_0010);
+    /// assert_eq!(image.segments[0].bytes, [1, 2, 3, 4]);
+    /// ```
+    pub fn read<R: BufRead>(kind: Kind, reader: R) -> Result<Image> {
+        let mut loader = Loader::default();
+        let mut number = 0; // Current line number
+        for line in reader.lines() {
+            let line = line?;
+            number += 1;
+            let text = line.trim();
+            if text.is_empty() {
+                continue;
+            }
+            match kind {
+                Kind::IntelHex => loader.intel_hex(text, number)?,
+                Kind::SRecord => loader.s_record(text, number)?,
+            }
+            if loader.ended {
+                break;
+            }
+        }
+        if !loader.ended {
+            let record = match kind {
+                Kind::IntelHex => "end-of-file record",
+                Kind::SRecord => "termination record (S7, S8 or S9)",
+            };
+            return Err(ParseError::new(number, format!("missing {}", record)).into());
+        }
+        Ok(loader.finish()?)
+    }
+
+    /// Dumps the bytes loaded at addresses from [`skip`](Config::skip) on,
+    /// [`length`](Config::length) of them at most, the way `config` says,
+    /// with their load addresses as offsets. Each segment is dumped on its
+    /// own, separated by a line giving the size of the gap between them. The
+    /// header comes first and the start address last, when the file has them.
+    pub fn dump<W: Write>(&self, config: &Config, mut writer: W) -> Result<()> {
+        if let Some(header) = &self.header {
+            let text: String = header
+                .iter()
+                .map(|&b| match b {
+                    0x20..=0x7e => b as char,
+                    _ => '.',
+                })
+                .collect();
+            writeln!(writer, "-- header: {} --", text)?;
+        }
+        let end = config
+            .length
+            .map_or(u64::MAX, |length| config.skip.saturating_add(length));
+        let mut last = None; // End of the last segment dumped
+        for segment in &self.segments {
+            let from = segment.address.max(config.skip);
+            let to = segment.end().min(end);
+            if from >= to {
+                continue;
+            }
+            if let Some(last) = last {
+                let gap = from - last;
+                writeln!(writer, "-- gap of {} (0x{:x}) bytes --", gap, gap)?;
+            }
+            let bytes =
+                &segment.bytes[(from - segment.address) as usize..(to - segment.address) as usize];
+            let part = Config {
+                skip: from,
+                length: None,
+                ..config.clone()
+            };
+            part.dump_positioned(bytes, &mut writer)?;
+            last = Some(to);
+        }
+        match self.start {
+            Some(Start::Linear(address)) => {
+                writeln!(writer, "-- start address: 0x{:08x} --", address)?
+            }
+            Some(Start::Segment(cs, ip)) => {
+                writeln!(writer, "-- start address: {:04x}:{:04x} (CS:IP) --", cs, ip)?
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+// Image being rebuilt from records
+#[derive(Debug, Default)]
+struct Loader {
+    image: Image,
+    pieces: Vec<(Segment, usize)>, // Bytes loaded in file order, with the line they start on
+    base: u64,                     // Intel HEX: address added to record addresses
+    segmented: bool,               // Intel HEX: whether addresses wrap at 64k above 'base'
+    records: u64,                  // S-records: data records read
+    ended: bool,                   // Whether the end record was read
+}
+
+impl Loader {
+    // Function to add 'bytes' loaded at 'address', from line 'number'
+    fn load(&mut self, address: u64, bytes: &[u8], number: usize) {
+        match self.pieces.last_mut() {
+            Some((last, _)) if last.end() == address => last.bytes.extend_from_slice(bytes),
+            _ if bytes.is_empty() => {}
+            _ => {
+                let segment = Segment {
+                    address,
+                    bytes: bytes.to_vec(),
+                };
+                self.pieces.push((segment, number));
+            }
+        }
+    }
+
+    // Function to read one Intel HEX record
+    fn intel_hex(&mut self, text: &str, number: usize) -> std::result::Result<(), ParseError> {
+        let digits = text.strip_prefix(':').ok_or_else(|| {
+            ParseError::new(number, "expected ':' at the start of an Intel HEX record")
+        })?;
+        let bytes = decode(digits, number)?;
+        if bytes.len() < 5 {
+            return Err(ParseError::new(number, "record too short"));
+        }
+        if bytes.len() != bytes[0] as usize + 5 {
+            let message = format!(
+                "byte count {} doesn't match the {} data bytes",
+                bytes[0],
+                bytes.len() - 5
+            );
+            return Err(ParseError::new(number, message));
+        }
+        let (checksum, body) = bytes.split_last().expect("checked length");
+        let expected = body
+            .iter()
+            .fold(0u8, |sum, &b| sum.wrapping_add(b))
+            .wrapping_neg();
+        check(*checksum, expected, number)?;
+
+        let address = u16::from_be_bytes([body[1], body[2]]) as u64;
+        let data = &body[4..];
+        let value = |size: usize| match data.len() == size {
+            true => Ok(data.iter().fold(0u64, |value, &b| value << 8 | b as u64)),
+            false => Err(ParseError::new(
+                number,
+                format!("record type {:02x} needs {} data bytes", body[3], size),
+            )),
+        };
+        match body[3] {
+            0x00 if self.segmented => {
+                // Addresses wrap around within the 64k segment
+                let split = data.len().min((0x10000 - address) as usize);
+                self.load(self.base + address, &data[..split], number);
+                self.load(self.base, &data[split..], number);
+            }
+            0x00 => self.load(self.base + address, data, number),
+            0x01 => self.ended = true,
+            0x02 => {
+                self.base = value(2)? << 4;
+                self.segmented = true;
+            }
+            0x03 => {
+                let value = value(4)?;
+                self.image.start = Some(Start::Segment((value >> 16) as u16, value as u16));
+            }
+            0x04 => {
+                self.base = value(2)? << 16;
+                self.segmented = false;
+            }
+            0x05 => self.image.start = Some(Start::Linear(value(4)? as u32)),
+            kind => {
+                let message = format!("unknown record type {:02x}", kind);
+                return Err(ParseError::new(number, message));
+            }
+        }
+        Ok(())
+    }
+
+    // Function to read one S-record
+    fn s_record(&mut self, text: &str, number: usize) -> std::result::Result<(), ParseError> {
+        let rest = text
+            .strip_prefix('S')
+            .ok_or_else(|| ParseError::new(number, "expected 'S' at the start of an S-record"))?;
+        let kind = rest.chars().next().unwrap_or(' ');
+        let width = match kind {
+            '0' | '1' | '5' | '9' => 2,
+            '2' | '6' | '8' => 3,
+            '3' | '7' => 4,
+            _ => {
+                return Err(ParseError::new(
+                    number,
+                    format!("unknown record type S{}", kind),
+                ))
+            }
+        };
+        let bytes = decode(&rest[1..], number)?;
+        if bytes.len() < width + 2 {
+            return Err(ParseError::new(number, "record too short"));
+        }
+        if bytes.len() != bytes[0] as usize + 1 {
+            let message = format!(
+                "byte count {} doesn't match the {} bytes after it",
+                bytes[0],
+                bytes.len() - 1
+            );
+            return Err(ParseError::new(number, message));
+        }
+        let (checksum, body) = bytes.split_last().expect("checked length");
+        let expected = !body.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+        check(*checksum, expected, number)?;
+
+        let address = body[1..=width]
+            .iter()
+            .fold(0u64, |value, &b| value << 8 | b as u64);
+        let data = &body[width + 1..];
+        match kind {
+            '0' => self.image.header = Some(data.to_vec()),
+            '1' | '2' | '3' => {
+                self.load(address, data, number);
+                self.records += 1;
+            }
+            '5' | '6' if address != self.records => {
+                let message = format!(
+                    "record count {} doesn't match the {} data records before it",
+                    address, self.records
+                );
+                return Err(ParseError::new(number, message));
+            }
+            '5' | '6' => {}
+            _ => {
+                self.image.start = Some(Start::Linear(address as u32));
+                self.ended = true;
+            }
+        }
+        Ok(())
+    }
+
+    // Function to sort the bytes loaded by address into the image, refusing overlaps
+    fn finish(mut self) -> std::result::Result<Image, ParseError> {
+        self.pieces.sort_by_key(|(segment, _)| segment.address);
+        let mut segments: Vec<(Segment, usize)> = Vec::new();
+        for (segment, number) in self.pieces {
+            match segments.last_mut() {
+                Some((last, from)) if segment.address < last.end() => {
+                    let message = format!(
+                        "bytes at 0x{:x} were already loaded by line {}",
+                        segment.address.max(last.address),
+                        (*from).min(number)
+                    );
+                    return Err(ParseError::new((*from).max(number), message));
+                }
+                Some((last, _)) if segment.address == last.end() => {
+                    last.bytes.extend_from_slice(&segment.bytes)
+                }
+                _ => segments.push((segment, number)),
+            }
+        }
+        self.image.segments = segments.into_iter().map(|(segment, _)| segment).collect();
+        Ok(self.image)
+    }
+}
+
+// Function to decode the hex digits of a record
+fn decode(digits: &str, number: usize) -> std::result::Result<Vec<u8>, ParseError> {
+    if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ParseError::new(number, "invalid hex digit in record"));
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err(ParseError::new(
+            number,
+            "odd number of hex digits in record",
+        ));
+    }
+    Ok((0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).expect("checked hex digits"))
+        .collect())
+}
+
+// Function to compare a record's checksum with the one computed
+fn check(checksum: u8, expected: u8, number: usize) -> std::result::Result<(), ParseError> {
+    if checksum != expected {
+        let message = format!("checksum {:02X} should be {:02X}", checksum, expected);
+        return Err(ParseError::new(number, message));
+    }
+    Ok(())
+}
+
+/// Settings for writing binary input as records.
+///
+/// ```
+/// use hexdump::records::{Encoder, Kind};
+/// let encoder = Encoder { base: 0x100, ..Encoder::new(Kind::IntelHex) };
+/// let mut out = Vec::new();
+/// encoder.write(&hexdump::Config::default(), &b"\x01\x02"[..], &mut out).unwrap();
+/// assert_eq!(out, b":020100000102FA\n:00000001FF\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Encoder {
+    /// Record format.
+    pub kind: Kind,
+    /// Data bytes per record, from 1 to [`MAX_RECORD_SIZE`].
+    pub record_size: usize,
+    /// Address of the first byte of the input, before any skipped bytes.
+    pub base: u64,
+    /// Text of the `S0` header record of S-records.
+    pub header: String,
+}
+
+impl Encoder {
+    /// Records of `kind` holding 16 bytes each, loaded from address 0,
+    /// without a header.
+    pub fn new(kind: Kind) -> Self {
+        Encoder {
+            kind,
+            record_size: 16,
+            base: 0,
+            header: String::new(),
+        }
+    }
+
+    /// Writes the records of `reader`, positioned [`skip`](Config::skip)
+    /// bytes into the input, which loads at [`base`](Encoder::base) plus the
+    /// skipped bytes. Returns the number of bytes written out.
+    ///
+    /// Intel HEX gets an extended linear address record (type 04) wherever
+    /// the upper 16 bits of the address change, and no record crosses a 64k
+    /// boundary. S-records use the narrowest data records (`S1`, `S2` or
+    /// `S3`) that hold the addresses so far, followed by a record count
+    /// (`S5` or `S6`) and the termination record matching the data records,
+    /// giving the base as start address. Addresses beyond 32 bits are an
+    /// error.
+    pub fn write<R: Read, W: Write>(
+        &self,
+        config: &Config,
+        reader: R,
+        mut writer: W,
+    ) -> io::Result<u64> {
+        let mut reader = reader.take(config.length.unwrap_or(u64::MAX));
+        let mut line = vec![0u8; self.record_size.clamp(1, MAX_RECORD_SIZE)];
+        let start = self.base.saturating_add(config.skip);
+        let mut address = start;
+        let mut upper = 0; // Intel HEX: upper 16 bits of the addresses in effect
+        let mut width = width(self.base); // S-records: address bytes of the data records
+        let mut records: u64 = 0; // S-records: data records written
+        if self.kind == Kind::SRecord {
+            let header = &self.header.as_bytes()[..self.header.len().min(252)];
+            write_s_record(&mut writer, 0, 2, 0, header)?;
+        }
+        loop {
+            let len = crate::read_line(&mut reader, &mut line)?;
+            if len == 0 {
+                break;
+            }
+            let last = address + len as u64 - 1;
+            if last > u32::MAX as u64 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("address 0x{:x} doesn't fit in 32 bits", last),
+                ));
+            }
+            match self.kind {
+                Kind::IntelHex => {
+                    let mut data = &line[..len];
+                    let mut at = address;
+                    while !data.is_empty() {
+                        if at >> 16 != upper {
+                            upper = at >> 16;
+                            write_intel_hex(&mut writer, 0x04, 0, &(upper as u16).to_be_bytes())?;
+                        }
+                        let n = data.len().min((0x10000 - (at & 0xffff)) as usize);
+                        write_intel_hex(&mut writer, 0x00, at as u16, &data[..n])?;
+                        data = &data[n..];
+                        at += n as u64;
+                    }
+                }
+                Kind::SRecord => {
+                    width = width.max(self::width(last));
+                    write_s_record(&mut writer, width as u8 - 1, width, address, &line[..len])?;
+                    records += 1;
+                }
+            }
+            address += len as u64;
+            if len < line.len() {
+                break; // A short record can only be the last one
+            }
+        }
+        match self.kind {
+            Kind::IntelHex => write_intel_hex(&mut writer, 0x01, 0, &[])?,
+            Kind::SRecord => {
+                match records {
+                    0..=0xffff => write_s_record(&mut writer, 5, 2, records, &[])?,
+                    0x10000..=0xff_ffff => write_s_record(&mut writer, 6, 3, records, &[])?,
+                    _ => {} // Too many to count
+                }
+                write_s_record(&mut writer, 11 - width as u8, width, self.base, &[])?;
+            }
+        }
+        Ok(address - start)
+    }
+}
+
+// Number of address bytes of the narrowest S-record holding 'address'
+fn width(address: u64) -> usize {
+    match address {
+        0..=0xffff => 2,
+        0x10000..=0xff_ffff => 3,
+        _ => 4,
+    }
+}
+
+// Function to write an Intel HEX record of type 'kind'
+fn write_intel_hex<W: Write>(
+    writer: &mut W,
+    kind: u8,
+    address: u16,
+    data: &[u8],
+) -> io::Result<()> {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend_from_slice(&address.to_be_bytes());
+    bytes.push(kind);
+    bytes.extend_from_slice(data);
+    let sum = bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+    bytes.push(sum.wrapping_neg());
+    writeln!(writer, ":{}", hex(&bytes))
+}
+
+// Function to write an S-record of type 'kind' with a 'width'-byte address
+fn write_s_record<W: Write>(
+    writer: &mut W,
+    kind: u8,
+    width: usize,
+    address: u64,
+    data: &[u8],
+) -> io::Result<()> {
+    let mut bytes = vec![(width + data.len() + 1) as u8];
+    bytes.extend_from_slice(&address.to_be_bytes()[8 - width..]);
+    bytes.extend_from_slice(data);
+    let sum = bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+    bytes.push(!sum);
+    writeln!(writer, "S{}{}", kind, hex(&bytes))
+}
+
+// Uppercase hex digits of 'bytes', as record files are usually written
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, Mode};
+
+    // Helper to encode an input into a string
+    fn encode(encoder: &Encoder, config: &Config, input: &[u8]) -> String {
+        let mut out = Vec::new();
+        encoder.write(config, input, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    // Helper to get the message of an error reading a record file
+    fn error(kind: Kind, file: &str) -> String {
+        match Image::read(kind, file.as_bytes()) {
+            Err(Error::Reverse(e)) => e.to_string(),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_intel_hex_round_trip() {
+        // Records stop at 64k boundaries, where an extended linear address record follows
+        let encoder = Encoder {
+            record_size: 4,
+            base: 0xfffe,
+            ..Encoder::new(Kind::IntelHex)
+        };
+        let out = encode(&encoder, &Config::default(), b"abcdef");
+        let expected = ":02FFFE0061623E\n\
+                        :020000040001F9\n\
+                        :02000000636437\n\
+                        :02000200656631\n\
+                        :00000001FF\n";
+        assert_eq!(out, expected);
+        let image = Image::read(Kind::IntelHex, out.as_bytes()).unwrap();
+        let segment = Segment {
+            address: 0xfffe,
+            bytes: b"abcdef".to_vec(),
+        };
+        assert_eq!(image.segments, vec![segment]);
+    }
+
+    #[test]
+    fn test_s_record_round_trip() {
+        // Records widen with the addresses, and the count and terminator follow
+        let encoder = Encoder {
+            record_size: 2,
+            base: 0xfffd,
+            header: "hi".to_string(),
+            ..Encoder::new(Kind::SRecord)
+        };
+        let config = Config {
+            skip: 1,
+            length: Some(3),
+            ..Config::default()
+        };
+        let out = encode(&encoder, &config, b"abc");
+        let expected = "S0050000686929\n\
+                        S105FFFE61623A\n\
+                        S2050100006396\n\
+                        S5030002FA\n\
+                        S80400FFFDFF\n";
+        assert_eq!(out, expected);
+        let image = Image::read(Kind::SRecord, out.as_bytes()).unwrap();
+        assert_eq!(image.header, Some(b"hi".to_vec()));
+        assert_eq!(image.segments[0].address, 0xfffe);
+        assert_eq!(image.segments[0].bytes, b"abc");
+        assert_eq!(image.start, Some(Start::Linear(0xfffd)));
+    }
+
+    #[test]
+    fn test_intel_hex_segments_and_starts() {
+        // Segment addresses wrap within 64k, and both kinds of start address are read
+        let file = ":020000021000EC\n\
+                    :02FFFF000102FD\n\
+                    :0400000312345678E5\n\
+                    :00000001FF\n\
+                    garbage after the end\n";
+        let image = Image::read(Kind::IntelHex, file.as_bytes()).unwrap();
+        let segments = vec![
+            Segment {
+                address: 0x10000,
+                bytes: vec![2],
+            },
+            Segment {
+                address: 0x1ffff,
+                bytes: vec![1],
+            },
+        ];
+        assert_eq!(image.segments, segments);
+        assert_eq!(image.start, Some(Start::Segment(0x1234, 0x5678)));
+        let file = ":0400000508000131BD\n:00000001FF\n";
+        let image = Image::read(Kind::IntelHex, file.as_bytes()).unwrap();
+        assert_eq!(image.start, Some(Start::Linear(0x0800_0131)));
+    }
+
+    #[test]
+    fn test_read_errors() {
+        // Bad checksums, counts, types and overlaps name their line
+        assert_eq!(
+            error(Kind::IntelHex, "\n:0100000041BF\n"),
+            "line 2: checksum BF should be BE"
+        );
+        assert_eq!(
+            error(Kind::IntelHex, ":0200000041BD\n"),
+            "line 1: byte count 2 doesn't match the 1 data bytes"
+        );
+        assert_eq!(
+            error(Kind::IntelHex, ":00000006FA\n"),
+            "line 1: unknown record type 06"
+        );
+        assert_eq!(
+            error(Kind::IntelHex, ":0100000041BE\n"),
+            "line 1: missing end-of-file record"
+        );
+        assert_eq!(
+            error(Kind::IntelHex, "S104000041BA\n"),
+            "line 1: expected ':' at the start of an Intel HEX record"
+        );
+        assert_eq!(
+            error(
+                Kind::IntelHex,
+                ":0200000041427B\n:0100010041BD\n:00000001FF\n"
+            ),
+            "line 2: bytes at 0x1 were already loaded by line 1"
+        );
+        assert_eq!(
+            error(Kind::SRecord, "S104000041BB\n"),
+            "line 1: checksum BB should be BA"
+        );
+        assert_eq!(
+            error(Kind::SRecord, "S104000041BA\nS5030002FA\nS9030000FC\n"),
+            "line 2: record count 2 doesn't match the 1 data records before it"
+        );
+        assert_eq!(
+            error(Kind::SRecord, "S104000041BA\n"),
+            "line 1: missing termination record (S7, S8 or S9)"
+        );
+        assert_eq!(
+            error(Kind::SRecord, "S4030000FC\n"),
+            "line 1: unknown record type S4"
+        );
+    }
+
+    #[test]
+    fn test_dump_with_gaps() {
+        // Segments are dumped at their addresses within the window, separated by their gaps
+        let image = Image {
+            header: Some(b"fw\n".to_vec()),
+            segments: vec![
+                Segment {
+                    address: 0x10,
+                    bytes: b"abcd".to_vec(),
+                },
+                Segment {
+                    address: 0x20,
+                    bytes: b"ef".to_vec(),
+                },
+            ],
+            start: Some(Start::Linear(0x10)),
+        };
+        let config = Config {
+            format: Mode::Canonical.format(),
+            skip: 0x12,
+            ..Config::default()
+        };
+        let mut out = Vec::new();
+        image.dump(&config, &mut out).unwrap();
+        let expected = "\
+-- header: fw. --
+00000012  63 64                                             |cd|
+00000014
+-- gap of 12 (0xc) bytes --
+00000020  65 66                                             |ef|
+00000022
+-- start address: 0x00000010 --
+";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+}
@@ -0,0 +1,672 @@
+// A small regular expression engine over bytes, for '--find'.
+//
+// Patterns are compiled into a program for a Pike VM: every possible
+// position in the pattern is followed at once, so any input is searched in
+// one pass with no backtracking. Matches are leftmost-longest and don't
+// overlap. The input can be fed in chunks; a match may span any number of
+// them.
+//
+// Syntax: literal characters (non-ASCII ones stand for their UTF-8 bytes),
+// '.' for any byte, classes such as '[a-z_]' and '[^\x00]', the escapes
+// '\xHH', '\n', '\r', '\t', '\0', '\d', '\w', '\s' (and '\D', '\W', '\S'),
+// groups '(...)' or '(?:...)', alternation '|', and the repetitions '*', '+',
+// '?', '{n}', '{n,}' and '{n,m}'.
+
+use std::ops::Range; // Match offsets
+
+// Highest count allowed in '{n,m}', as each repetition is a copy
+const MAX_REPEAT: usize = 1000;
+
+// Most instructions a program may have, as nested repetitions multiply copies
+const MAX_INSTS: usize = 100_000;
+
+// A set of bytes, one bit each
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Set([u64; 4]);
+
+impl Set {
+    const EMPTY: Set = Set([0; 4]);
+    const ALL: Set = Set([u64::MAX; 4]);
+
+    fn byte(b: u8) -> Set {
+        Set::range(b, b)
+    }
+
+    fn range(lo: u8, hi: u8) -> Set {
+        let mut set = Set::EMPTY;
+        for b in lo..=hi {
+            set.0[b as usize / 64] |= 1 << (b % 64);
+        }
+        set
+    }
+
+    fn contains(&self, b: u8) -> bool {
+        self.0[b as usize / 64] & (1 << (b % 64)) != 0
+    }
+
+    fn union(self, other: Set) -> Set {
+        Set(std::array::from_fn(|i| self.0[i] | other.0[i]))
+    }
+
+    fn negate(self) -> Set {
+        Set(self.0.map(|bits| !bits))
+    }
+}
+
+// A parsed pattern
+#[derive(Debug)]
+enum Node {
+    Set(Set),                                // One byte from a set
+    Concat(Vec<Node>),                       // Nodes one after the other
+    Alt(Vec<Node>),                          // Any one of the nodes
+    Repeat(Box<Node>, usize, Option<usize>), // A node repeated between a minimum and maximum
+}
+
+// One instruction of the compiled program
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Inst {
+    Byte(Set),           // Consume a byte from the set
+    Split(usize, usize), // Continue at both places
+    Jump(usize),         // Continue elsewhere
+    Match,               // The pattern matched
+}
+
+/// A compiled byte pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Regex {
+    insts: Vec<Inst>,
+    first: Set, // Bytes a match can start with, to skip quickly to the next candidate
+}
+
+impl Regex {
+    // Function to compile a pattern, or describe what is wrong with it
+    pub(crate) fn parse(pattern: &str) -> Result<Regex, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            at: 0,
+        };
+        let node = parser.alternation()?;
+        if let Some(c) = parser.peek() {
+            return Err(format!("unmatched '{}'", c)); // Only ')' stops an alternation early
+        }
+        if size(&node) > MAX_INSTS {
+            return Err("pattern too large".to_string());
+        }
+        let mut insts = Vec::new();
+        emit(&node, &mut insts);
+        insts.push(Inst::Match);
+
+        // Bytes consumed by the instructions reachable from the start without consuming any
+        let mut first = Set::EMPTY;
+        let mut seen = vec![false; insts.len()];
+        let mut stack = vec![0];
+        while let Some(pc) = stack.pop() {
+            if std::mem::replace(&mut seen[pc], true) {
+                continue;
+            }
+            match &insts[pc] {
+                Inst::Byte(set) => first = first.union(*set),
+                Inst::Split(a, b) => stack.extend([*b, *a]),
+                Inst::Jump(to) => stack.push(*to),
+                Inst::Match => {}
+            }
+        }
+        if first == Set::EMPTY {
+            return Err("empty pattern".to_string()); // Such as '' or '()', only matching nothing
+        }
+        Ok(Regex { insts, first })
+    }
+}
+
+// Recursive descent parser over the characters of a pattern
+struct Parser {
+    chars: Vec<char>,
+    at: usize, // Index of the next character
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.at += 1;
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    // 'a|b|...'
+    fn alternation(&mut self) -> Result<Node, String> {
+        let mut alts = vec![self.concat()?];
+        while self.eat('|') {
+            alts.push(self.concat()?);
+        }
+        Ok(if alts.len() == 1 {
+            alts.pop().unwrap()
+        } else {
+            Node::Alt(alts)
+        })
+    }
+
+    // Atoms and their repetitions, up to '|', ')' or the end
+    fn concat(&mut self) -> Result<Node, String> {
+        let mut items = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let mut node = self.atom()?;
+            while let Some((min, max)) = self.repetition()? {
+                node = Node::Repeat(Box::new(node), min, max);
+            }
+            items.push(node);
+        }
+        Ok(Node::Concat(items))
+    }
+
+    // A repetition after an atom, if there is one
+    fn repetition(&mut self) -> Result<Option<(usize, Option<usize>)>, String> {
+        let range = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.at += 1;
+                let min = self.number()?;
+                let max = if self.eat(',') {
+                    match self.peek() {
+                        Some('}') => None,
+                        _ => Some(self.number()?),
+                    }
+                } else {
+                    Some(min)
+                };
+                if self.peek() != Some('}') {
+                    return Err("expected '}' after a repetition count".to_string());
+                }
+                if max.is_some_and(|max| max < min) {
+                    return Err(format!("bad repetition {{{},{}}}", min, max.unwrap()));
+                }
+                (min, max)
+            }
+            _ => return Ok(None),
+        };
+        self.at += 1;
+        if self.peek() == Some('?') {
+            return Err("lazy repetitions are not supported (matches are longest)".to_string());
+        }
+        Ok(Some(range))
+    }
+
+    // A repetition count
+    fn number(&mut self) -> Result<usize, String> {
+        let start = self.at;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.at += 1;
+        }
+        let digits: String = self.chars[start..self.at].iter().collect();
+        match digits.parse() {
+            Ok(n) if n <= MAX_REPEAT => Ok(n),
+            Ok(_) => Err(format!("repetition counts are limited to {}", MAX_REPEAT)),
+            Err(_) => Err("expected a repetition count".to_string()),
+        }
+    }
+
+    // A single byte, class or group
+    fn atom(&mut self) -> Result<Node, String> {
+        let c = self.next().unwrap();
+        Ok(match c {
+            '(' => {
+                // Groups don't capture, so '(?:' is the same as '('
+                if self.chars[self.at..].starts_with(&['?', ':']) {
+                    self.at += 2;
+                }
+                let node = self.alternation()?;
+                if !self.eat(')') {
+                    return Err("missing ')'".to_string());
+                }
+                node
+            }
+            '[' => Node::Set(self.class()?),
+            '.' => Node::Set(Set::ALL),
+            '\\' => Node::Set(self.escape()?),
+            '*' | '+' | '?' | '{' => return Err(format!("nothing to repeat before '{}'", c)),
+            '^' | '$' => return Err(format!("anchors like '{}' are not supported", c)),
+            c => {
+                // Characters stand for their UTF-8 bytes
+                let mut buf = [0; 4];
+                let bytes = c.encode_utf8(&mut buf).bytes();
+                Node::Concat(bytes.map(|b| Node::Set(Set::byte(b))).collect())
+            }
+        })
+    }
+
+    // The rest of a '[...]' class
+    fn class(&mut self) -> Result<Set, String> {
+        let negated = self.eat('^');
+        let mut set = Set::EMPTY;
+        let mut first = true; // A ']' right at the start is literal
+        loop {
+            let item = match self.next() {
+                None => return Err("missing ']'".to_string()),
+                Some(']') if !first => break,
+                Some('\\') => self.escape()?,
+                Some(c) => Set::byte(ascii(c)?),
+            };
+            first = false;
+            // A range, unless the '-' is last
+            let single = (0..=255)
+                .find(|&b| item.contains(b))
+                .filter(|&b| item == Set::byte(b));
+            if let (Some(lo), Some('-')) = (single, self.peek()) {
+                if self.chars.get(self.at + 1).is_some_and(|&c| c != ']') {
+                    self.at += 1;
+                    let hi = match self.next() {
+                        Some('\\') => self.escape()?,
+                        Some(c) => Set::byte(ascii(c)?),
+                        None => unreachable!(),
+                    };
+                    let hi = (0..=255)
+                        .find(|&b| hi.contains(b))
+                        .filter(|&b| hi == Set::byte(b))
+                        .ok_or("bad range in class")?;
+                    if hi < lo {
+                        return Err(format!("bad range {:02x}-{:02x} in class", lo, hi));
+                    }
+                    set = set.union(Set::range(lo, hi));
+                    continue;
+                }
+            }
+            set = set.union(item);
+        }
+        Ok(if negated { set.negate() } else { set })
+    }
+
+    // The rest of an escape, after '\'
+    fn escape(&mut self) -> Result<Set, String> {
+        let digit = Set::range(b'0', b'9');
+        let word = digit
+            .union(Set::range(b'a', b'z'))
+            .union(Set::range(b'A', b'Z'))
+            .union(Set::byte(b'_'));
+        let space = Set::range(b'\t', b'\r').union(Set::byte(b' '));
+        Ok(match self.next() {
+            Some('x') => {
+                let hex: String = self
+                    .chars
+                    .get(self.at..self.at + 2)
+                    .unwrap_or_default()
+                    .iter()
+                    .collect();
+                let value = u8::from_str_radix(&hex, 16)
+                    .map_err(|_| "expected two hex digits after '\\x'")?;
+                self.at += 2;
+                Set::byte(value)
+            }
+            Some('n') => Set::byte(b'\n'),
+            Some('r') => Set::byte(b'\r'),
+            Some('t') => Set::byte(b'\t'),
+            Some('0') => Set::byte(0),
+            Some('d') => digit,
+            Some('D') => digit.negate(),
+            Some('w') => word,
+            Some('W') => word.negate(),
+            Some('s') => space,
+            Some('S') => space.negate(),
+            Some(c) if c.is_ascii_punctuation() => Set::byte(c as u8),
+            Some(c) => return Err(format!("unknown escape '\\{}'", c)),
+            None => return Err("trailing '\\'".to_string()),
+        })
+    }
+}
+
+// Function to check that a character in a class is a single byte
+fn ascii(c: char) -> Result<u8, String> {
+    u8::try_from(c)
+        .ok()
+        .filter(u8::is_ascii)
+        .ok_or_else(|| format!("'{}' in a class is not ASCII (use \\xHH)", c))
+}
+
+// Function to count the instructions 'emit' appends for a node, without overflowing
+fn size(node: &Node) -> usize {
+    match node {
+        Node::Set(_) => 1,
+        Node::Concat(nodes) => nodes.iter().map(size).fold(0, usize::saturating_add),
+        Node::Alt(nodes) => nodes
+            .iter()
+            .map(|node| size(node).saturating_add(2)) // A split and a jump for all but the last
+            .fold(0, usize::saturating_add)
+            .saturating_sub(2),
+        Node::Repeat(node, min, max) => {
+            let one = size(node);
+            let optional = match max {
+                None => one.saturating_add(2),
+                Some(max) => (max - min).saturating_mul(one.saturating_add(1)),
+            };
+            min.saturating_mul(one).saturating_add(optional)
+        }
+    }
+}
+
+// Function to compile a node, appending its instructions
+fn emit(node: &Node, insts: &mut Vec<Inst>) {
+    match node {
+        Node::Set(set) => insts.push(Inst::Byte(*set)),
+        Node::Concat(nodes) => nodes.iter().for_each(|node| emit(node, insts)),
+        Node::Alt(nodes) => {
+            // Each alternative but the last is tried through a split, then jumps to the end
+            let mut jumps = Vec::new();
+            for (i, node) in nodes.iter().enumerate() {
+                if i + 1 < nodes.len() {
+                    let split = insts.len();
+                    insts.push(Inst::Split(split + 1, 0));
+                    emit(node, insts);
+                    jumps.push(insts.len());
+                    insts.push(Inst::Jump(0));
+                    let next = insts.len();
+                    insts[split] = Inst::Split(split + 1, next);
+                } else {
+                    emit(node, insts);
+                }
+            }
+            let end = insts.len();
+            for jump in jumps {
+                insts[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Repeat(node, min, max) => {
+            for _ in 0..*min {
+                emit(node, insts);
+            }
+            match max {
+                None => {
+                    // Loop: split into another repetition or out
+                    let split = insts.len();
+                    insts.push(Inst::Split(split + 1, 0));
+                    emit(node, insts);
+                    insts.push(Inst::Jump(split));
+                    let end = insts.len();
+                    insts[split] = Inst::Split(split + 1, end);
+                }
+                Some(max) => {
+                    // Optional copies, each able to skip to the end
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(insts.len());
+                        insts.push(Inst::Split(0, 0));
+                        emit(node, insts);
+                    }
+                    let end = insts.len();
+                    for split in splits {
+                        insts[split] = Inst::Split(split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Streaming search with a compiled pattern
+#[derive(Debug)]
+pub(crate) struct Matcher<'a> {
+    regex: &'a Regex,
+    history: Vec<u8>,           // Bytes from 'base' on, to scan again after a match
+    base: u64,                  // Offset of the first byte in 'history'
+    pos: u64,                   // Offset of the next byte to scan
+    threads: Vec<(usize, u64)>, // Instruction and start offset of each thread, earliest start first
+    next: Vec<(usize, u64)>,    // Threads for the next byte, reused between bytes
+    seen: Vec<usize>,           // Generation in which each instruction last got a thread
+    generation: usize,
+    best: Option<Range<u64>>, // Leftmost-longest match found so far, not yet reported
+    stack: Vec<usize>,        // Instructions still to follow while adding a thread
+}
+
+impl<'a> Matcher<'a> {
+    // Function to start searching at an offset
+    pub(crate) fn new(regex: &'a Regex, start: u64) -> Self {
+        Matcher {
+            regex,
+            history: Vec::new(),
+            base: start,
+            pos: start,
+            threads: Vec::new(),
+            next: Vec::new(),
+            seen: vec![0; regex.insts.len()],
+            generation: 1,
+            best: None,
+            stack: Vec::new(),
+        }
+    }
+
+    // Function to search the next bytes of input, adding the matches that are certain to 'found'
+    pub(crate) fn feed(&mut self, chunk: &[u8], found: &mut Vec<Range<u64>>) {
+        self.history.extend_from_slice(chunk);
+        self.scan(false, found);
+    }
+
+    // Function to report the last matches at the end of input
+    pub(crate) fn finish(&mut self, found: &mut Vec<Range<u64>>) {
+        self.scan(true, found);
+    }
+
+    // Offset before which no more matches can start or end
+    pub(crate) fn settled(&self) -> u64 {
+        let mut settled = self.pos;
+        if let Some(&(_, start)) = self.threads.first() {
+            settled = settled.min(start);
+        }
+        if let Some(best) = &self.best {
+            settled = settled.min(best.start);
+        }
+        settled
+    }
+
+    // Function to run the threads over the bytes not scanned yet
+    fn scan(&mut self, eof: bool, found: &mut Vec<Range<u64>>) {
+        let end = self.base + self.history.len() as u64;
+        loop {
+            while self.pos < end {
+                // With nothing under way, skip to a byte that can start a match
+                if self.threads.is_empty() && self.best.is_none() {
+                    let from = (self.pos - self.base) as usize;
+                    let first = &self.regex.first;
+                    match self.history[from..].iter().position(|&b| first.contains(b)) {
+                        Some(skip) => self.pos += skip as u64,
+                        None => {
+                            self.pos = end;
+                            break;
+                        }
+                    }
+                }
+                let byte = self.history[(self.pos - self.base) as usize];
+                // A thread starting here, unless a match already started earlier
+                if self.best.is_none() {
+                    let mut threads = std::mem::take(&mut self.threads);
+                    self.add(&mut threads, 0, self.pos, self.pos);
+                    self.threads = threads;
+                }
+
+                // Advance every thread over the byte
+                self.generation += 1;
+                let mut next = std::mem::take(&mut self.next);
+                next.clear();
+                let threads = std::mem::take(&mut self.threads);
+                for &(pc, start) in &threads {
+                    if let Inst::Byte(set) = &self.regex.insts[pc] {
+                        if set.contains(byte) {
+                            self.add(&mut next, pc + 1, start, self.pos + 1);
+                        }
+                    }
+                }
+                self.threads = next;
+                self.next = threads;
+                self.pos += 1;
+
+                // Only threads starting no later than the match found can still beat it
+                if let Some(best) = &self.best {
+                    let start = best.start;
+                    self.threads.retain(|&(_, from)| from <= start);
+                    if self.threads.is_empty() {
+                        self.report(found);
+                    }
+                }
+            }
+            // At the end of input, no thread can match any more
+            if eof && self.best.is_some() {
+                self.report(found);
+                continue;
+            }
+            break;
+        }
+
+        // Keep only the bytes a later rescan can need
+        let keep = self.settled().max(self.base);
+        self.history.drain(..(keep - self.base) as usize);
+        self.base = keep;
+    }
+
+    // Function to report the best match, then look again from its end
+    fn report(&mut self, found: &mut Vec<Range<u64>>) {
+        let best = self.best.take().unwrap();
+        self.pos = best.end;
+        self.threads.clear();
+        self.generation += 1;
+        found.push(best);
+    }
+
+    // Function to add a thread and those its splits and jumps lead to, at offset 'at'
+    fn add(&mut self, list: &mut Vec<(usize, u64)>, pc: usize, start: u64, at: u64) {
+        let mut stack = std::mem::take(&mut self.stack);
+        stack.push(pc);
+        while let Some(pc) = stack.pop() {
+            if self.seen[pc] == self.generation {
+                continue; // An earlier start already got here
+            }
+            self.seen[pc] = self.generation;
+            match self.regex.insts[pc] {
+                Inst::Byte(_) => list.push((pc, start)),
+                Inst::Split(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                Inst::Jump(to) => stack.push(to),
+                Inst::Match if at > start => {
+                    // Empty matches are ignored
+                    let better = match &self.best {
+                        None => true,
+                        Some(best) => start < best.start || (start == best.start && at > best.end),
+                    };
+                    if better {
+                        self.best = Some(start..at);
+                    }
+                }
+                Inst::Match => {}
+            }
+        }
+        self.stack = stack;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper to find the matches of a pattern, feeding the input in chunks of 'size' bytes
+    fn find(pattern: &str, input: &[u8], size: usize) -> Vec<Range<u64>> {
+        let regex = Regex::parse(pattern).unwrap();
+        let mut matcher = Matcher::new(&regex, 0);
+        let mut found = Vec::new();
+        for chunk in input.chunks(size) {
+            matcher.feed(chunk, &mut found);
+        }
+        matcher.finish(&mut found);
+        found
+    }
+
+    #[test]
+    fn test_syntax() {
+        // Literals, classes, escapes, groups, alternation and repetitions
+        let input = b"xx PK\x03\x04 abc123 \xff\xfe ab-ab-ab id=42; Caf\xc3\xa9";
+        assert_eq!(find(r"PK\x03\x04", input, 64), vec![3..7]);
+        assert_eq!(find(r"[a-c]+\d{2,3}", input, 64), vec![8..14]);
+        assert_eq!(find(r"[^\x00-\x7f]{2}", input, 64), vec![15..17, 37..39]);
+        assert_eq!(find(r"(ab-?){3}", input, 64), vec![18..26]);
+        assert_eq!(find(r"id=\d+;|zzz", input, 64), vec![27..33]);
+        assert_eq!(find(r"(?:Caf)é", input, 64), vec![34..39]);
+        assert_eq!(find(r"\w+\s", input, 64), vec![0..3, 8..15, 24..27]);
+        assert_eq!(find(r"[]x]x", input, 64), vec![0..2]);
+    }
+
+    #[test]
+    fn test_leftmost_longest() {
+        // The earliest match wins, then the longest from there, and matches don't overlap
+        assert_eq!(find("a|ab|abc", b"xabcx", 64), vec![1..4]);
+        assert_eq!(find("b+|ab", b"abbb", 64), vec![0..2, 2..4]);
+        assert_eq!(find("aa", b"aaaaa", 64), vec![0..2, 2..4]);
+        assert_eq!(find("x*", b"abc", 64), vec![]);
+        assert_eq!(find("a.*z", b"a..z..z", 64), vec![0..7]);
+    }
+
+    #[test]
+    fn test_chunks() {
+        // Matches are the same however the input is split, including across many chunks
+        let input = b"header MAGIC-12345-END and MAGIC-9-END, MAGIC-END";
+        let expected = find(r"MAGIC-\d+-END", input, input.len());
+        assert_eq!(expected, vec![7..22, 27..38]);
+        for size in 1..8 {
+            assert_eq!(
+                find(r"MAGIC-\d+-END", input, size),
+                expected,
+                "chunks of {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn test_program_size() {
+        // The size checked before compiling is the size compiled
+        for pattern in ["abc", "a|bc|d", "(ab){2,5}", "x*y+z?", "(a|b){3}c{2,}"] {
+            let regex = Regex::parse(pattern).unwrap();
+            let mut parser = Parser {
+                chars: pattern.chars().collect(),
+                at: 0,
+            };
+            let node = parser.alternation().unwrap();
+            assert_eq!(size(&node) + 1, regex.insts.len(), "{}", pattern); // Plus the final match
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        // Mistakes are described instead of being matched literally
+        for pattern in [
+            "(ab",
+            "ab)",
+            "[ab",
+            "*a",
+            "a{3,1}",
+            "a{2000}",
+            r"\q",
+            r"\x4",
+            "a+?",
+            "^a",
+            "[é]",
+            "",
+            "()",
+            "(?:)*",
+            "((a{1000}){1000}){1000}",
+        ] {
+            assert!(Regex::parse(pattern).is_err(), "{}", pattern);
+        }
+    }
+}
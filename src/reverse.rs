@@ -0,0 +1,473 @@
+//! Reverse conversion: turns a dump back into the bytes it shows, like
+//! `xxd -r`.
+//!
+//! The default layout, the canonical (`-C`) layout, the `xxd` layout and
+//! plain hex are understood. A `*` line repeats the line before it up to the
+//! next offset, and any other gap between offsets is filled with zeros. An
+//! end offset inside the last line drops the bytes past it, such as the zeros
+//! util-linux pads a final partial word with.
+
+use std::error; // Error trait
+use std::fmt; // Display formatting
+use std::io::{self, BufRead, Read, Write}; // I/O operations
+
+/// Layout of the dump being converted back.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Style {
+    /// Detect the layout from the first line.
+    #[default]
+    Auto,
+    /// The default layout: offset and little-endian hex groups (`0100 0302`).
+    Words,
+    /// The canonical layout: offset, hex bytes and a `|...|` column.
+    Canonical,
+    /// The `xxd` layout: `00000000: 4865 6c6c  Hell`.
+    Xxd,
+    /// Continuous hex digits without offsets, as written by `xxd -p`.
+    Plain,
+}
+
+impl Style {
+    /// Guesses the layout of a dump from one of its lines.
+    ///
+    /// The first number is only taken as an offset when the rest of the line
+    /// is laid out like a dump: two blanks, hex bytes and a `|...|` column
+    /// for the canonical layout, or one blank and groups of the same width
+    /// (the last one maybe narrower) for the default layout. As a lone short line at a nonzero offset
+    /// can't be told from hex split by blanks, it is read as plain hex.
+    ///
+    /// ```
+    /// use hexdump::reverse::Style;
+    /// assert_eq!(Style::detect("00000000 0100 0302"), Style::Words);
+    /// assert_eq!(Style::detect("00000000 0100 02"), Style::Words);
+    /// assert_eq!(Style::detect("00000000  00 01  |..|"), Style::Canonical);
+    /// assert_eq!(Style::detect("00000000: 0001 0203  ...."), Style::Xxd);
+    /// assert_eq!(Style::detect("00010203"), Style::Plain);
+    /// assert_eq!(Style::detect("48656c6c 6f"), Style::Plain);
+    /// ```
+    pub fn detect(line: &str) -> Style {
+        let (offset, rest) = split_hex(line);
+        if offset.is_empty() {
+            Style::Plain
+        } else if rest.starts_with(':') {
+            Style::Xxd
+        } else if offset.len() < 7 {
+            Style::Plain
+        } else if let Some(hex) = rest.strip_prefix("  ") {
+            match hex.split_once('|') {
+                Some((hex, _)) if hex.split_whitespace().all(|byte| is_hex(byte, 2)) => {
+                    Style::Canonical
+                }
+                _ => Style::Plain,
+            }
+        } else if let Some(groups) = rest.strip_prefix(' ') {
+            // Groups of one width, the last one possibly narrower, separated by one blank
+            // and making up at most one full line
+            let groups: Vec<&str> = groups.split(' ').collect();
+            let (last, whole) = groups.split_last().expect("split yields a group");
+            // A lone group may be a partial one of any width
+            let width = whole.first().map_or(32, |group| group.len());
+            let bytes = (whole.len() * width + last.len()) / 2;
+            let full = bytes == 16 || offset.bytes().all(|digit| digit == b'0');
+            if [2, 4, 8, 16, 32].contains(&width)
+                && !last.is_empty()
+                && whole.iter().all(|group| is_hex(group, width))
+                && last.len() <= width
+                && is_hex(last, last.len())
+                && last.len() % 2 == 0
+                && bytes <= 16
+                && full
+            {
+                Style::Words
+            } else {
+                Style::Plain
+            }
+        } else {
+            Style::Plain
+        }
+    }
+}
+
+/// Error produced when a line of the dump can't be understood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    line: usize,
+    message: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(line: usize, message: impl Into<String>) -> Self {
+        ParseError {
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// Line number (starting at 1) where the problem was found.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for ParseError {}
+
+// What one line of a dump stands for
+enum Line {
+    Data(u64, Vec<u8>), // Bytes shown at an offset
+    Repeat,             // '*': the previous line repeats
+    End(u64),           // A bare offset marking the end of the input
+    Skip,               // Nothing to do, e.g. a blank line
+}
+
+/// Reads a dump from `reader` and writes the bytes it shows to `writer`.
+pub fn reverse<R: BufRead, W: Write>(style: Style, reader: R, writer: W) -> crate::Result<()> {
+    let mut style = style;
+    let mut out = Rebuilder {
+        writer,
+        pos: 0,
+        last: Vec::new(),
+        held: false,
+        repeat: false,
+    };
+    let mut nibble = None; // Plain hex may split a byte across lines
+    let mut number = 0; // Current line number
+
+    for line in reader.lines() {
+        let line = line?;
+        number += 1;
+        let text = line.trim_end();
+        if style == Style::Auto && !text.is_empty() && text != "*" {
+            style = Style::detect(text);
+        }
+
+        let parsed = match style {
+            Style::Plain => {
+                out.write_all(&parse_plain(text, &mut nibble, number)?)?;
+                continue;
+            }
+            _ if text.is_empty() => Line::Skip,
+            _ if text == "*" => Line::Repeat,
+            Style::Words => parse_words(text, number)?,
+            Style::Canonical => parse_canonical(text, number)?,
+            Style::Xxd => parse_xxd(text, number)?,
+            Style::Auto => Line::Skip,
+        };
+
+        match parsed {
+            Line::Data(offset, bytes) => {
+                out.fill_to(offset, number)?;
+                out.hold(bytes)?;
+            }
+            Line::Repeat => out.repeat = true,
+            Line::End(offset) => out.end(offset, number)?,
+            Line::Skip => {}
+        }
+    }
+
+    if nibble.is_some() {
+        return Err(ParseError::new(number, "odd number of hex digits").into());
+    }
+    out.release()?;
+    out.writer.flush()?;
+    Ok(())
+}
+
+// Writes rebuilt bytes, filling gaps between offsets
+struct Rebuilder<W> {
+    writer: W,
+    pos: u64,      // Offset of the byte after the last line
+    last: Vec<u8>, // Bytes of the last data line, for '*'
+    held: bool,    // Whether 'last' is still unwritten, as an end offset may cut it short
+    repeat: bool,  // Whether a '*' is waiting for the next offset
+}
+
+impl<W: Write> Rebuilder<W> {
+    fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.pos += bytes.len() as u64;
+        self.writer.write_all(bytes)
+    }
+
+    // Keeps a data line back until the next line shows whether all of it is input
+    fn hold(&mut self, bytes: Vec<u8>) -> io::Result<()> {
+        self.release()?;
+        self.pos += bytes.len() as u64;
+        self.last = bytes;
+        self.held = true;
+        Ok(())
+    }
+
+    // Writes the line kept back, if any
+    fn release(&mut self) -> io::Result<()> {
+        if self.held {
+            self.held = false;
+            self.writer.write_all(&self.last)?;
+        }
+        Ok(())
+    }
+
+    // Ends the output at 'offset', which may fall inside the line kept back
+    fn end(&mut self, offset: u64, number: usize) -> crate::Result<()> {
+        let start = self.pos - self.last.len() as u64;
+        if self.held && !self.repeat && (start..self.pos).contains(&offset) {
+            self.held = false;
+            self.writer
+                .write_all(&self.last[..(offset - start) as usize])?;
+            self.pos = offset;
+            return Ok(());
+        }
+        self.fill_to(offset, number)
+    }
+
+    // Brings the output up to 'offset' by repeating the last line after a '*', or with zeros
+    fn fill_to(&mut self, offset: u64, number: usize) -> crate::Result<()> {
+        if offset < self.pos {
+            let message = format!("offset {:x} goes backwards", offset);
+            return Err(ParseError::new(number, message).into());
+        }
+        self.release()?;
+        if self.repeat && !self.last.is_empty() {
+            let line = std::mem::take(&mut self.last);
+            while self.pos < offset {
+                let n = line.len().min((offset - self.pos) as usize);
+                self.write_all(&line[..n])?;
+            }
+            self.last = line; // Still the line a later '*' refers to
+        } else {
+            let gap = offset - self.pos;
+            io::copy(&mut io::repeat(0).take(gap), &mut self.writer)?;
+            self.pos = offset;
+        }
+        self.repeat = false;
+        Ok(())
+    }
+}
+
+// Splits leading hex digits off a line
+fn split_hex(text: &str) -> (&str, &str) {
+    let end = text
+        .find(|c: char| !c.is_ascii_hexdigit())
+        .unwrap_or(text.len());
+    text.split_at(end)
+}
+
+// Whether 'text' is 'width' hex digits
+fn is_hex(text: &str, width: usize) -> bool {
+    text.len() == width && text.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+// Parses a line's leading offset
+fn parse_offset(text: &str, number: usize) -> Result<(u64, &str), ParseError> {
+    let (digits, rest) = split_hex(text);
+    let offset = u64::from_str_radix(digits, 16)
+        .map_err(|_| ParseError::new(number, format!("expected an offset at '{}'", text)))?;
+    Ok((offset, rest))
+}
+
+// Decodes a run of hex digit pairs
+fn decode_hex(digits: &str, number: usize) -> Result<Vec<u8>, ParseError> {
+    if !digits.len().is_multiple_of(2) || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ParseError::new(
+            number,
+            format!("bad hex group '{}'", digits),
+        ));
+    }
+    Ok((0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).expect("checked hex digits"))
+        .collect())
+}
+
+// Default layout: each group is a little-endian number of one or more bytes
+fn parse_words(text: &str, number: usize) -> Result<Line, ParseError> {
+    let (offset, rest) = parse_offset(text, number)?;
+    let mut bytes = Vec::new();
+    for group in rest.split_whitespace() {
+        bytes.extend(decode_hex(group, number)?.iter().rev());
+    }
+    Ok(if bytes.is_empty() {
+        Line::End(offset)
+    } else {
+        Line::Data(offset, bytes)
+    })
+}
+
+// Canonical layout: single hex bytes before the '|' column
+fn parse_canonical(text: &str, number: usize) -> Result<Line, ParseError> {
+    let (offset, rest) = parse_offset(text, number)?;
+    let hex = rest.split('|').next().unwrap_or("");
+    let mut bytes = Vec::new();
+    for byte in hex.split_whitespace() {
+        if byte.len() != 2 {
+            return Err(ParseError::new(number, format!("bad hex byte '{}'", byte)));
+        }
+        bytes.extend(decode_hex(byte, number)?);
+    }
+    Ok(if bytes.is_empty() {
+        Line::End(offset)
+    } else {
+        Line::Data(offset, bytes)
+    })
+}
+
+// xxd layout: hex groups after the colon, up to the two blanks before the text column
+fn parse_xxd(text: &str, number: usize) -> Result<Line, ParseError> {
+    let (offset, rest) = parse_offset(text, number)?;
+    let body = match rest.strip_prefix(':') {
+        Some(body) => body,
+        None if rest.is_empty() => return Ok(Line::End(offset)), // Offset after a squeezed tail
+        None => return Err(ParseError::new(number, "expected ':' after the offset")),
+    };
+    let body = body.strip_prefix(' ').unwrap_or(body);
+    let hex = body.split("  ").next().unwrap_or("");
+    let digits: String = hex.split(' ').collect();
+    Ok(Line::Data(offset, decode_hex(&digits, number)?))
+}
+
+// Plain hex: any hex digits, ignoring whitespace, with pairs allowed to span lines
+fn parse_plain(text: &str, nibble: &mut Option<u8>, number: usize) -> Result<Vec<u8>, ParseError> {
+    let mut bytes = Vec::new();
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        let value = c
+            .to_digit(16)
+            .ok_or_else(|| ParseError::new(number, format!("bad hex digit '{}'", c)))?
+            as u8;
+        match nibble.take() {
+            Some(high) => bytes.push(high << 4 | value),
+            None => *nibble = Some(value),
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Format, Mode};
+
+    // Helper to reverse a dump held in a string
+    fn undump(style: Style, text: &str) -> crate::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        reverse(style, text.as_bytes(), &mut out)?;
+        Ok(out)
+    }
+
+    // Helper to dump bytes with a format, with or without squeezing
+    fn dump(format: Format, squeeze: bool, input: &[u8]) -> String {
+        let config = Config {
+            format,
+            squeeze,
+            ..Config::default()
+        };
+        let mut out = Vec::new();
+        config.dump(input, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    // Inputs covering odd lengths, repeated lines and every byte value
+    fn samples() -> Vec<Vec<u8>> {
+        let mut repeated = vec![0u8; 80];
+        repeated.extend_from_slice(b"tail");
+        let mut all: Vec<u8> = (0..=255).collect();
+        all.extend_from_slice(&[7; 33]);
+        vec![
+            Vec::new(),
+            vec![0x41],
+            b"abc".to_vec(),
+            b"Hello, world! 0123456789\x00\xff".to_vec(),
+            repeated,
+            vec![0x55; 64],
+            all,
+        ]
+    }
+
+    #[test]
+    fn test_round_trip_builtin_layouts() {
+        // Dumping and reversing gives back the original bytes exactly
+        let formats = [
+            Mode::Words.format(),
+            Mode::Canonical.format(),
+            Format::grouped(4).unwrap(),
+            Format::grouped(16).unwrap(),
+            Format::xxd(16, 2, false).unwrap(),
+            Format::xxd(10, 3, true).unwrap(),
+            Format::xxd(16, 0, false).unwrap(),
+        ];
+        for format in formats {
+            for input in samples() {
+                for squeeze in [true, false] {
+                    let text = dump(format.clone(), squeeze, &input);
+                    assert_eq!(undump(Style::Auto, &text).unwrap(), input, "{}", text);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_reverse_end_offset_drops_padding() {
+        // util-linux's default output ends with the length, which cuts the padding off
+        let text = "0000000 4241 0043\n0000003\n";
+        assert_eq!(undump(Style::Auto, text).unwrap(), b"ABC");
+        let text = "0000000 4141 4141\n*\n0000010 0041\n0000011\n";
+        let mut expected = vec![b'A'; 17];
+        assert_eq!(undump(Style::Auto, text).unwrap(), expected);
+        expected.truncate(8);
+        let text = "0000000 4141 4141\n*\n0000008\n";
+        assert_eq!(undump(Style::Auto, text).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_round_trip_plain() {
+        // Plain hex round-trips, with pairs allowed to span lines
+        for input in samples() {
+            let text = dump(Format::xxd_plain(7, false).unwrap(), false, &input);
+            assert_eq!(undump(Style::Plain, &text).unwrap(), input);
+        }
+        assert_eq!(undump(Style::Plain, "4 8\n65 6C\n").unwrap(), b"Hel");
+    }
+
+    #[test]
+    fn test_reverse_real_xxd_output() {
+        // Output of `xxd -g1` reverses even though the text column looks like hex
+        let text = "\
+00000000: 30 31 32 33 34 35 36 37 38 39 61 62 63 64 65 66  0123456789abcdef
+00000010: 41                                               A
+";
+        assert_eq!(undump(Style::Auto, text).unwrap(), b"0123456789abcdefA");
+    }
+
+    #[test]
+    fn test_detect_spaced_plain_hex() {
+        // Hex split by blanks isn't mistaken for a dump whose first number is an offset
+        assert_eq!(undump(Style::Auto, "48656c6c 6f\n").unwrap(), b"Hello");
+        assert_eq!(Style::detect("48656c6c 6f726c64 0a"), Style::Plain);
+        assert_eq!(Style::detect("48656c6c  6f"), Style::Plain);
+        assert_eq!(Style::detect("0000000 4241 0043"), Style::Words);
+        assert_eq!(Style::detect("00000100 41"), Style::Plain);
+        let line = "00000010 0100 0302 0504 0706 0908 0b0a 0d0c 0f0e";
+        assert_eq!(Style::detect(line), Style::Words);
+    }
+
+    #[test]
+    fn test_reverse_gap_filled_with_zeros() {
+        // A jump in offsets without '*' is filled with zeros, like `xxd -r`
+        let text = "00000000: 4142  AB\n00000006: 4344  CD\n";
+        assert_eq!(undump(Style::Auto, text).unwrap(), b"AB\0\0\0\0CD");
+    }
+
+    #[test]
+    fn test_reverse_errors() {
+        // Malformed lines report their line number
+        let err = undump(Style::Canonical, "00000000  41 4  |A|\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 1: bad hex byte '4'");
+        let err = undump(Style::Words, "00000010 4241\n00000000 4443\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: offset 0 goes backwards");
+        let err = undump(Style::Plain, "4g").unwrap_err();
+        assert_eq!(err.to_string(), "line 1: bad hex digit 'g'");
+    }
+}
@@ -0,0 +1,576 @@
+//! Printable strings embedded in binary data, like `strings`.
+//!
+//! Runs of printable characters are looked for in several encodings at
+//! once: ASCII and UTF-8, and UTF-16 in either byte order at either
+//! alignment. UTF-16 characters are limited to printable Latin, Greek and
+//! Cyrillic ones, so that ASCII text read at the wrong alignment doesn't
+//! turn into CJK characters.
+
+use std::fmt;
+use std::io::{Read, Write}; // I/O operations
+use std::ops::Range; // String offsets
+
+use crate::color; // Colored offsets
+use crate::format; // Offset colors
+use crate::Config; // Skip, length and theme
+
+// Bytes read at a time
+const CHUNK: usize = 64 * 1024;
+
+/// How a string is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Printable ASCII and tabs.
+    Ascii,
+    /// Printable characters in UTF-8, some of them not ASCII.
+    Utf8,
+    /// UTF-16, little-endian.
+    Utf16Le,
+    /// UTF-16, big-endian.
+    Utf16Be,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Encoding::Ascii => "ascii",
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+        })
+    }
+}
+
+/// A string found in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found {
+    /// Offset of its first byte.
+    pub offset: u64,
+    /// Length in bytes.
+    pub len: u64,
+    /// How it is encoded.
+    pub encoding: Encoding,
+    /// The characters.
+    pub text: String,
+}
+
+impl Found {
+    /// The bytes the string takes up.
+    pub fn range(&self) -> Range<u64> {
+        self.offset..self.offset + self.len
+    }
+}
+
+// A run of printable characters being collected
+#[derive(Debug)]
+struct Run {
+    start: u64,   // Offset of the first byte
+    end: u64,     // Offset after the last byte
+    text: String, // Characters so far
+    ascii: bool,  // Whether all of them are ASCII
+}
+
+// Function to add a character of 'start..end' to a run, starting one if needed
+fn push(run: &mut Option<Run>, c: char, start: u64, end: u64) {
+    let run = run.get_or_insert_with(|| Run {
+        start,
+        end,
+        text: String::new(),
+        ascii: true,
+    });
+    run.text.push(c);
+    run.ascii &= c.is_ascii();
+    run.end = end;
+}
+
+// Function to end a run, keeping it if it is long enough
+fn end(run: &mut Option<Run>, min: usize, wide: Option<Encoding>, found: &mut Vec<Found>) {
+    if let Some(run) = run.take() {
+        if run.text.chars().count() >= min {
+            let encoding = match wide {
+                Some(encoding) => encoding,
+                None if run.ascii => Encoding::Ascii,
+                None => Encoding::Utf8,
+            };
+            found.push(Found {
+                offset: run.start,
+                len: run.end - run.start,
+                encoding,
+                text: run.text,
+            });
+        }
+    }
+}
+
+// Function to check whether a run is already long enough to be a string
+fn qualified(run: &Option<Run>, min: usize) -> bool {
+    run.as_ref()
+        .is_some_and(|run| run.text.chars().count() >= min)
+}
+
+// Looks for ASCII and UTF-8 strings
+#[derive(Debug, Default)]
+struct Narrow {
+    run: Option<Run>,
+    partial: Vec<u8>,   // Start of a multi-byte character
+    partial_start: u64, // Offset of 'partial'
+}
+
+impl Narrow {
+    fn byte(&mut self, b: u8, at: u64, min: usize, found: &mut Vec<Found>) {
+        if !self.partial.is_empty() {
+            if b & 0xc0 == 0x80 {
+                self.partial.push(b);
+                let needed = match self.partial[0] {
+                    0xc2..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    _ => 4,
+                };
+                if self.partial.len() == needed {
+                    match std::str::from_utf8(&self.partial)
+                        .ok()
+                        .and_then(|s| s.chars().next())
+                    {
+                        Some(c) if !c.is_control() => {
+                            push(&mut self.run, c, self.partial_start, at + 1)
+                        }
+                        _ => end(&mut self.run, min, None, found), // Overlong, surrogate or C1 control
+                    }
+                    self.partial.clear();
+                }
+                return;
+            }
+            // Not a continuation: the character is broken, and this byte starts afresh
+            self.partial.clear();
+            end(&mut self.run, min, None, found);
+        }
+        match b {
+            b'\t' | 0x20..=0x7e => push(&mut self.run, b as char, at, at + 1),
+            0xc2..=0xf4 => {
+                self.partial.push(b);
+                self.partial_start = at;
+            }
+            _ => end(&mut self.run, min, None, found),
+        }
+    }
+
+    fn finish(&mut self, min: usize, found: &mut Vec<Found>) {
+        self.partial.clear();
+        end(&mut self.run, min, None, found);
+    }
+
+    // Earliest offset whose part in a string isn't known yet
+    fn undecided(&self, min: usize) -> Option<u64> {
+        match &self.run {
+            Some(run) if !qualified(&self.run, min) => Some(run.start),
+            _ => (!self.partial.is_empty()).then_some(self.partial_start),
+        }
+    }
+}
+
+// Function to check whether a UTF-16 character can be part of a string
+fn wide_printable(c: char) -> bool {
+    match c {
+        '\t' | ' '..='~' | '\u{a0}'..='\u{24f}' => true, // Latin
+        '\u{370}'..='\u{4ff}' => !matches!(c, '\u{483}'..='\u{489}'), // Greek and Cyrillic, not combining
+        _ => false,
+    }
+}
+
+// Looks for UTF-16 strings in one byte order, at one alignment
+#[derive(Debug)]
+struct Wide {
+    encoding: Encoding, // Utf16Le or Utf16Be
+    parity: u64,        // Offsets of the first byte of each character, modulo 2
+    run: Option<Run>,
+    first: Option<u8>, // First byte of the character under way
+}
+
+impl Wide {
+    fn byte(&mut self, b: u8, at: u64, min: usize, found: &mut Vec<Found>) {
+        if at % 2 == self.parity {
+            self.first = Some(b);
+            return;
+        }
+        let Some(first) = self.first.take() else {
+            return; // The character started before the input
+        };
+        let unit = match self.encoding {
+            Encoding::Utf16Be => u16::from_be_bytes([first, b]),
+            _ => u16::from_le_bytes([first, b]),
+        };
+        match char::from_u32(unit as u32) {
+            Some(c) if wide_printable(c) => push(&mut self.run, c, at - 1, at + 1),
+            _ => end(&mut self.run, min, Some(self.encoding), found),
+        }
+    }
+
+    fn finish(&mut self, min: usize, found: &mut Vec<Found>) {
+        self.first = None;
+        end(&mut self.run, min, Some(self.encoding), found);
+    }
+
+    fn undecided(&self, min: usize, pos: u64) -> Option<u64> {
+        match &self.run {
+            Some(run) if !qualified(&self.run, min) => Some(run.start),
+            _ => self.first.map(|_| pos - 1),
+        }
+    }
+}
+
+// All the scanners, fed the same bytes
+#[derive(Debug)]
+pub(crate) struct Scanners {
+    min: usize, // Characters a string needs at least
+    narrow: Narrow,
+    wide: [Wide; 4],
+    pos: u64, // Offset of the next byte
+}
+
+impl Scanners {
+    pub(crate) fn new(min: usize, start: u64) -> Self {
+        let wide = |encoding, parity| Wide {
+            encoding,
+            parity,
+            run: None,
+            first: None,
+        };
+        Scanners {
+            min: min.max(1),
+            narrow: Narrow::default(),
+            wide: [
+                wide(Encoding::Utf16Le, 0),
+                wide(Encoding::Utf16Le, 1),
+                wide(Encoding::Utf16Be, 0),
+                wide(Encoding::Utf16Be, 1),
+            ],
+            pos: start,
+        }
+    }
+
+    // Function to scan the next bytes, adding the strings that ended to 'found', in no order
+    pub(crate) fn feed(&mut self, chunk: &[u8], found: &mut Vec<Found>) {
+        for &b in chunk {
+            self.narrow.byte(b, self.pos, self.min, found);
+            for wide in &mut self.wide {
+                wide.byte(b, self.pos, self.min, found);
+            }
+            self.pos += 1;
+        }
+    }
+
+    // Function to end the strings under way at the end of input
+    pub(crate) fn finish(&mut self, found: &mut Vec<Found>) {
+        self.narrow.finish(self.min, found);
+        for wide in &mut self.wide {
+            wide.finish(self.min, found);
+        }
+    }
+
+    // Offset before which every byte is known to be in a string or not
+    pub(crate) fn settled(&self) -> u64 {
+        let wide = self
+            .wide
+            .iter()
+            .map(|wide| wide.undecided(self.min, self.pos));
+        std::iter::once(self.narrow.undecided(self.min))
+            .chain(wide)
+            .flatten()
+            .fold(self.pos, u64::min)
+    }
+
+    // Strings under way that are already long enough, up to the last byte scanned
+    pub(crate) fn runs(&self) -> impl Iterator<Item = Range<u64>> + '_ {
+        let runs = std::iter::once(&self.narrow.run).chain(self.wide.iter().map(|wide| &wide.run));
+        runs.filter(|run| qualified(run, self.min))
+            .flatten()
+            .map(|run| run.start..run.end)
+    }
+
+    // Offset before which no more strings can start
+    fn earliest(&self) -> u64 {
+        let runs = std::iter::once(&self.narrow.run).chain(self.wide.iter().map(|wide| &wide.run));
+        let mut earliest = runs.flatten().map(|run| run.start).fold(self.pos, u64::min);
+        if !self.narrow.partial.is_empty() {
+            earliest = earliest.min(self.narrow.partial_start);
+        }
+        if self.wide.iter().any(|wide| wide.first.is_some()) {
+            earliest = earliest.min(self.pos - 1);
+        }
+        earliest
+    }
+}
+
+/// Streaming search for strings, reporting them in order of offset.
+///
+/// Strings found in different encodings can overlap. UTF-16 text can also
+/// be read in the other byte order one byte off, in which case the reading
+/// with the most characters is kept, little-endian winning ties. Otherwise
+/// the later string loses the bytes of the earlier one, and is dropped if
+/// that leaves it too short.
+///
+/// ```
+/// use hexdump::strings::{Encoding, Strings};
+/// let mut strings = Strings::new(4, 0);
+/// let mut found = Vec::new();
+/// strings.feed(b"\x00\x01Hello\x00W\x00i\x00d\x00e\x00\x00\x00", &mut found);
+/// strings.finish(&mut found);
+/// let list: Vec<_> = found.iter().map(|s| (s.offset, s.encoding, s.text.as_str())).collect();
+/// assert_eq!(list, vec![(2, Encoding::Ascii, "Hello"), (8, Encoding::Utf16Le, "Wide")]);
+/// ```
+#[derive(Debug)]
+pub struct Strings {
+    scanners: Scanners,
+    ready: Vec<Found>, // Strings that ended, waiting for earlier ones still under way
+    pending: Option<Found>, // Best of the strings overlapping so far, until no more can
+    reported: u64,     // End of the last string reported
+}
+
+impl Strings {
+    /// Starts looking for strings of at least `min` characters, counting
+    /// offsets from `start`.
+    pub fn new(min: usize, start: u64) -> Self {
+        Strings {
+            scanners: Scanners::new(min, start),
+            ready: Vec::new(),
+            pending: None,
+            reported: start,
+        }
+    }
+
+    /// Scans the next bytes of input, adding the strings certain to come
+    /// next to `found`.
+    pub fn feed(&mut self, chunk: &[u8], found: &mut Vec<Found>) {
+        self.scanners.feed(chunk, &mut self.ready);
+        self.flush(self.scanners.earliest(), found);
+    }
+
+    /// Adds the last strings at the end of input to `found`.
+    pub fn finish(&mut self, found: &mut Vec<Found>) {
+        self.scanners.finish(&mut self.ready);
+        self.flush(u64::MAX, found);
+    }
+
+    // Function to report the strings starting before 'limit', in order and without overlaps
+    fn flush(&mut self, limit: u64, found: &mut Vec<Found>) {
+        let min = self.scanners.min;
+        let rank = |s: &Found| (s.text.chars().count(), std::cmp::Reverse(s.encoding as u8));
+        let wide = |s: &Found| matches!(s.encoding, Encoding::Utf16Le | Encoding::Utf16Be);
+        self.ready.sort_by_key(|s| std::cmp::Reverse(s.offset));
+        while self.ready.last().is_some_and(|s| s.offset < limit) {
+            let string = self.ready.pop().unwrap();
+            let Some(string) = trim(string, self.reported, min) else {
+                continue;
+            };
+            self.pending = match self.pending.take() {
+                Some(pending) if string.offset < pending.offset + pending.len => {
+                    if wide(&string) && wide(&pending) {
+                        Some(if rank(&string) > rank(&pending) {
+                            string
+                        } else {
+                            pending
+                        })
+                    } else {
+                        match trim(string, pending.offset + pending.len, min) {
+                            Some(rest) => {
+                                self.report(pending, found);
+                                Some(rest)
+                            }
+                            None => Some(pending),
+                        }
+                    }
+                }
+                pending => {
+                    if let Some(pending) = pending {
+                        self.report(pending, found);
+                    }
+                    Some(string)
+                }
+            };
+        }
+        // Strings to come start at 'limit' or later, so can't overlap one ending by then
+        if let Some(pending) = self.pending.take() {
+            if pending.offset + pending.len <= limit {
+                self.report(pending, found);
+            } else {
+                self.pending = Some(pending);
+            }
+        }
+    }
+
+    fn report(&mut self, string: Found, found: &mut Vec<Found>) {
+        self.reported = string.offset + string.len;
+        found.push(string);
+    }
+}
+
+// Function to drop the bytes of a string before 'from', if it keeps 'min' characters
+fn trim(string: Found, from: u64, min: usize) -> Option<Found> {
+    if string.offset >= from {
+        return Some(string);
+    }
+    let cut = from - string.offset;
+    if cut >= string.len {
+        return None;
+    }
+    let (cut, text) = match string.encoding {
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let chars = cut.div_ceil(2);
+            (
+                chars * 2,
+                string.text.chars().skip(chars as usize).collect::<String>(),
+            )
+        }
+        _ => {
+            // The text is the very bytes of the input
+            let mut cut = cut as usize;
+            while !string.text.is_char_boundary(cut) {
+                cut += 1;
+            }
+            (cut as u64, string.text[cut..].to_string())
+        }
+    };
+    if text.chars().count() < min {
+        return None;
+    }
+    let encoding = match string.encoding {
+        Encoding::Utf8 if text.is_ascii() => Encoding::Ascii,
+        encoding => encoding,
+    };
+    Some(Found {
+        offset: string.offset + cut,
+        len: string.len - cut,
+        encoding,
+        text,
+    })
+}
+
+/// Writes the strings of at least `min` characters in `reader`, positioned
+/// [`skip`](Config::skip) bytes into the input, one per line with its hex
+/// offset and encoding, like `strings -t x`. Returns the number of
+/// strings.
+pub fn write<R: Read, W: Write>(
+    config: &Config,
+    min: usize,
+    reader: R,
+    mut writer: W,
+) -> std::io::Result<usize> {
+    let mut reader = reader.take(config.length.unwrap_or(u64::MAX));
+    let mut strings = Strings::new(min, config.skip);
+    let mut chunk = vec![0; CHUNK];
+    let mut found = Vec::new();
+    let mut count = 0;
+    loop {
+        let n = crate::read_line(&mut reader, &mut chunk)?;
+        if n == 0 {
+            strings.finish(&mut found);
+        } else {
+            strings.feed(&chunk[..n], &mut found);
+        }
+        for string in found.drain(..) {
+            let mut line = Vec::new();
+            color::paint(
+                &mut line,
+                format::offset_color(config.theme.as_ref()),
+                |out| out.extend_from_slice(format!("{:08x}", string.offset).as_bytes()),
+            );
+            line.extend_from_slice(format!(" {:<8} {}\n", string.encoding, string.text).as_bytes());
+            writer.write_all(&line)?;
+            count += 1;
+        }
+        if n == 0 {
+            return Ok(count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper to list the strings of an input fed in chunks of 'size' bytes
+    fn strings(input: &[u8], min: usize, size: usize) -> Vec<(u64, Encoding, String)> {
+        let mut strings = Strings::new(min, 0);
+        let mut found = Vec::new();
+        for chunk in input.chunks(size) {
+            strings.feed(chunk, &mut found);
+        }
+        strings.finish(&mut found);
+        found
+            .into_iter()
+            .map(|s| (s.offset, s.encoding, s.text))
+            .collect()
+    }
+
+    #[test]
+    fn test_encodings() {
+        // Each encoding is recognized wherever it starts, however the input is split
+        let mut input = b"\x00\x00ELF\x01GNU C\tv1\x00caf\xc3\xa9 cr\xc3\xa8me\xff".to_vec();
+        input.extend_from_slice(b"\x00\x00\x00Path\x00\x00");
+        input.extend("Setup".encode_utf16().flat_map(u16::to_le_bytes));
+        input.extend_from_slice(b"\x07");
+        input.extend("Ωmega".encode_utf16().flat_map(u16::to_be_bytes));
+        let expected = vec![
+            (6, Encoding::Ascii, "GNU C\tv1".to_string()),
+            (15, Encoding::Utf8, "café crème".to_string()),
+            (31, Encoding::Ascii, "Path".to_string()),
+            (37, Encoding::Utf16Le, "Setup".to_string()),
+            (48, Encoding::Utf16Be, "Ωmega".to_string()),
+        ];
+        for size in [1, 2, 3, 7, input.len()] {
+            assert_eq!(strings(&input, 4, size), expected, "chunks of {}", size);
+        }
+    }
+
+    #[test]
+    fn test_min_length_and_broken_characters() {
+        // Short runs are dropped, and a broken UTF-8 sequence ends a string
+        assert_eq!(
+            strings(b"abc\x00abcd", 4, 64),
+            vec![(4, Encoding::Ascii, "abcd".to_string())]
+        );
+        assert_eq!(
+            strings(b"abcd\xc3(efgh\xe0\x80\x80", 4, 64),
+            vec![
+                (0, Encoding::Ascii, "abcd".to_string()),
+                (5, Encoding::Ascii, "(efgh".to_string()),
+            ]
+        );
+        assert_eq!(strings(b"\x00\x00", 1, 64), vec![]);
+    }
+
+    #[test]
+    fn test_scanners_settle() {
+        // Bytes are settled once their strings are known, even before the string ends
+        let mut scanners = Scanners::new(4, 0);
+        let mut found = Vec::new();
+        scanners.feed(b"\xffab", &mut found);
+        assert_eq!(scanners.settled(), 1);
+        scanners.feed(b"cdef", &mut found);
+        assert_eq!(scanners.settled(), 6); // 'f' could still start a UTF-16 character
+        assert_eq!(scanners.runs().collect::<Vec<_>>(), vec![1..7]);
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_write() {
+        // Strings are listed with their offsets and encodings, from the skipped offset
+        let config = Config {
+            skip: 0x10,
+            ..Config::default()
+        };
+        let mut out = Vec::new();
+        let count = write(
+            &config,
+            4,
+            &b"\x01hello\x00w\x00o\x00r\x00l\x00d\x00"[..],
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "00000011 ascii    hello\n00000017 utf-16le world\n"
+        );
+    }
+}
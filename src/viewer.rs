@@ -0,0 +1,1153 @@
+//! Interactive full-screen viewer and editor.
+//!
+//! The file is shown in the canonical layout, rendered by the same line
+//! formatter as the dumps, with a cursor whose offset and decoded values
+//! are shown below the lines. The file is read lazily, a page at a time,
+//! so even very large files open instantly. Edits are kept in memory as
+//! changed bytes over the file until they are saved, in place or to a new
+//! file.
+//!
+//! The viewer only talks to a key source and a screen, both plain
+//! [`Read`] and [`Write`] streams; putting the terminal in raw mode is up
+//! to the caller.
+
+use std::collections::{BTreeMap, HashMap, VecDeque}; // Bookmarks, edits and page cache
+use std::fs::{self, File, OpenOptions}; // Saving
+use std::io::{self, Read, Seek, SeekFrom, Write}; // I/O operations
+use std::ops::Range; // Search matches
+use std::path::{Path, PathBuf}; // Files saved to
+
+use crate::color::Palette; // Cursor and match highlighting
+use crate::format::Program; // Line formatter
+use crate::input::parse_size; // Offsets typed at the goto prompt
+use crate::{Mode, Theme}; // Layout and colors
+
+// Bytes read from the file at a time
+const PAGE_SIZE: usize = 64 * 1024;
+// Pages kept in memory, oldest dropped first
+const CACHED_PAGES: usize = 64;
+
+// Highlights, as SGR parameters
+const CURSOR: &str = "7"; // Reverse video
+const MATCH: &str = "30;43"; // Black on yellow
+const BOOKMARK: &str = "4"; // Underlined
+const CHANGED: &str = "1;31"; // Bold red
+
+// Entering and leaving the full-screen mode: alternate screen, hidden cursor, no line wrapping
+const ENTER: &[u8] = b"\x1b[?1049h\x1b[?25l\x1b[?7l";
+const LEAVE: &[u8] = b"\x1b[?7h\x1b[?25h\x1b[?1049l";
+
+// Summary of the keys, shown by '?'
+const HELP: &str = "arrows/hjkl move  PgUp/PgDn/space/b page  g/G start/end  \
+                    : goto  / text search  x hex search  n/N next/prev  \
+                    m<a-z> mark  '<a-z> jump  i edit (Tab: hex/ASCII, Esc: done)  \
+                    u/^R undo/redo  w save  W save as  q quit  Q quit without saving";
+
+/// A file read lazily in fixed-size pages, keeping only the most recently
+/// loaded ones in memory, with changed bytes kept apart until saved.
+#[derive(Debug)]
+pub struct Pages<R> {
+    reader: R,
+    len: u64,
+    pages: HashMap<u64, Vec<u8>>, // Loaded pages by index
+    order: VecDeque<u64>,         // Page indexes, oldest first
+    edits: BTreeMap<u64, u8>,     // Bytes that differ from the file
+}
+
+impl<R: Read + Seek> Pages<R> {
+    /// Wraps `reader`, finding its length by seeking to the end.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        Ok(Pages {
+            reader,
+            len,
+            pages: HashMap::new(),
+            order: VecDeque::new(),
+            edits: BTreeMap::new(),
+        })
+    }
+
+    /// Length of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Fills `buf` with the bytes at `offset`, changes included, returning
+    /// how many there were.
+    pub fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.read_original(offset, buf)?;
+        for (&at, &byte) in self.edits.range(offset..offset + n as u64) {
+            buf[(at - offset) as usize] = byte;
+        }
+        Ok(n)
+    }
+
+    /// Changes the byte at `offset` in memory. Setting a byte back to its
+    /// value in the file drops the change.
+    pub fn set(&mut self, offset: u64, byte: u8) -> io::Result<()> {
+        let mut original = [0u8; 1];
+        if self.read_original(offset, &mut original)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "offset past the end of the file",
+            ));
+        }
+        if original[0] == byte {
+            self.edits.remove(&offset);
+        } else {
+            self.edits.insert(offset, byte);
+        }
+        Ok(())
+    }
+
+    /// The changed bytes by offset.
+    pub fn edits(&self) -> &BTreeMap<u64, u8> {
+        &self.edits
+    }
+
+    /// Writes the changed bytes into the file at `path`, which must be the
+    /// file being read. Only the changed runs of bytes are written.
+    pub fn save_in_place(&mut self, path: &Path) -> io::Result<()> {
+        let mut runs: Vec<(u64, Vec<u8>)> = Vec::new();
+        for (&offset, &byte) in &self.edits {
+            match runs.last_mut() {
+                Some((start, bytes)) if *start + bytes.len() as u64 == offset => bytes.push(byte),
+                _ => runs.push((offset, vec![byte])),
+            }
+        }
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        for (start, bytes) in runs {
+            file.seek(SeekFrom::Start(start))?;
+            file.write_all(&bytes)?;
+        }
+        file.sync_all()?;
+
+        // The file now holds the changes; reload it from there
+        self.edits.clear();
+        self.pages.clear();
+        self.order.clear();
+        Ok(())
+    }
+
+    /// Writes the whole file with its changes to `writer`, a page at a time.
+    pub fn save_to<W: Write>(&mut self, mut writer: W) -> io::Result<()> {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        let mut offset = 0;
+        while offset < self.len {
+            let n = self.read_at(offset, &mut buf)?;
+            if n == 0 {
+                break; // The file shrank since it was opened
+            }
+            writer.write_all(&buf[..n])?;
+            offset += n as u64;
+        }
+        writer.flush()
+    }
+
+    // Fills 'buf' with the bytes of the file itself at 'offset'
+    fn read_original(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let mut done = 0;
+        while done < buf.len() {
+            let at = offset + done as u64;
+            if at >= self.len {
+                break;
+            }
+            let page = self.page(at / PAGE_SIZE as u64)?;
+            let start = (at % PAGE_SIZE as u64) as usize;
+            if start >= page.len() {
+                break; // The file shrank since it was opened
+            }
+            let n = (page.len() - start).min(buf.len() - done);
+            buf[done..done + n].copy_from_slice(&page[start..start + n]);
+            done += n;
+        }
+        Ok(done)
+    }
+
+    /// Finds `pattern` starting at or after `from` (forward) or before it
+    /// (backward), returning the offset of the nearest match.
+    pub fn find(&mut self, pattern: &[u8], from: u64, forward: bool) -> io::Result<Option<u64>> {
+        if pattern.is_empty() || (pattern.len() as u64) > self.len {
+            return Ok(None);
+        }
+        let last = self.len - pattern.len() as u64; // Last offset a match can start at
+        let overlap = pattern.len() - 1; // Bytes shared by neighbouring chunks
+        let mut buf = vec![0u8; PAGE_SIZE + overlap];
+
+        if forward {
+            let mut start = from;
+            while start <= last {
+                let n = self.read_at(start, &mut buf)?;
+                if let Some(i) = buf[..n].windows(pattern.len()).position(|w| w == pattern) {
+                    return Ok(Some(start + i as u64));
+                }
+                start += PAGE_SIZE as u64;
+            }
+        } else {
+            // Chunks ending just before 'from', going back to the start
+            let mut end = from.min(last + 1); // Matches must start before 'end'
+            while end > 0 {
+                let start = end.saturating_sub(PAGE_SIZE as u64);
+                let n = self.read_at(start, &mut buf[..(end - start) as usize + overlap])?;
+                if let Some(i) = buf[..n].windows(pattern.len()).rposition(|w| w == pattern) {
+                    return Ok(Some(start + i as u64));
+                }
+                end = start;
+            }
+        }
+        Ok(None)
+    }
+
+    // Loads a page, dropping the oldest one when the cache is full
+    fn page(&mut self, index: u64) -> io::Result<&[u8]> {
+        if !self.pages.contains_key(&index) {
+            if self.order.len() >= CACHED_PAGES {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.pages.remove(&oldest);
+                }
+            }
+            let mut page = Vec::with_capacity(PAGE_SIZE);
+            self.reader
+                .seek(SeekFrom::Start(index * PAGE_SIZE as u64))?;
+            (&mut self.reader)
+                .take(PAGE_SIZE as u64)
+                .read_to_end(&mut page)?;
+            self.pages.insert(index, page);
+            self.order.push_back(index);
+        }
+        Ok(&self.pages[&index])
+    }
+}
+
+/// A key press, decoded from the bytes a terminal sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    /// A control key, as the letter held with Ctrl (`Ctrl('c')`).
+    Ctrl(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Delete,
+    Enter,
+    Backspace,
+    Tab,
+    Esc,
+}
+
+/// Decodes the keys in one read from a terminal in raw mode.
+///
+/// Terminals send each escape sequence in a single write, so an `ESC` at
+/// the end of the bytes is the Escape key itself. Unknown sequences are
+/// dropped.
+///
+/// ```
+/// use hexdump::viewer::{decode_keys, Key};
+/// assert_eq!(
+///     decode_keys(b"q\x1b[A\x1b[6~\r\x03\x1b"),
+///     vec![Key::Char('q'), Key::Up, Key::PageDown, Key::Enter, Key::Ctrl('c'), Key::Esc]
+/// );
+/// ```
+pub fn decode_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        i += 1;
+        let key = match b {
+            0x1b => {
+                // CSI ('ESC [') and SS3 ('ESC O') sequences end at their first letter or '~'
+                match bytes.get(i) {
+                    Some(b'[' | b'O') => {
+                        let body = &bytes[i + 1..];
+                        let len = body
+                            .iter()
+                            .position(|b| b.is_ascii_alphabetic() || *b == b'~')
+                            .map_or(body.len(), |end| end + 1);
+                        i += 1 + len;
+                        match &body[..len] {
+                            b"A" => Key::Up,
+                            b"B" => Key::Down,
+                            b"C" => Key::Right,
+                            b"D" => Key::Left,
+                            b"H" | b"1~" | b"7~" => Key::Home,
+                            b"F" | b"4~" | b"8~" => Key::End,
+                            b"3~" => Key::Delete,
+                            b"5~" => Key::PageUp,
+                            b"6~" => Key::PageDown,
+                            _ => continue,
+                        }
+                    }
+                    _ => Key::Esc,
+                }
+            }
+            b'\r' | b'\n' => Key::Enter,
+            b'\t' => Key::Tab,
+            0x7f | 0x08 => Key::Backspace,
+            0x01..=0x1a => Key::Ctrl((b'a' + b - 1) as char),
+            0x00..=0x7e => Key::Char(b as char),
+            _ => {
+                // A UTF-8 character, its length given by the leading byte
+                let len = match b {
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    0xf0..=0xf7 => 4,
+                    _ => continue,
+                };
+                let end = (i - 1 + len).min(bytes.len());
+                let text = std::str::from_utf8(&bytes[i - 1..end]);
+                i = end;
+                match text.ok().and_then(|text| text.chars().next()) {
+                    Some(c) => Key::Char(c),
+                    None => continue,
+                }
+            }
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+// What a prompt asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ask {
+    Goto,   // An offset, absolute or relative with '+' or '-'
+    Text,   // Text to search for
+    Hex,    // Hex bytes to search for
+    SaveAs, // A file to save a copy to
+}
+
+// The column typed into while editing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Hex,   // Two hex digits per byte
+    Ascii, // One character per byte
+}
+
+// One edited byte, for undo and redo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Change {
+    offset: u64,
+    old: u8,
+    new: u8,
+}
+
+// The key after 'm' or '\'' names a bookmark
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pending {
+    SetMark,
+    JumpToMark,
+}
+
+/// State of the full-screen viewer over a lazily read file.
+#[derive(Debug)]
+pub struct Viewer<R> {
+    data: Pages<R>,
+    program: Program,               // Line layout
+    theme: Option<Theme>,           // Byte colors, if any
+    rows: usize,                    // Terminal height
+    top: u64,                       // Offset of the first line shown
+    cursor: u64,                    // Offset of the selected byte
+    bookmarks: BTreeMap<char, u64>, // Offsets marked with 'm'
+    search: Option<Vec<u8>>,        // Last pattern searched for
+    found: Option<Range<u64>>,      // Last match, highlighted
+    prompt: Option<(Ask, String)>,  // Prompt being typed in, and its input
+    pending: Option<Pending>,       // Waiting for a bookmark name
+    message: String,                // Shown in the status line until the next key
+    path: Option<PathBuf>,          // File saved to in place
+    edit: Option<Column>,           // Column typed into, when editing
+    nibble: bool,                   // The high hex digit of the cursor byte was just typed
+    undo: Vec<Change>,              // Changes made, latest last
+    redo: Vec<Change>,              // Changes undone, latest last
+    dirty: bool,                    // Changes since the last save
+}
+
+impl<R: Read + Seek> Viewer<R> {
+    /// Opens a viewer on `reader`, colored with `theme` if given.
+    pub fn new(reader: R, theme: Option<Theme>) -> io::Result<Self> {
+        let program = Mode::Canonical
+            .format()
+            .compile()
+            .expect("canonical layout is valid");
+        Ok(Viewer {
+            data: Pages::new(reader)?,
+            program,
+            theme,
+            rows: 24,
+            top: 0,
+            cursor: 0,
+            bookmarks: BTreeMap::new(),
+            search: None,
+            found: None,
+            prompt: None,
+            pending: None,
+            message: String::new(),
+            path: None,
+            edit: None,
+            nibble: false,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            dirty: false,
+        })
+    }
+
+    /// Lets `w` save the changes in place to `path`, which must be the file
+    /// being read.
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Whether there are changes that haven't been saved.
+    pub fn is_modified(&self) -> bool {
+        self.dirty
+    }
+
+    /// Offset of the selected byte.
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    /// Sets the terminal height.
+    pub fn resize(&mut self, rows: usize) {
+        self.rows = rows;
+        self.scroll_to_cursor();
+    }
+
+    /// Moves the cursor to `offset`, or to the last byte if past the end.
+    pub fn goto(&mut self, offset: u64) {
+        self.cursor = offset.min(self.data.len().saturating_sub(1));
+        self.scroll_to_cursor();
+    }
+
+    /// Handles a key, returning `false` when the viewer should close.
+    pub fn handle(&mut self, key: Key) -> io::Result<bool> {
+        self.message.clear();
+        if self.prompt.is_some() {
+            self.handle_prompt(key)?;
+            return Ok(true);
+        }
+        if let Some(pending) = self.pending.take() {
+            self.handle_mark(pending, key);
+            return Ok(true);
+        }
+        if let Some(column) = self.edit {
+            if self.handle_edit(column, key)? {
+                return Ok(true);
+            }
+        }
+        self.nibble = false; // Moving on finishes the byte being typed
+
+        let line = self.line_size();
+        let page = line * self.lines() as u64;
+        match key {
+            // Unsaved changes need a save or 'Q' first
+            Key::Char('q') | Key::Ctrl('c') | Key::Esc if self.dirty => {
+                self.message =
+                    "unsaved changes: w saves, W saves as, Q quits without saving".to_string()
+            }
+            Key::Char('q' | 'Q') | Key::Ctrl('c') | Key::Esc => return Ok(false),
+            Key::Left | Key::Char('h') => self.goto(self.cursor.saturating_sub(1)),
+            Key::Right | Key::Char('l') => self.goto(self.cursor + 1),
+            Key::Up | Key::Char('k') => self.goto(self.cursor.saturating_sub(line)),
+            Key::Down | Key::Char('j') => self.move_down(line),
+            Key::PageUp | Key::Char('b') | Key::Ctrl('b') => {
+                self.top = self.top.saturating_sub(page);
+                self.goto(self.cursor.saturating_sub(page));
+            }
+            Key::PageDown | Key::Char(' ') | Key::Ctrl('f') => {
+                self.top = (self.top + page).min(self.last_top());
+                self.move_down(page);
+            }
+            Key::Home => self.goto(self.cursor - self.cursor % line),
+            Key::End => self.goto(self.cursor - self.cursor % line + line - 1),
+            Key::Char('g') => self.goto(0),
+            Key::Char('G') => self.goto(u64::MAX),
+            Key::Char(':') => self.prompt = Some((Ask::Goto, String::new())),
+            Key::Char('/') => self.prompt = Some((Ask::Text, String::new())),
+            Key::Char('x') => self.prompt = Some((Ask::Hex, String::new())),
+            Key::Char('n') => self.search_next(true, true)?,
+            Key::Char('N') => self.search_next(false, true)?,
+            Key::Char('m') => self.pending = Some(Pending::SetMark),
+            Key::Char('\'') => self.pending = Some(Pending::JumpToMark),
+            Key::Char('?') => self.message = HELP.to_string(),
+            Key::Char('i') if self.data.is_empty() => self.message = "nothing to edit".to_string(),
+            Key::Char('i') => self.edit = Some(Column::Hex),
+            Key::Char('u') | Key::Ctrl('z') => self.undo()?,
+            Key::Ctrl('r') | Key::Ctrl('y') => self.redo()?,
+            Key::Char('w') => self.save()?,
+            Key::Char('W') => self.prompt = Some((Ask::SaveAs, String::new())),
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Draws the whole screen: the lines, the values at the cursor and a
+    /// status line.
+    pub fn render<W: Write>(&mut self, mut screen: W) -> io::Result<()> {
+        let line = self.line_size();
+        let mut marks: Vec<(Range<u64>, &str)> = self
+            .bookmarks
+            .values()
+            .map(|&offset| (offset..offset + 1, BOOKMARK))
+            .collect();
+        let shown = self.top..self.top + line * self.lines() as u64;
+        marks.extend(
+            self.data
+                .edits()
+                .range(shown)
+                .map(|(&offset, _)| (offset..offset + 1, CHANGED)),
+        );
+        if let Some(found) = &self.found {
+            marks.push((found.clone(), MATCH));
+        }
+        if !self.data.is_empty() {
+            marks.push((self.cursor..self.cursor + 1, CURSOR));
+        }
+        let palette = Palette {
+            theme: self.theme.as_ref(),
+            marks: &marks,
+            ..Default::default()
+        };
+
+        let mut out = b"\x1b[H".to_vec();
+        let mut block = vec![0u8; line as usize];
+        for row in 0..self.lines() {
+            let offset = self.top + row as u64 * line;
+            let len = self.data.read_at(offset, &mut block)?;
+            out.extend_from_slice(format!("\x1b[{};1H", row + 1).as_bytes());
+            if len > 0 {
+                self.program
+                    .render_block(&mut out, &block, len, offset, &palette)?;
+                if out.last() == Some(&b'\n') {
+                    out.pop();
+                }
+            }
+            out.extend_from_slice(b"\x1b[K");
+        }
+
+        // Values at the cursor in both byte orders, then the status line
+        let mut bytes = [0u8; 8];
+        let len = self.data.read_at(self.cursor, &mut bytes)?;
+        let row = self.lines() + 1;
+        for (i, little) in [true, false].into_iter().enumerate() {
+            let values = decode(&bytes[..len], little);
+            out.extend_from_slice(format!("\x1b[{};1H{}\x1b[K", row + i, values).as_bytes());
+        }
+        let status = match &self.prompt {
+            Some((ask, input)) => {
+                let label = match ask {
+                    Ask::Goto => "goto offset",
+                    Ask::Text => "search text",
+                    Ask::Hex => "search hex",
+                    Ask::SaveAs => "save as",
+                };
+                format!("{}: {}", label, input)
+            }
+            None => {
+                let position = format!(
+                    "{:08x} ({}) of {:08x}",
+                    self.cursor,
+                    self.cursor,
+                    self.data.len()
+                );
+                let mode = match self.edit {
+                    Some(Column::Hex) => "  -- EDIT HEX --",
+                    Some(Column::Ascii) => "  -- EDIT ASCII --",
+                    None => "",
+                };
+                let modified = if self.dirty { "  [modified]" } else { "" };
+                match self.message.as_str() {
+                    "" => format!("{}{}{}  ? help", position, mode, modified),
+                    message => format!("{}{}{}  {}", position, mode, modified, message),
+                }
+            }
+        };
+        out.extend_from_slice(
+            format!("\x1b[{};1H\x1b[7m{}\x1b[0m\x1b[K", row + 2, status).as_bytes(),
+        );
+
+        screen.write_all(&out)?;
+        screen.flush()
+    }
+
+    // Bytes per line
+    fn line_size(&self) -> u64 {
+        self.program.block_size() as u64
+    }
+
+    // Number of lines of the file shown, leaving room for the values and status
+    fn lines(&self) -> usize {
+        self.rows.saturating_sub(3).max(1)
+    }
+
+    // First line offset that still fills the screen, or 0 for short files
+    fn last_top(&self) -> u64 {
+        let line = self.line_size();
+        let last_line = self.data.len().saturating_sub(1) / line * line;
+        last_line.saturating_sub((self.lines() as u64 - 1) * line)
+    }
+
+    // Moves the cursor down by 'n' bytes, stopping on the last line
+    fn move_down(&mut self, n: u64) {
+        let last = self.data.len().saturating_sub(1);
+        let line = self.line_size();
+        if self.cursor / line < last / line {
+            self.goto(self.cursor + n);
+        }
+    }
+
+    // Scrolls so that the cursor's line is on screen
+    fn scroll_to_cursor(&mut self) {
+        let line = self.line_size();
+        let cursor_line = self.cursor / line * line;
+        let height = self.lines() as u64 * line;
+        if cursor_line < self.top {
+            self.top = cursor_line;
+        } else if cursor_line >= self.top + height {
+            self.top = cursor_line + line - height;
+        }
+    }
+
+    // Handles a key typed at a prompt, acting on it with Enter
+    fn handle_prompt(&mut self, key: Key) -> io::Result<()> {
+        let Some((ask, input)) = &mut self.prompt else {
+            return Ok(());
+        };
+        match key {
+            Key::Char(c) => input.push(c),
+            Key::Backspace => {
+                input.pop();
+            }
+            Key::Esc | Key::Ctrl('c') => self.prompt = None,
+            Key::Enter => {
+                let (ask, input) = (*ask, std::mem::take(input));
+                self.prompt = None;
+                match ask {
+                    Ask::Goto => self.goto_input(&input),
+                    Ask::Text => self.start_search(input.into_bytes())?,
+                    Ask::Hex => match parse_hex(&input) {
+                        Some(pattern) => self.start_search(pattern)?,
+                        None => self.message = format!("invalid hex bytes '{}'", input),
+                    },
+                    Ask::SaveAs if input.is_empty() => {}
+                    Ask::SaveAs => self.save_as(Path::new(&input))?,
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Handles a key while editing, returning false for keys left to navigation
+    fn handle_edit(&mut self, column: Column, key: Key) -> io::Result<bool> {
+        match (column, key) {
+            (_, Key::Esc) => {
+                self.edit = None;
+                self.nibble = false;
+            }
+            (Column::Hex, Key::Tab) => self.edit = Some(Column::Ascii),
+            (Column::Ascii, Key::Tab) => self.edit = Some(Column::Hex),
+            (_, Key::Ctrl('z')) => self.undo()?,
+            (_, Key::Ctrl('y') | Key::Ctrl('r')) => self.redo()?,
+            (Column::Hex, Key::Char(c)) => match c.to_digit(16) {
+                // The first digit sets the high half of the byte, the second the low half
+                Some(digit) => {
+                    let old = self.byte_at(self.cursor)?;
+                    let digit = digit as u8;
+                    if self.nibble {
+                        self.overwrite((old & 0xf0) | digit, true)?;
+                        self.nibble = false;
+                        self.goto(self.cursor + 1);
+                    } else {
+                        self.overwrite((digit << 4) | (old & 0x0f), false)?;
+                        self.nibble = true;
+                    }
+                }
+                None => self.message = format!("'{}' is not a hex digit", c),
+            },
+            (Column::Ascii, Key::Char(c)) => match u8::try_from(c) {
+                Ok(byte @ b' '..=b'~') => {
+                    self.overwrite(byte, false)?;
+                    self.goto(self.cursor + 1);
+                }
+                _ => self.message = format!("'{}' is not printable ASCII", c),
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // The byte at 'offset', changes included
+    fn byte_at(&mut self, offset: u64) -> io::Result<u8> {
+        let mut byte = [0u8; 1];
+        self.data.read_at(offset, &mut byte)?;
+        Ok(byte[0])
+    }
+
+    // Sets the cursor byte, merging with the last change when finishing a hex byte
+    fn overwrite(&mut self, byte: u8, merge: bool) -> io::Result<()> {
+        let offset = self.cursor;
+        let old = self.byte_at(offset)?;
+        match self.undo.last_mut() {
+            Some(change) if merge && change.offset == offset => change.new = byte,
+            _ => self.undo.push(Change {
+                offset,
+                old,
+                new: byte,
+            }),
+        }
+        self.redo.clear();
+        self.data.set(offset, byte)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    // Reverts the latest change
+    fn undo(&mut self) -> io::Result<()> {
+        self.nibble = false;
+        let Some(change) = self.undo.pop() else {
+            self.message = "nothing to undo".to_string();
+            return Ok(());
+        };
+        self.data.set(change.offset, change.old)?;
+        self.redo.push(change);
+        self.dirty = true;
+        self.goto(change.offset);
+        Ok(())
+    }
+
+    // Makes the latest undone change again
+    fn redo(&mut self) -> io::Result<()> {
+        self.nibble = false;
+        let Some(change) = self.redo.pop() else {
+            self.message = "nothing to redo".to_string();
+            return Ok(());
+        };
+        self.data.set(change.offset, change.new)?;
+        self.undo.push(change);
+        self.dirty = true;
+        self.goto(change.offset);
+        Ok(())
+    }
+
+    // Writes the changes into the file being read; failures are shown, not fatal
+    fn save(&mut self) -> io::Result<()> {
+        let Some(path) = self.path.clone() else {
+            self.message = "no file to save to; W saves as".to_string();
+            return Ok(());
+        };
+        let count = self.data.edits().len();
+        match self.data.save_in_place(&path) {
+            Ok(()) => {
+                self.dirty = false;
+                self.message = format!("saved {} changed bytes to {}", count, path.display());
+            }
+            Err(e) => self.message = format!("can't save {}: {}", path.display(), e),
+        }
+        Ok(())
+    }
+
+    // Writes a copy of the file with its changes to 'path'
+    fn save_as(&mut self, path: &Path) -> io::Result<()> {
+        // Creating the file being read would empty it first
+        let same = |a: &Path, b: &Path| matches!((fs::canonicalize(a), fs::canonicalize(b)), (Ok(a), Ok(b)) if a == b);
+        if self.path.as_deref().is_some_and(|own| same(own, path)) {
+            return self.save();
+        }
+        match File::create(path).and_then(|file| self.data.save_to(file)) {
+            Ok(()) => {
+                self.dirty = false;
+                self.message = format!("wrote {} bytes to {}", self.data.len(), path.display());
+            }
+            Err(e) => self.message = format!("can't save {}: {}", path.display(), e),
+        }
+        Ok(())
+    }
+
+    // Moves to an offset typed at the goto prompt
+    fn goto_input(&mut self, input: &str) {
+        let input = input.trim();
+        let target = if let Some(rest) = input.strip_prefix('+') {
+            parse_size(rest).map(|n| self.cursor.saturating_add(n))
+        } else if let Some(rest) = input.strip_prefix('-') {
+            parse_size(rest).map(|n| self.cursor.saturating_sub(n))
+        } else {
+            parse_size(input)
+        };
+        match target {
+            Some(offset) if offset < self.data.len() => self.goto(offset),
+            Some(_) => self.message = format!("offset {} is past the end", input),
+            None => self.message = format!("invalid offset '{}'", input),
+        }
+    }
+
+    // Handles the bookmark name typed after 'm' or '\''
+    fn handle_mark(&mut self, pending: Pending, key: Key) {
+        let Key::Char(name @ 'a'..='z') = key else {
+            self.message = "bookmarks are named a to z".to_string();
+            return;
+        };
+        match pending {
+            Pending::SetMark => {
+                self.bookmarks.insert(name, self.cursor);
+                self.message = format!("mark '{}' set at {:08x}", name, self.cursor);
+            }
+            Pending::JumpToMark => match self.bookmarks.get(&name) {
+                Some(&offset) => self.goto(offset),
+                None => self.message = format!("no mark '{}'", name),
+            },
+        }
+    }
+
+    // Searches for a new pattern from the cursor
+    fn start_search(&mut self, pattern: Vec<u8>) -> io::Result<()> {
+        if pattern.is_empty() {
+            return Ok(());
+        }
+        self.search = Some(pattern);
+        self.search_next(true, false) // A match at the cursor counts
+    }
+
+    // Moves to the next or previous match of the last pattern, past any match at the cursor
+    // if 'skip_current' is set
+    fn search_next(&mut self, forward: bool, skip_current: bool) -> io::Result<()> {
+        let Some(pattern) = &self.search else {
+            self.message = "no previous search".to_string();
+            return Ok(());
+        };
+        let from = match forward && skip_current {
+            true => self.cursor + 1,
+            false => self.cursor,
+        };
+        match self.data.find(pattern, from, forward)? {
+            Some(offset) => {
+                self.found = Some(offset..offset + pattern.len() as u64);
+                self.goto(offset);
+            }
+            None => {
+                self.found = None;
+                self.message = "pattern not found".to_string();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs the viewer until it is closed, reading keys from `keys` and
+/// drawing on `screen`. Before each redraw `size` is asked for the
+/// terminal height, so resizes are picked up.
+pub fn run<R, K, W>(
+    viewer: &mut Viewer<R>,
+    mut keys: K,
+    mut screen: W,
+    mut size: impl FnMut() -> Option<usize>,
+) -> io::Result<()>
+where
+    R: Read + Seek,
+    K: Read,
+    W: Write,
+{
+    screen.write_all(ENTER)?;
+    let mut session = || -> io::Result<()> {
+        let mut buf = [0u8; 64];
+        loop {
+            if let Some(rows) = size() {
+                viewer.resize(rows);
+            }
+            viewer.render(&mut screen)?;
+            let n = keys.read(&mut buf)?;
+            if n == 0 {
+                return Ok(()); // No more keys
+            }
+            for key in decode_keys(&buf[..n]) {
+                if !viewer.handle(key)? {
+                    return Ok(());
+                }
+            }
+        }
+    };
+    let result = session();
+    screen.write_all(LEAVE)?;
+    screen.flush()?;
+    result
+}
+
+// Function to show the values starting with 'bytes' in one byte order
+fn decode(bytes: &[u8], little: bool) -> String {
+    // Reads 'N' bytes as an array in the chosen order, if there are enough
+    fn take<const N: usize>(bytes: &[u8], little: bool) -> Option<[u8; N]> {
+        let mut array: [u8; N] = bytes.get(..N)?.try_into().ok()?;
+        if !little {
+            array.reverse();
+        }
+        Some(array)
+    }
+    // Formats a value, or '-' when the file ends too soon
+    fn show<T: std::fmt::Display>(value: Option<T>) -> String {
+        value.map_or("-".to_string(), |value| value.to_string())
+    }
+
+    // Formats a float, switching to an exponent when it is very large or small
+    fn float<T>(value: Option<T>) -> String
+    where
+        T: Copy + std::fmt::Display + std::fmt::LowerExp + Into<f64>,
+    {
+        match value {
+            Some(v) => {
+                let abs = v.into().abs();
+                if abs != 0.0 && abs.is_finite() && !(1e-4..1e16).contains(&abs) {
+                    format!("{:e}", v)
+                } else {
+                    v.to_string()
+                }
+            }
+            None => "-".to_string(),
+        }
+    }
+
+    let values = [
+        ("u8", show(bytes.first())),
+        ("u16", show(take(bytes, little).map(u16::from_le_bytes))),
+        ("u32", show(take(bytes, little).map(u32::from_le_bytes))),
+        ("u64", show(take(bytes, little).map(u64::from_le_bytes))),
+        ("f32", float(take(bytes, little).map(f32::from_le_bytes))),
+        ("f64", float(take(bytes, little).map(f64::from_le_bytes))),
+    ];
+    let mut line = String::from(if little { "le" } else { "be" });
+    for (name, value) in values {
+        line.push_str(&format!("  {} {}", name, value));
+    }
+    line
+}
+
+// Function to parse hex bytes typed at the search prompt, ignoring blanks
+fn parse_hex(input: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = input.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // Helper to open a viewer on 'bytes' with a terminal of 'rows' lines
+    fn viewer(bytes: Vec<u8>, rows: usize) -> Viewer<Cursor<Vec<u8>>> {
+        let mut viewer = Viewer::new(Cursor::new(bytes), None).unwrap();
+        viewer.resize(rows);
+        viewer
+    }
+
+    // Helper to press the keys sent as 'bytes'
+    fn press(viewer: &mut Viewer<Cursor<Vec<u8>>>, bytes: &[u8]) {
+        for key in decode_keys(bytes) {
+            viewer.handle(key).unwrap();
+        }
+    }
+
+    // Helper to render the screen and return it as text
+    fn screen(viewer: &mut Viewer<Cursor<Vec<u8>>>) -> String {
+        let mut out = Vec::new();
+        viewer.render(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_pages_read_and_find() {
+        // Reads and searches cross page boundaries
+        let mut bytes = vec![0u8; PAGE_SIZE * 2 + 10];
+        bytes[PAGE_SIZE - 2..PAGE_SIZE + 2].copy_from_slice(b"abcd");
+        bytes[10..14].copy_from_slice(b"abcd");
+        let mut pages = Pages::new(Cursor::new(bytes)).unwrap();
+        let mut buf = [0u8; 6];
+        assert_eq!(pages.read_at(PAGE_SIZE as u64 - 3, &mut buf).unwrap(), 6);
+        assert_eq!(&buf, b"\0abcd\0");
+        assert_eq!(pages.read_at(pages.len() - 2, &mut buf).unwrap(), 2);
+        assert_eq!(pages.find(b"abcd", 10, true).unwrap(), Some(10));
+        assert_eq!(
+            pages.find(b"abcd", 11, true).unwrap(),
+            Some(PAGE_SIZE as u64 - 2)
+        );
+        assert_eq!(
+            pages.find(b"abcd", PAGE_SIZE as u64, false).unwrap(),
+            Some(PAGE_SIZE as u64 - 2)
+        );
+        assert_eq!(
+            pages.find(b"abcd", PAGE_SIZE as u64 - 2, false).unwrap(),
+            Some(10)
+        );
+        assert_eq!(pages.find(b"abcd", 10, false).unwrap(), None);
+        assert_eq!(pages.find(b"zz", 0, true).unwrap(), None);
+    }
+
+    #[test]
+    fn test_navigation() {
+        // Cursor keys, paging and goto keep the cursor on screen and in the file
+        let mut viewer = viewer(vec![0; 1000], 8); // Five lines of 16 bytes
+        press(&mut viewer, b"\x1b[B\x1b[Cl");
+        assert_eq!(viewer.cursor(), 18);
+        press(&mut viewer, b" ");
+        assert_eq!((viewer.cursor(), viewer.top), (98, 80));
+        press(&mut viewer, b"G");
+        assert_eq!((viewer.cursor(), viewer.top), (999, 928));
+        press(&mut viewer, b"\x1b[5~g");
+        assert_eq!((viewer.cursor(), viewer.top), (0, 0));
+        press(&mut viewer, b":0x100\r:+16\r");
+        assert_eq!(viewer.cursor(), 272);
+        press(&mut viewer, b":5000\r");
+        assert_eq!(viewer.cursor(), 272);
+        assert_eq!(viewer.message, "offset 5000 is past the end");
+    }
+
+    #[test]
+    fn test_search_and_bookmarks() {
+        // Text and hex searches move between matches; bookmarks jump back
+        let mut bytes = vec![0u8; 100];
+        bytes[20..23].copy_from_slice(b"key");
+        bytes[70..73].copy_from_slice(b"key");
+        let mut viewer = viewer(bytes, 24);
+        press(&mut viewer, b"ma/key\r");
+        assert_eq!(viewer.cursor(), 20);
+        press(&mut viewer, b"n");
+        assert_eq!(viewer.cursor(), 70);
+        press(&mut viewer, b"n");
+        assert_eq!(viewer.message, "pattern not found");
+        press(&mut viewer, b"gx6b 65\r");
+        assert_eq!(viewer.cursor(), 20);
+        press(&mut viewer, b"'a");
+        assert_eq!(viewer.cursor(), 0);
+        press(&mut viewer, b"'b");
+        assert_eq!(viewer.message, "no mark 'b'");
+        press(&mut viewer, b"xzz\r");
+        assert_eq!(viewer.message, "invalid hex bytes 'zz'");
+    }
+
+    #[test]
+    fn test_search_match_at_cursor() {
+        // A new search finds a match under the cursor, while 'n' moves past it
+        let mut bytes = vec![0u8; 64];
+        bytes[..4].copy_from_slice(b"\x7fELF");
+        bytes[32..36].copy_from_slice(b"\x7fELF");
+        let mut viewer = viewer(bytes, 24);
+        press(&mut viewer, b"x7f454c46\r");
+        assert_eq!(viewer.cursor(), 0);
+        assert_eq!(viewer.found, Some(0..4));
+        press(&mut viewer, b"n");
+        assert_eq!(viewer.cursor(), 32);
+        press(&mut viewer, b"x7f454c46\r");
+        assert_eq!(viewer.cursor(), 32);
+        press(&mut viewer, b"N");
+        assert_eq!(viewer.cursor(), 0);
+    }
+
+    #[test]
+    fn test_edit_undo_redo() {
+        // Hex digits fill a byte in two halves, ASCII one byte per key; undo goes a byte at a time
+        let mut viewer = viewer(b"abcdef".to_vec(), 24);
+        press(&mut viewer, b"i4");
+        assert_eq!(viewer.byte_at(0).unwrap(), 0x41);
+        press(&mut viewer, b"2\tXY\x1b");
+        assert_eq!(
+            viewer.data.edits(),
+            &BTreeMap::from([(0, 0x42), (1, b'X'), (2, b'Y')])
+        );
+        assert_eq!(viewer.cursor(), 3);
+        assert!(viewer.is_modified());
+        press(&mut viewer, b"uu");
+        assert_eq!(viewer.data.edits(), &BTreeMap::from([(0, 0x42)]));
+        assert_eq!(viewer.cursor(), 1);
+        press(&mut viewer, b"\x12");
+        assert_eq!(viewer.byte_at(1).unwrap(), b'X');
+
+        // Typing a byte's own value leaves no change behind
+        press(&mut viewer, b"gi61\x1b");
+        assert_eq!(viewer.data.edits(), &BTreeMap::from([(1, b'X')]));
+        press(&mut viewer, b"iz");
+        assert_eq!(viewer.message, "'z' is not a hex digit");
+    }
+
+    #[test]
+    fn test_unsaved_changes_and_saving() {
+        // Quitting asks for a save first; saving in place writes just the changes
+        let dir = std::env::temp_dir().join(format!("hexdump-viewer-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("blob.bin");
+        fs::write(&path, b"0123456789").unwrap();
+        let file = File::open(&path).unwrap();
+        let mut viewer = Viewer::new(file, None).unwrap().with_path(&path);
+        viewer.resize(24);
+        for key in decode_keys(b"i\tAB\x1bq") {
+            assert!(viewer.handle(key).unwrap());
+        }
+        assert!(viewer.message.starts_with("unsaved changes"));
+
+        let copy = dir.join("copy.bin");
+        for key in decode_keys(format!("W{}\r", copy.display()).as_bytes()) {
+            viewer.handle(key).unwrap();
+        }
+        assert_eq!(fs::read(&copy).unwrap(), b"AB23456789");
+        assert_eq!(fs::read(&path).unwrap(), b"0123456789");
+        assert!(!viewer.is_modified());
+
+        for key in decode_keys(b"\x1b[Ci\t!\x1bw") {
+            viewer.handle(key).unwrap();
+        }
+        assert_eq!(fs::read(&path).unwrap(), b"AB2!456789");
+        assert!(viewer.data.edits().is_empty());
+        assert!(!viewer.handle(Key::Char('q')).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_render() {
+        // Lines use the canonical layout with the cursor highlighted, then values and status
+        let mut viewer = viewer(b"\x01\x02\x03\x04\x05\x06\x07\x08ABCDEFGHij".to_vec(), 6);
+        press(&mut viewer, b"l");
+        let screen = screen(&mut viewer);
+        let lines: Vec<&str> = screen.split("\x1b[K").collect();
+        assert_eq!(
+            lines[0],
+            "\x1b[H\x1b[1;1H00000000  01 \x1b[7m02\x1b[0m 03 04 05 06 07 08  \
+             41 42 43 44 45 46 47 48  |.\x1b[7m.\x1b[0m......ABCDEFGH|"
+        );
+        assert_eq!(
+            lines[3],
+            "\x1b[4;1Hle  u8 2  u16 770  u32 84148994  u64 4686003134714348290  \
+             f32 6.2071626e-36  f64 196832.75244905805"
+        );
+        assert!(lines[4].starts_with("\x1b[5;1Hbe  u8 2  u16 515  u32 33752069"));
+        assert_eq!(
+            lines[5],
+            "\x1b[6;1H\x1b[7m00000001 (1) of 00000012  ? help\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_run_session() {
+        // The session enters and leaves the alternate screen and stops on 'q'
+        let mut viewer = viewer(vec![1, 2, 3], 24);
+        let mut out = Vec::new();
+        run(&mut viewer, &b"lq"[..], &mut out, || Some(10)).unwrap();
+        assert!(out.starts_with(ENTER));
+        assert!(out.ends_with(LEAVE));
+        assert_eq!(viewer.cursor(), 1);
+        assert_eq!(viewer.rows, 10);
+    }
+}
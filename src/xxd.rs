@@ -0,0 +1,162 @@
+//! `xxd -i` style C include output.
+//!
+//! The hex layouts of `xxd` are plain formats (see [`Format::xxd`] and
+//! [`Format::xxd_plain`]); this module covers the include output, which
+//! needs a header and a footer around the data.
+//!
+//! [`Format::xxd`]: crate::Format::xxd
+//! [`Format::xxd_plain`]: crate::Format::xxd_plain
+
+use std::io::{self, Read, Write}; // I/O operations
+
+use crate::export::{self, Emitter}; // Whole-input output
+
+/// Settings for `xxd -i` output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Include {
+    /// Variable name, usually derived from the input file name. Without a
+    /// name only the array elements are written, as `xxd -i` does for stdin.
+    pub name: Option<String>,
+    /// Number of bytes per line.
+    pub cols: usize,
+    /// Print hex digits in uppercase (`0XAB`).
+    pub upper: bool,
+}
+
+impl Default for Include {
+    fn default() -> Self {
+        Include {
+            name: None,
+            cols: 12,
+            upper: false,
+        }
+    }
+}
+
+impl Include {
+    /// Settings named after `path` the way `xxd` does it: every character
+    /// that isn't a letter or digit becomes `_`, and a leading digit gets a
+    /// `__` prefix.
+    pub fn for_path(path: &str) -> Self {
+        let mut name: String = path
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if name.starts_with(|c: char| c.is_ascii_digit()) {
+            name.insert_str(0, "__");
+        }
+        Include {
+            name: Some(name),
+            ..Include::default()
+        }
+    }
+
+    /// Streams `reader` to `writer` as the body of a C array.
+    ///
+    /// ```
+    /// let include = hexdump::xxd::Include::for_path("x.bin");
+    /// let mut out = Vec::new();
+    /// include.write(&b"abc"[..], &mut out).unwrap();
+    /// let expected = "\
+    /// unsigned char x_bin[] = {
+    ///   0x61, 0x62, 0x63
+    /// };
+    /// unsigned int x_bin_len = 3;
+    /// ";
+    /// assert_eq!(String::from_utf8(out).unwrap(), expected);
+    /// ```
+    pub fn write<R: Read, W: Write>(&self, reader: R, writer: W) -> io::Result<()> {
+        export::emit(self, reader, writer).map(|_| ())
+    }
+}
+
+impl Emitter for Include {
+    fn begin(&self, writer: &mut dyn Write) -> io::Result<()> {
+        match &self.name {
+            Some(name) => writeln!(writer, "unsigned char {}[] = {{", name),
+            None => Ok(()),
+        }
+    }
+
+    fn bytes(&self, writer: &mut dyn Write, offset: u64, bytes: &[u8]) -> io::Result<()> {
+        let cols = self.cols.max(1) as u64;
+        let mut out = String::new();
+        for (count, &b) in (offset..).zip(bytes) {
+            // The separator goes before each element, so the last one has no comma
+            out.push_str(match count {
+                0 => "  ",
+                c if c % cols == 0 => ",\n  ",
+                _ => ", ",
+            });
+            if self.upper {
+                out.push_str(&format!("0X{:02X}", b));
+            } else {
+                out.push_str(&format!("0x{:02x}", b));
+            }
+        }
+        writer.write_all(out.as_bytes())
+    }
+
+    fn end(&self, writer: &mut dyn Write, total: u64) -> io::Result<()> {
+        if total > 0 {
+            writeln!(writer)?;
+        }
+        match &self.name {
+            Some(name) => writeln!(writer, "}};\nunsigned int {}_len = {};", name, total),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Helper to run an include dump into memory
+    fn include(settings: &Include, input: &[u8]) -> String {
+        let mut out = Vec::new();
+        settings.write(input, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_include_golden() {
+        // Golden output of `xxd -i x.bin`
+        let expected = "\
+unsigned char x_bin[] = {
+  0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x2c, 0x20, 0x77, 0x6f, 0x72, 0x6c, 0x64,
+  0x21, 0x20, 0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39,
+  0x00, 0xff
+};
+unsigned int x_bin_len = 26;
+";
+        let input = b"Hello, world! 0123456789\x00\xff";
+        assert_eq!(include(&Include::for_path("x.bin"), input), expected);
+    }
+
+    #[test]
+    fn test_include_uppercase_columns() {
+        // Golden output of `xxd -i -u -c 4` on stdin
+        let settings = Include {
+            cols: 4,
+            upper: true,
+            ..Include::default()
+        };
+        assert_eq!(
+            include(&settings, b"Hello"),
+            "  0X48, 0X65, 0X6C, 0X6C,\n  0X6F\n"
+        );
+    }
+
+    #[test]
+    fn test_include_names_and_empty_input() {
+        // Names are sanitized like xxd, and empty input still gets the declarations
+        assert_eq!(
+            Include::for_path("1-x.bin").name.as_deref(),
+            Some("__1_x_bin")
+        );
+        let expected = "unsigned char e[] = {\n};\nunsigned int e_len = 0;\n";
+        assert_eq!(include(&Include::for_path("e"), b""), expected);
+        assert_eq!(include(&Include::default(), b""), "");
+    }
+}
@@ -0,0 +1,178 @@
+// Integration tests driving the public library API
+use hexdump::{hexdump, Config, HexDumper, Mode, OffsetBase};
+use std::io::Cursor;
+
+#[test]
+fn test_hexdump_into_vec() {
+    // The free function dumps the whole input with default settings
+    let mut output = Vec::new();
+    hexdump(Cursor::new(b"ABCDEFGHIJKLMNOPQR".to_vec()), &mut output).unwrap();
+    let expected = "\
+        00000000 4241 4443 4645 4847 4a49 4c4b 4e4d 504f\n\
+        00000010 5251\n";
+    assert_eq!(String::from_utf8(output).unwrap(), expected);
+}
+
+#[test]
+fn test_config_dump_with_length() {
+    // A configured length stops the dump early
+    let config = Config {
+        length: Some(2),
+        ..Config::default()
+    };
+    let mut output = Vec::new();
+    config.dump(&b"ABCD"[..], &mut output).unwrap();
+    assert_eq!(output, b"00000000 4241\n");
+}
+
+#[test]
+fn test_config_dump_canonical() {
+    // Canonical mode matches `hexdump -C` including the trailing length line
+    let config = Config {
+        format: Mode::Canonical.format(),
+        ..Config::default()
+    };
+    let mut output = Vec::new();
+    config.dump(&b"hello, world\n"[..], &mut output).unwrap();
+    let expected = "\
+00000000  68 65 6c 6c 6f 2c 20 77  6f 72 6c 64 0a           |hello, world.|
+0000000d
+";
+    assert_eq!(String::from_utf8(output).unwrap(), expected);
+}
+
+#[test]
+fn test_error_reports_io_failure() {
+    // Write failures surface as the crate's error type
+    struct FailingWriter;
+    impl std::io::Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk full"))
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let err = hexdump(&b"AB"[..], FailingWriter).unwrap_err();
+    assert!(matches!(err, hexdump::Error::Io(_)));
+    assert_eq!(err.to_string(), "disk full");
+}
+
+#[test]
+fn test_concat_continuous_offsets() {
+    // Several files are dumped as one stream, skipping files that can't be opened
+    use hexdump::input::{Concat, Source};
+    let dir = std::env::temp_dir().join(format!("hexdump-concat-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.bin"), b"0123456789").unwrap();
+    std::fs::write(dir.join("b.bin"), b"ABCDEFGHIJ").unwrap();
+
+    let sources = vec![
+        Source::File(dir.join("a.bin")),
+        Source::File(dir.join("missing.bin")),
+        Source::File(dir.join("b.bin")),
+    ];
+    let mut failed = Vec::new();
+    let mut input = Concat::new(sources, |source, _| failed.push(source.clone()));
+    let config = Config {
+        format: Mode::Canonical.format(),
+        ..Config::default()
+    };
+    let mut output = Vec::new();
+    config.dump(&mut input, &mut output).unwrap();
+    assert_eq!(input.failures(), 1);
+    drop(input);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let expected = "\
+00000000  30 31 32 33 34 35 36 37  38 39 41 42 43 44 45 46  |0123456789ABCDEF|
+00000010  47 48 49 4a                                       |GHIJ|
+00000014
+";
+    assert_eq!(String::from_utf8(output).unwrap(), expected);
+    assert_eq!(failed, vec![Source::File(dir.join("missing.bin"))]);
+}
+
+#[test]
+fn test_concat_read_error_skips_source() {
+    // A source that opens but can't be read is reported and the next one is still read
+    use hexdump::input::{Concat, Source};
+    use std::io::Read;
+    let dir = std::env::temp_dir().join(format!("hexdump-unreadable-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("a.bin"), b"0123").unwrap();
+    std::fs::write(dir.join("b.bin"), b"ABCD").unwrap();
+
+    let sources = vec![
+        Source::File(dir.join("a.bin")),
+        Source::File(dir.join("sub")), // Directories open, but reading them fails
+        Source::File(dir.join("b.bin")),
+    ];
+    let mut failed = Vec::new();
+    let mut input = Concat::new(sources, |source, _| failed.push(source.clone()));
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes).unwrap();
+    assert_eq!(input.failures(), 1);
+    drop(input);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(bytes, b"0123ABCD");
+    assert_eq!(failed, vec![Source::File(dir.join("sub"))]);
+}
+
+#[test]
+fn test_concat_skip_across_files() {
+    // Skipping seeks through whole files and into the next one
+    use hexdump::input::{Concat, Source};
+    let dir = std::env::temp_dir().join(format!("hexdump-skip-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.bin"), b"0123456789").unwrap();
+    std::fs::write(dir.join("b.bin"), b"ABCDEFGHIJ").unwrap();
+
+    let sources = vec![
+        Source::File(dir.join("a.bin")),
+        Source::File(dir.join("b.bin")),
+    ];
+    let mut input = Concat::new(sources, |_, _| {});
+    assert_eq!(input.skip(12).unwrap(), 12);
+    let config = Config {
+        skip: 12,
+        length: Some(4),
+        format: Mode::Canonical.format(),
+        ..Config::default()
+    };
+    let mut output = Vec::new();
+    config.dump_positioned(&mut input, &mut output).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let expected = "\
+0000000c  43 44 45 46                                       |CDEF|
+00000010
+";
+    assert_eq!(String::from_utf8(output).unwrap(), expected);
+}
+
+#[test]
+fn test_hex_dumper_builder() {
+    // The builder writes to any io::Write and formats through Display
+    let hex = HexDumper::new()
+        .width(8)
+        .group(1)
+        .ascii(true)
+        .offset_base(OffsetBase::Decimal)
+        .end_offset(true);
+    let mut output = Vec::new();
+    hex.dump(&b"builder!"[..], &mut output).unwrap();
+    let expected = "00000000 62 75 69 6c 64 65 72 21  |builder!|\n00000008\n";
+    assert_eq!(String::from_utf8(output).unwrap(), expected);
+    let hex = hex.build().unwrap();
+    assert_eq!(format!("{}", hex.display(b"builder!")), expected);
+
+    // The default settings match the default dump, odd lengths included
+    let mut expected = Vec::new();
+    hexdump(&b"abc"[..], &mut expected).unwrap();
+    assert_eq!(
+        format!("{}", HexDumper::new().build().unwrap().display(b"abc")).as_bytes(),
+        expected
+    );
+}
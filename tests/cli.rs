@@ -0,0 +1,28 @@
+// Tests running the binary for behavior the library can't show
+use std::io::Read;
+use std::process::{Command, Stdio};
+
+#[test]
+fn test_closed_output() {
+    // A reader that stops early, as in 'hexdump FILE | head', ends the dump quietly
+    let dir = std::env::temp_dir().join(format!("hexdump-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("counter");
+    let input: Vec<u8> = (0u32..1 << 18).flat_map(u32::to_le_bytes).collect();
+    std::fs::write(&path, input).unwrap();
+
+    for args in [&["-C"][..], &[], &["--xxd"], &["--od", "-tx1"]] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_hexdump"))
+            .args(args)
+            .arg(&path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = [0; 8];
+        child.stdout.take().unwrap().read_exact(&mut line).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "", "{:?}", args);
+        assert!(output.status.success(), "{:?}", args);
+    }
+}
@@ -0,0 +1,161 @@
+// Snapshot tests for colored output; run with UPDATE_SNAPSHOTS=1 to rewrite the snapshots
+use hexdump::{od, Config, Format, Mode, Theme};
+use std::path::PathBuf;
+use std::process::Command;
+
+// Input with every class of byte, a repeated line and a partial last line
+fn sample() -> Vec<u8> {
+    let mut input = b"\x00\x00Hi there\n\t\x1b\x7f\xff\xff\x80\xfe".to_vec();
+    input.extend_from_slice(&[0; 48]);
+    input.extend_from_slice(b"end\r\n");
+    input
+}
+
+// Helper to compare output with a snapshot file, showing escapes readably on mismatch
+fn assert_snapshot(name: &str, output: &[u8]) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{}.txt", name));
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, output).unwrap();
+        return;
+    }
+    let expected = std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    assert_eq!(
+        String::from_utf8_lossy(output).escape_debug().to_string(),
+        String::from_utf8_lossy(&expected)
+            .escape_debug()
+            .to_string(),
+        "snapshot {} differs",
+        name
+    );
+}
+
+// Helper to dump the sample with a format and the default theme
+fn dump(format: Format) -> Vec<u8> {
+    let config = Config {
+        format,
+        theme: Some(Theme::default()),
+        ..Config::default()
+    };
+    let mut output = Vec::new();
+    config.dump(&sample()[..], &mut output).unwrap();
+    output
+}
+
+#[test]
+fn test_color_default() {
+    // Two-byte words are colored only when both bytes share a class
+    assert_snapshot("default", &dump(Format::default()));
+}
+
+#[test]
+fn test_color_canonical() {
+    // Hex and ASCII columns are colored per byte, offsets in their own color
+    assert_snapshot("canonical", &dump(Mode::Canonical.format()));
+}
+
+#[test]
+fn test_color_xxd() {
+    // xxd layout, including the partial last line
+    assert_snapshot("xxd", &dump(Format::xxd(16, 2, false).unwrap()));
+}
+
+#[test]
+fn test_color_od() {
+    // od layout with a character trailer and the end offset
+    let types = od::Type::parse_list("x1z").unwrap();
+    let format = Format::od(od::Address::Hex, &types, None).unwrap();
+    assert_snapshot("od", &dump(format));
+}
+
+#[test]
+fn test_color_custom_theme() {
+    // Themes replace some colors and can turn others off
+    let config = Config {
+        format: Mode::Canonical.format(),
+        theme: Some(Theme::parse("offset=:nul=1;30:print=4")),
+        ..Config::default()
+    };
+    let mut output = Vec::new();
+    config.dump(&b"\x00A\x00B"[..], &mut output).unwrap();
+    assert_snapshot("custom_theme", &output);
+}
+
+// Helper to run the binary with arguments and environment on the sample
+fn run(args: &[&str], env: &[(&str, &str)]) -> Vec<u8> {
+    let dir = std::env::temp_dir().join(format!("hexdump-color-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(args.join("_").replace(['-', '='], ""));
+    std::fs::write(&path, sample()).unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_hexdump"));
+    command
+        .args(args)
+        .arg(&path)
+        .env_remove("NO_COLOR")
+        .env_remove("HEXDUMP_COLORS");
+    command.envs(env.iter().copied());
+    let output = command.output().unwrap();
+    assert!(output.status.success());
+    output.stdout
+}
+
+#[test]
+fn test_color_option() {
+    // '--color' forces colors past NO_COLOR, and output to a pipe stays plain by default
+    let plain = run(&["-C"], &[]);
+    assert!(!plain.contains(&0x1b));
+    assert_eq!(run(&["-C", "--color=never"], &[]), plain);
+    assert_eq!(
+        run(&["-C", "--color"], &[("NO_COLOR", "1")]),
+        dump(Mode::Canonical.format())
+    );
+    assert_eq!(run(&["-CL", "--color=auto"], &[]), plain);
+
+    // The theme comes from HEXDUMP_COLORS
+    let themed = run(&["-C", "--color=always"], &[("HEXDUMP_COLORS", "nul=7")]);
+    assert!(themed.windows(6).any(|w| w == b"\x1b[7m00"));
+    assert_snapshot("option_xxd", &run(&["--xxd", "-R", "always"], &[]));
+}
+
+#[test]
+fn test_color_mark_strings() {
+    // Bytes of strings are underlined on top of their colors, and squeezing still works
+    let config = Config {
+        format: Mode::Canonical.format(),
+        theme: Some(Theme::default()),
+        mark_strings: Some(4),
+        ..Config::default()
+    };
+    let mut output = Vec::new();
+    config.dump(&sample()[..], &mut output).unwrap();
+    assert_snapshot("mark_strings", &output);
+
+    // Without colors, the dump is unchanged
+    let config = Config {
+        theme: None,
+        ..config
+    };
+    let mut output = Vec::new();
+    config.dump(&sample()[..], &mut output).unwrap();
+    let plain = Config {
+        format: Mode::Canonical.format(),
+        ..Config::default()
+    };
+    let mut expected = Vec::new();
+    plain.dump(&sample()[..], &mut expected).unwrap();
+    assert_eq!(output, expected);
+
+    // The program warns that a plain dump can't show them
+    let output = Command::new(env!("CARGO_BIN_EXE_hexdump"))
+        .args(["-C", "--mark-strings", "--color=never", "/dev/null"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--mark-strings needs colored output"));
+    let output = Command::new(env!("CARGO_BIN_EXE_hexdump"))
+        .args(["-C", "--mark-strings", "--color=always", "/dev/null"])
+        .output()
+        .unwrap();
+    assert!(output.stderr.is_empty());
+}